@@ -0,0 +1,60 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Venue independent error returned by the unified interfaces. Venue error
+/// codes are mapped to the variant describing their cause, codes without a
+/// dedicated variant are kept in `Api`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    RateLimited(String),
+    InsufficientBalance(String),
+    OrderNotFound(String),
+    InvalidQuantity(String),
+    InvalidPrice(String),
+    AuthFailed(String),
+    Timeout(String),
+    Network(String),
+    /// Parameters rejected locally or by the venue.
+    InvalidParams(String),
+    /// Venue response that could not be parsed.
+    Deserialize(String),
+    /// A stream update does not follow the last applied one, the local state
+    /// has to be rebuilt from a new snapshot.
+    SequenceGap {
+        expected: i64,
+        received: i64,
+    },
+    Api {
+        code: i64,
+        msg: String,
+    },
+    Unknown(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RateLimited(msg) => write!(f, "rate limited: {msg}"),
+            Error::InsufficientBalance(msg) => write!(f, "insufficient balance: {msg}"),
+            Error::OrderNotFound(msg) => write!(f, "order not found: {msg}"),
+            Error::InvalidQuantity(msg) => write!(f, "invalid quantity: {msg}"),
+            Error::InvalidPrice(msg) => write!(f, "invalid price: {msg}"),
+            Error::AuthFailed(msg) => write!(f, "authentication failed: {msg}"),
+            Error::Timeout(msg) => write!(f, "timeout: {msg}"),
+            Error::Network(msg) => write!(f, "network error: {msg}"),
+            Error::InvalidParams(msg) => write!(f, "invalid params: {msg}"),
+            Error::Deserialize(msg) => write!(f, "deserialize error: {msg}"),
+            Error::SequenceGap { expected, received } => {
+                write!(
+                    f,
+                    "sequence gap: expected update {expected}, received {received}"
+                )
+            }
+            Error::Api { code, msg } => write!(f, "api error {code}: {msg}"),
+            Error::Unknown(msg) => write!(f, "unknown error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
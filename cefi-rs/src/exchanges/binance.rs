@@ -1,108 +1,1836 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
 use crate::{
+    errors::{Error, Result},
     interface_http::InterfaceHttp,
+    interface_ws::{InterfaceWs, Subscription, WsEvent},
+    orderbook::{LocalOrderbook, OrderbookDelta},
     trade::*,
-    types::{Orderbook, OrderbookLevel},
+    types::{
+        parse_number, Balance, ContractType, Fill, Instrument, Order, OrderStatus, Orderbook,
+        OrderbookLevel, Position, Side, Ticker, Trade,
+    },
 };
 use async_trait::async_trait;
-use cefi_rs_binance::{http::BinanceHttp, types::OrderBook as BinanceOrderBook};
+use cefi_rs_binance::{
+    config::ClientConfig,
+    errors::BinanceError,
+    http::BinanceHttp,
+    types::{
+        BinanceBalance, BinanceOrder, BinanceOrderRef, BinanceOrderStatus, BinanceOrderType,
+        BinancePositionRisk, BinancePositionSide, BinanceSide, BinanceTimeInForce, Filter,
+        ModifyOrderRequest, NewOrderRequest, OrderBook as BinanceOrderBook, Symbol,
+    },
+    ws::BinanceWs,
+    ws_types::{
+        AggTrade, BalanceUpdate, BinanceMarketEvent, BinanceStream, BinanceUserEvent,
+        BinanceUserWsEvent, BinanceWsEvent, BinanceWsMessage, BookTicker, ConnectionState,
+        DepthUpdate, MarkPriceUpdate, OrderTradeUpdate, PositionUpdate,
+    },
+    ws_user::BinanceUserWs,
+};
+use tokio::{sync::mpsc, time::Instant};
+use tracing::warn;
+
+/// Depth of the REST snapshot used to initialise a `BinanceDepthSync`.
+const DEPTH_SNAPSHOT_LIMIT: i32 = 1000;
+/// Delay before fetching another snapshot after a stale or failed one,
+/// doubled on every failure.
+const SNAPSHOT_MIN_BACKOFF: Duration = Duration::from_millis(250);
+const SNAPSHOT_MAX_BACKOFF: Duration = Duration::from_secs(10);
 
 pub struct BinanceHttpWrapper {
     client: BinanceHttp,
 }
 
 impl BinanceHttpWrapper {
-    pub fn new(api_key: String, api_secret: String) -> Self {
-        Self {
-            client: BinanceHttp::new(api_key, api_secret),
+    pub fn new(api_key: String, api_secret: String) -> Result<Self> {
+        Ok(Self {
+            client: BinanceHttp::new(api_key, api_secret)?,
+        })
+    }
+
+    /// Environment, urls, timeout, proxy and user agent from `config`.
+    pub fn with_config(api_key: String, api_secret: String, config: &ClientConfig) -> Result<Self> {
+        Ok(Self {
+            client: BinanceHttp::with_config(api_key, api_secret, config)?,
+        })
+    }
+
+    pub fn from_client(client: BinanceHttp) -> Self {
+        Self { client }
+    }
+}
+
+fn parse_order_id(order_id: &str) -> Result<BinanceOrderRef> {
+    let order_id = order_id
+        .parse::<i64>()
+        .map_err(|e| Error::InvalidParams(format!("invalid binance order id {order_id}: {e}")))?;
+    Ok(BinanceOrderRef::OrderId(order_id))
+}
+
+/// Translates unified order params into a Binance `NewOrderRequest`, rejecting
+/// combinations the futures api cannot express.
+fn new_order_request(params: &PlaceOrderParams) -> Result<NewOrderRequest> {
+    params.validate()?;
+
+    let side = if params.is_buy {
+        BinanceSide::Buy
+    } else {
+        BinanceSide::Sell
+    };
+    let order_type = match params.order_type {
+        OrderType::Market => BinanceOrderType::Market,
+        OrderType::Limit => BinanceOrderType::Limit,
+        OrderType::StopMarket => BinanceOrderType::StopMarket,
+        OrderType::StopLimit => BinanceOrderType::Stop,
+        OrderType::TakeProfitMarket => BinanceOrderType::TakeProfitMarket,
+        OrderType::TakeProfitLimit => BinanceOrderType::TakeProfit,
+    };
+    let position_side = match params.position_idx {
+        PositionIdx::OneWay => BinancePositionSide::Both,
+        PositionIdx::HedgeLong => BinancePositionSide::Long,
+        PositionIdx::HedgeShort => BinancePositionSide::Short,
+    };
+
+    let mut request = NewOrderRequest::new(&params.symbol, side, order_type);
+    if params.position_idx != PositionIdx::OneWay {
+        if params.reduce_only {
+            return Err(Error::InvalidParams(
+                "binance does not accept reduce only orders in hedge mode".to_string(),
+            ));
         }
+        request = request.position_side(position_side);
     }
+
+    if params.close_on_trigger {
+        if params.order_type.is_limit() {
+            return Err(Error::InvalidParams(
+                "binance only supports close on trigger for market stop and take profit orders"
+                    .to_string(),
+            ));
+        }
+        if params.reduce_only {
+            return Err(Error::InvalidParams(
+                "binance does not accept reduce only together with close on trigger".to_string(),
+            ));
+        }
+        request = request.close_position(true);
+    } else {
+        request = request.quantity(&params.amount.to_string());
+        if params.reduce_only {
+            request = request.reduce_only(true);
+        }
+    }
+
+    if params.order_type.is_limit() {
+        let time_in_force = match params.time_in_force {
+            TimeInForce::Gtc => BinanceTimeInForce::Gtc,
+            TimeInForce::Ioc => BinanceTimeInForce::Ioc,
+            TimeInForce::Fok => BinanceTimeInForce::Fok,
+            TimeInForce::PostOnly => BinanceTimeInForce::Gtx,
+        };
+        if let Some(price) = params.price {
+            request = request
+                .price(&price.to_string())
+                .time_in_force(time_in_force);
+        }
+    }
+    if let Some(trigger_price) = params.trigger_price {
+        request = request.stop_price(&trigger_price.to_string());
+    }
+    if let Some(client_order_id) = &params.client_order_id {
+        request = request.new_client_order_id(client_order_id);
+    }
+
+    Ok(request)
 }
 
 #[async_trait]
 impl InterfaceHttp for BinanceHttpWrapper {
-    async fn get_server_time(&self) -> anyhow::Result<u64> {
-        let server_time = self
-            .client
-            .check_server_time()
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    async fn get_server_time(&self) -> Result<u64> {
+        let server_time = self.client.check_server_time().await?;
         Ok(server_time.server_time)
     }
 
-    async fn get_orderbook(
-        &self,
-        symbol: &String,
-        limit: Option<i32>,
-    ) -> anyhow::Result<Orderbook> {
-        let orderbook = self
-            .client
-            .get_orderbook(symbol, limit)
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    async fn get_orderbook(&self, symbol: &str, limit: Option<i32>) -> Result<Orderbook> {
+        let orderbook = self.client.get_orderbook(symbol, limit).await?;
 
-        todo!()
-        // Ok(orderbook)
+        Orderbook::from_binance_orderbook(orderbook, symbol.to_string())
     }
 
-    async fn place_order(&self, params: &PlaceOrderParams) -> anyhow::Result<PlaceOrderResponse> {
-        todo!()
+    async fn get_instrument(&self, symbol: &str) -> Result<Instrument> {
+        let exchange_info = self.client.get_exchange_info().await?;
+        match exchange_info.symbols.iter().find(|s| s.symbol == symbol) {
+            Some(info) => Instrument::from_binance_symbol(info),
+            None => Err(Error::InvalidParams(format!("unknown symbol {symbol}"))),
+        }
     }
 
-    async fn cancel_order(&self, order_id: &String) -> anyhow::Result<CancelOrderResponse> {
-        todo!()
+    async fn place_order(&self, params: &PlaceOrderParams) -> Result<PlaceOrderResponse> {
+        let request = new_order_request(params)?;
+
+        let order = self.client.new_order(&request).await?;
+
+        Ok(PlaceOrderResponse {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id,
+        })
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: &str) -> Result<CancelOrderResponse> {
+        let order = self
+            .client
+            .cancel_order(symbol, &parse_order_id(order_id)?)
+            .await?;
+
+        Ok(CancelOrderResponse::from_binance_order(order))
     }
 
-    async fn cancel_all_orders(&self, symbol: &String) -> anyhow::Result<CancelAllOrdersResponse> {
-        todo!()
+    async fn cancel_all_orders(&self, symbol: &str) -> Result<CancelAllOrdersResponse> {
+        self.client.cancel_all_orders(symbol).await?;
+
+        Ok(CancelAllOrdersResponse { cancelled: vec![] })
     }
 
     async fn amend_order(
         &self,
-        order_id: &String,
+        order_id: &str,
         params: &AmendOrderParams,
-    ) -> anyhow::Result<AmendOrderResponse> {
-        todo!()
+    ) -> Result<AmendOrderResponse> {
+        if params.price.is_none() && params.amount.is_none() {
+            return Err(Error::InvalidParams(format!(
+                "amend order {order_id}: nothing to amend, price and amount are both empty"
+            )));
+        }
+
+        // binance requires side, price and quantity on every modification,
+        // so the missing values are taken from the current order
+        let order_ref = parse_order_id(order_id)?;
+        let order = self.client.query_order(&params.symbol, &order_ref).await?;
+        let price = params
+            .price
+            .map(|price| price.to_string())
+            .unwrap_or(order.price);
+        let quantity = params
+            .amount
+            .map(|amount| amount.to_string())
+            .unwrap_or(order.orig_qty);
+
+        let request =
+            ModifyOrderRequest::new(&params.symbol, order_ref, order.side, &quantity, &price);
+        let order = self.client.modify_order(&request).await?;
+
+        Ok(AmendOrderResponse {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id,
+        })
+    }
+
+    async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let balances = self.client.get_balance().await?;
+
+        balances.iter().map(Balance::from_binance_balance).collect()
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        let positions = self.client.get_position_risk(None).await?;
+
+        let mut res = vec![];
+        for position in positions.iter() {
+            if let Some(position) = Position::from_binance_position_risk(position)? {
+                res.push(position);
+            }
+        }
+        Ok(res)
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<Order>> {
+        let orders = self.client.get_open_orders(Some(symbol)).await?;
+
+        orders.iter().map(Order::from_binance_order).collect()
+    }
+
+    async fn get_order(&self, symbol: &str, order_id: &str) -> Result<Order> {
+        let order = self
+            .client
+            .query_order(symbol, &parse_order_id(order_id)?)
+            .await?;
+
+        Order::from_binance_order(&order)
     }
 }
 
 impl Orderbook {
-    fn from_binance_orderbook(orderbook: BinanceOrderBook, symbol: String) -> Self {
-        Orderbook {
-            symbol: symbol,
-            asks: orderbook
-                .asks
-                .into_iter()
-                .map(|ask| OrderbookLevel {
-                    price: ask[0].parse::<f64>().unwrap(),
-                    amount: ask[1].parse::<f64>().unwrap(),
-                })
-                .collect(),
-            bids: orderbook
-                .bids
-                .into_iter()
-                .map(|bid| OrderbookLevel {
-                    price: bid[0].parse::<f64>().unwrap(),
-                    amount: bid[1].parse::<f64>().unwrap(),
-                })
-                .collect(),
+    fn from_binance_orderbook(orderbook: BinanceOrderBook, symbol: String) -> Result<Self> {
+        Ok(Orderbook {
+            symbol,
+            asks: OrderbookLevel::parse_levels(&orderbook.asks)?,
+            bids: OrderbookLevel::parse_levels(&orderbook.bids)?,
             timestamp_ms: orderbook.event_time,
+            update_id: orderbook.last_update_id,
+        })
+    }
+}
+
+/// `InterfaceWs` over the market data stream and, with credentials, the user
+/// data stream. Books are synchronised with `BinanceDepthSync`, their
+/// snapshots are fetched in the background so the streams are read meanwhile.
+pub struct BinanceWsWrapper {
+    client: Arc<BinanceHttp>,
+    market: BinanceWs,
+    user: Option<BinanceUserWs>,
+    /// Local books with the depth they are published with.
+    books: HashMap<String, (BinanceDepthSync, usize)>,
+    snapshots_tx: mpsc::UnboundedSender<(String, Result<Orderbook>)>,
+    snapshots_rx: mpsc::UnboundedReceiver<(String, Result<Orderbook>)>,
+    /// Last ticker per symbol, book ticker and mark price updates are merged
+    /// into it.
+    tickers: HashMap<String, Ticker>,
+    pending: VecDeque<WsEvent>,
+}
+
+enum BinanceStreamEvent {
+    Market(Option<BinanceWsEvent>),
+    User(Option<BinanceUserWsEvent>),
+    Snapshot(String, Result<Orderbook>),
+}
+
+impl BinanceWsWrapper {
+    /// Market data only.
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_clients(
+            BinanceHttp::new("".to_string(), "".to_string())?,
+            BinanceWs::new(vec![]),
+            None,
+        ))
+    }
+
+    /// Creates the listen key of the user data stream.
+    pub async fn with_credentials(api_key: String, api_secret: String) -> Result<Self> {
+        let user =
+            BinanceUserWs::new(BinanceHttp::new(api_key.clone(), api_secret.clone())?).await?;
+        Ok(Self::from_clients(
+            BinanceHttp::new(api_key, api_secret)?,
+            BinanceWs::new(vec![]),
+            Some(user),
+        ))
+    }
+
+    /// Streams of the environment of `config`, the user data stream only with
+    /// `credentials`, whose listen key is created here.
+    pub async fn with_config(
+        config: &ClientConfig,
+        credentials: Option<(String, String)>,
+    ) -> Result<Self> {
+        let (api_key, api_secret) = credentials.clone().unwrap_or_default();
+        let client = BinanceHttp::with_config(api_key, api_secret, config)?;
+        let user = match credentials {
+            Some((api_key, api_secret)) => {
+                let user_client = BinanceHttp::with_config(api_key, api_secret, config)?;
+                Some(BinanceUserWs::with_config(user_client, config.ws_user_data()).await?)
+            }
+            None => None,
+        };
+        Ok(Self::from_clients(
+            client,
+            BinanceWs::with_config(vec![], config.ws_market()),
+            user,
+        ))
+    }
+
+    /// `client` fetches the book snapshots.
+    pub fn from_clients(
+        client: BinanceHttp,
+        market: BinanceWs,
+        user: Option<BinanceUserWs>,
+    ) -> Self {
+        let (snapshots_tx, snapshots_rx) = mpsc::unbounded_channel();
+        Self {
+            client: Arc::new(client),
+            market,
+            user,
+            books: HashMap::new(),
+            snapshots_tx,
+            snapshots_rx,
+            tickers: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn on_state(&mut self, state: ConnectionState) {
+        match state {
+            ConnectionState::Connected => self.pending.push_back(WsEvent::Connected),
+            ConnectionState::Disconnected(reason) => {
+                self.pending.push_back(WsEvent::Disconnected(reason))
+            }
+            ConnectionState::Reconnecting { .. } => {}
+        }
+    }
+
+    fn on_ticker(&mut self, ticker: Ticker) {
+        let last = self
+            .tickers
+            .entry(ticker.symbol.clone())
+            .or_insert_with(|| Ticker {
+                symbol: ticker.symbol.clone(),
+                ..Ticker::default()
+            });
+        last.merge(ticker);
+        self.pending.push_back(WsEvent::Ticker(last.clone()));
+    }
+
+    /// Fetches a snapshot of the `symbol` book in the background, it is
+    /// returned through `snapshots_rx`.
+    fn fetch_snapshot(&self, symbol: String) {
+        let client = self.client.clone();
+        let snapshots = self.snapshots_tx.clone();
+        tokio::spawn(async move {
+            let snapshot = BinanceDepthSync::fetch_snapshot(&client, &symbol).await;
+            // the wrapper may have been dropped meanwhile
+            let _ = snapshots.send((symbol, snapshot));
+        });
+    }
+
+    fn on_snapshot(&mut self, symbol: &str, snapshot: Result<Orderbook>) -> Result<()> {
+        let Some((sync, depth)) = self.books.get_mut(symbol) else {
+            return Ok(());
+        };
+        sync.on_snapshot(snapshot)?;
+        if !sync.needs_snapshot() {
+            let book = sync.book().to_orderbook(Some(*depth));
+            self.pending.push_back(WsEvent::BookUpdate(book));
+        }
+        Ok(())
+    }
+
+    fn on_market_event(&mut self, event: BinanceMarketEvent) -> Result<()> {
+        match event {
+            BinanceMarketEvent::DepthUpdate(update) => {
+                let symbol = update.symbol.clone();
+                let Some((sync, depth)) = self.books.get_mut(&symbol) else {
+                    return Ok(());
+                };
+                if sync.process(update) {
+                    self.fetch_snapshot(symbol);
+                } else if !sync.needs_snapshot() {
+                    let book = sync.book().to_orderbook(Some(*depth));
+                    self.pending.push_back(WsEvent::BookUpdate(book));
+                }
+            }
+            BinanceMarketEvent::AggTrade(trade) => {
+                let trade = Trade::from_binance_agg_trade(&trade)?;
+                self.pending.push_back(WsEvent::Trade(trade));
+            }
+            BinanceMarketEvent::BookTicker(ticker) => {
+                let ticker = Ticker::from_binance_book_ticker(&ticker)?;
+                self.on_ticker(ticker);
+            }
+            BinanceMarketEvent::MarkPriceUpdate(update) => {
+                let ticker = Ticker::from_binance_mark_price(&update)?;
+                self.on_ticker(ticker);
+            }
+            BinanceMarketEvent::Kline(_) => {}
+        }
+        Ok(())
+    }
+
+    fn on_user_event(&mut self, event: &BinanceUserEvent) -> Result<()> {
+        match event {
+            BinanceUserEvent::OrderTradeUpdate(update) => {
+                let order = Order::from_binance_order_update(update)?;
+                self.pending.push_back(WsEvent::OrderUpdate(order));
+                if let Some(fill) = Fill::from_binance_order_update(update)? {
+                    self.pending.push_back(WsEvent::Fill(fill));
+                }
+            }
+            BinanceUserEvent::AccountUpdate(update) => {
+                for balance in &update.account.balances {
+                    let balance = Balance::from_binance_balance_update(balance)?;
+                    self.pending.push_back(WsEvent::BalanceUpdate(balance));
+                }
+                for position in &update.account.positions {
+                    let position = Position::from_binance_position_update(position)?;
+                    self.pending.push_back(WsEvent::PositionUpdate(position));
+                }
+            }
+            BinanceUserEvent::MarginCall(_)
+            | BinanceUserEvent::ListenKeyExpired(_)
+            | BinanceUserEvent::Other => {}
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InterfaceWs for BinanceWsWrapper {
+    fn subscribe(&mut self, subscriptions: &[Subscription]) {
+        let mut streams = vec![];
+        for subscription in subscriptions {
+            match subscription {
+                Subscription::Orderbook { symbol, depth } => {
+                    if let Some(book) = self.books.get_mut(symbol) {
+                        book.1 = *depth;
+                        continue;
+                    }
+                    self.books
+                        .insert(symbol.clone(), (BinanceDepthSync::new(symbol), *depth));
+                    streams.push(BinanceStream::Depth {
+                        symbol: symbol.clone(),
+                        speed_ms: Some(100),
+                    });
+                }
+                Subscription::Trades(symbol) => {
+                    streams.push(BinanceStream::AggTrade(symbol.clone()))
+                }
+                Subscription::Ticker(symbol) => {
+                    streams.push(BinanceStream::BookTicker(symbol.clone()));
+                    streams.push(BinanceStream::MarkPrice {
+                        symbol: symbol.clone(),
+                        every_second: true,
+                    });
+                }
+            }
+        }
+        if !streams.is_empty() {
+            self.market.subscribe(streams);
+        }
+    }
+
+    async fn next(&mut self) -> Option<WsEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            let user = async {
+                match self.user.as_mut() {
+                    Some(user) => user.next().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let event = tokio::select! {
+                event = self.market.next() => BinanceStreamEvent::Market(event),
+                event = user => BinanceStreamEvent::User(event),
+                // the sender is held by the wrapper, the channel never closes
+                Some((symbol, snapshot)) = self.snapshots_rx.recv() => {
+                    BinanceStreamEvent::Snapshot(symbol, snapshot)
+                }
+            };
+            let res = match event {
+                BinanceStreamEvent::Market(Some(BinanceWsEvent::Update(message))) => {
+                    match message {
+                        BinanceWsMessage::Stream { data, .. } => self.on_market_event(data),
+                        BinanceWsMessage::Error { error, .. } => {
+                            Err(Error::from_binance_code(error.code, error.msg))
+                        }
+                        BinanceWsMessage::Response { .. } => Ok(()),
+                    }
+                }
+                BinanceStreamEvent::User(Some(BinanceUserWsEvent::Update(event))) => {
+                    self.on_user_event(&event)
+                }
+                BinanceStreamEvent::Market(Some(BinanceWsEvent::State(state)))
+                | BinanceStreamEvent::User(Some(BinanceUserWsEvent::State(state))) => {
+                    self.on_state(state);
+                    Ok(())
+                }
+                BinanceStreamEvent::Snapshot(symbol, snapshot) => {
+                    self.on_snapshot(&symbol, snapshot)
+                }
+                BinanceStreamEvent::Market(None) | BinanceStreamEvent::User(None) => return None,
+            };
+            if let Err(err) = res {
+                self.pending.push_back(WsEvent::Error(err));
+            }
+        }
+    }
+}
+
+/// Maintains a `LocalOrderbook` from the `<symbol>@depth` stream.
+///
+/// Follows Binance's procedure: updates are buffered until a REST snapshot is
+/// applied, updates with `u` below its `lastUpdateId` are dropped and the
+/// first applied one must contain it. Every later update must have `pu` equal
+/// to the previous `u`, otherwise the book is cleared and a new snapshot is
+/// needed, with buffering restarting from the update that revealed the gap.
+#[derive(Debug, Clone)]
+pub struct BinanceDepthSync {
+    book: LocalOrderbook,
+    buffer: Vec<DepthUpdate>,
+    /// Whether an update was applied on top of the current snapshot.
+    bridged: bool,
+    /// Snapshots that failed or were stale since the last one applied.
+    failed_snapshots: u32,
+    /// Whether a snapshot was requested and not handed to `on_snapshot` yet.
+    fetching: bool,
+    /// No snapshot is fetched before, while backing off.
+    next_snapshot_at: Option<Instant>,
+}
+
+impl BinanceDepthSync {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            book: LocalOrderbook::new(symbol),
+            buffer: Vec::new(),
+            bridged: false,
+            failed_snapshots: 0,
+            fetching: false,
+            next_snapshot_at: None,
+        }
+    }
+
+    pub fn book(&self) -> &LocalOrderbook {
+        &self.book
+    }
+
+    /// Whether a snapshot has to be applied before the book can be used.
+    pub fn needs_snapshot(&self) -> bool {
+        !self.book.is_synced()
+    }
+
+    /// Applies an update, or buffers it while waiting for a snapshot. A gap is
+    /// returned as `Error::SequenceGap`, see `needs_snapshot`.
+    pub fn push(&mut self, update: DepthUpdate) -> Result<()> {
+        if update.symbol != self.book.symbol() {
+            return Err(Error::InvalidParams(format!(
+                "depth update for {} pushed to the {} book",
+                update.symbol,
+                self.book.symbol()
+            )));
+        }
+        if self.needs_snapshot() {
+            self.buffer.push(update);
+            return Ok(());
+        }
+        let res = self.apply(&update);
+        if self.needs_snapshot() {
+            self.bridged = false;
+            self.buffer = vec![update];
+        }
+        res
+    }
+
+    fn apply(&mut self, update: &DepthUpdate) -> Result<()> {
+        let last_update_id = self.book.update_id();
+        if update.final_update_id < last_update_id {
+            return Ok(());
+        }
+        let prev_update_id = if self.bridged {
+            update.prev_final_update_id
+        } else if update.first_update_id <= last_update_id {
+            last_update_id
+        } else {
+            // the snapshot is older than the first update received
+            self.book.reset();
+            return Err(Error::SequenceGap {
+                expected: last_update_id,
+                received: update.first_update_id,
+            });
+        };
+        self.book.apply_delta(&OrderbookDelta {
+            bids: OrderbookLevel::parse_levels(&update.bids)?,
+            asks: OrderbookLevel::parse_levels(&update.asks)?,
+            prev_update_id,
+            update_id: update.final_update_id,
+            timestamp_ms: update.event_time,
+        })?;
+        self.bridged = true;
+        Ok(())
+    }
+
+    /// Replaces the book with `snapshot` and applies the buffered updates.
+    pub fn apply_snapshot(&mut self, snapshot: &Orderbook) -> Result<()> {
+        self.book.apply_snapshot(snapshot);
+        self.bridged = false;
+        let mut res = Ok(());
+        for update in std::mem::take(&mut self.buffer) {
+            let pushed = self.push(update);
+            if res.is_ok() {
+                res = pushed;
+            }
+        }
+        res
+    }
+
+    /// Fetches a snapshot of `symbol` with `BinanceHttp::get_orderbook`.
+    pub async fn fetch_snapshot(client: &BinanceHttp, symbol: &str) -> Result<Orderbook> {
+        let snapshot = client
+            .get_orderbook(symbol, Some(DEPTH_SNAPSHOT_LIMIT))
+            .await?;
+        Orderbook::from_binance_orderbook(snapshot, symbol.to_string())
+    }
+
+    /// Pushes an update and returns whether a snapshot has to be fetched now,
+    /// its result then goes to `on_snapshot`. Updates are buffered until a
+    /// snapshot applies, a single snapshot is requested at a time and after a
+    /// stale or failed one the next waits for a backoff. Gaps are resolved
+    /// here.
+    pub fn process(&mut self, update: DepthUpdate) -> bool {
+        if let Err(err) = self.push(update) {
+            warn!("{} book out of sync: {err}", self.book.symbol());
+        }
+        let backing_off = self
+            .next_snapshot_at
+            .is_some_and(|next_snapshot_at| Instant::now() < next_snapshot_at);
+        if !self.needs_snapshot() || self.fetching || backing_off {
+            return false;
+        }
+        self.fetching = true;
+        true
+    }
+
+    /// Applies a snapshot requested by `process`. A stale snapshot is retried
+    /// after a backoff, only failed requests return errors.
+    pub fn on_snapshot(&mut self, snapshot: Result<Orderbook>) -> Result<()> {
+        self.fetching = false;
+        let res = snapshot.and_then(|snapshot| self.apply_snapshot(&snapshot));
+        let Err(err) = res else {
+            self.failed_snapshots = 0;
+            self.next_snapshot_at = None;
+            return Ok(());
+        };
+        self.failed_snapshots += 1;
+        let delay = SNAPSHOT_MIN_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failed_snapshots - 1))
+            .min(SNAPSHOT_MAX_BACKOFF);
+        self.next_snapshot_at = Some(Instant::now() + delay);
+        match err {
+            Error::SequenceGap { .. } => {
+                warn!(
+                    "{} snapshot is stale: {err}, next one in {delay:?}",
+                    self.book.symbol()
+                );
+                Ok(())
+            }
+            err => Err(err),
+        }
+    }
+}
+
+impl Instrument {
+    fn from_binance_symbol(info: &Symbol) -> Result<Self> {
+        let contract_type = match info.contract_type.as_str() {
+            "PERPETUAL" => ContractType::Perpetual,
+            "CURRENT_MONTH" | "NEXT_MONTH" | "CURRENT_QUARTER" | "NEXT_QUARTER" => {
+                ContractType::Futures
+            }
+            other => ContractType::Other(other.to_string()),
+        };
+        let mut instrument = Instrument {
+            symbol: info.symbol.clone(),
+            contract_type,
+            settle_coin: info.margin_asset.clone(),
+            tick_size: 0.0,
+            qty_step: 0.0,
+            min_qty: 0.0,
+            max_qty: 0.0,
+            min_notional: 0.0,
+        };
+        for filter in &info.filters {
+            match filter {
+                Filter::PriceFilter { tick_size, .. } => {
+                    instrument.tick_size = parse_number(tick_size, "tick size")?;
+                }
+                Filter::LotSize {
+                    max_qty,
+                    min_qty,
+                    step_size,
+                } => {
+                    instrument.qty_step = parse_number(step_size, "step size")?;
+                    instrument.min_qty = parse_number(min_qty, "min qty")?;
+                    instrument.max_qty = parse_number(max_qty, "max qty")?;
+                }
+                Filter::MinNotional { notional } => {
+                    instrument.min_notional = parse_number(notional, "min notional")?;
+                }
+                _ => {}
+            }
+        }
+        Ok(instrument)
+    }
+}
+
+impl CancelOrderResponse {
+    fn from_binance_order(order: BinanceOrder) -> Self {
+        CancelOrderResponse {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id,
+        }
+    }
+}
+
+impl Side {
+    fn from_binance_side(side: &BinanceSide) -> Self {
+        match side {
+            BinanceSide::Buy => Side::Buy,
+            BinanceSide::Sell => Side::Sell,
+        }
+    }
+}
+
+impl OrderStatus {
+    fn from_binance_order_status(status: &BinanceOrderStatus) -> Self {
+        match status {
+            // liquidation orders are new until the venue fills them
+            BinanceOrderStatus::New
+            | BinanceOrderStatus::NewInsurance
+            | BinanceOrderStatus::NewAdl => OrderStatus::New,
+            BinanceOrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+            BinanceOrderStatus::Filled => OrderStatus::Filled,
+            BinanceOrderStatus::Canceled => OrderStatus::Cancelled,
+            BinanceOrderStatus::Rejected => OrderStatus::Rejected,
+            BinanceOrderStatus::Expired | BinanceOrderStatus::ExpiredInMatch => {
+                OrderStatus::Expired
+            }
+        }
+    }
+}
+
+impl Balance {
+    fn from_binance_balance(balance: &BinanceBalance) -> Result<Self> {
+        Ok(Balance {
+            asset: balance.asset.clone(),
+            total: parse_number(&balance.balance, "balance")?,
+            available: Some(parse_number(
+                &balance.available_balance,
+                "available balance",
+            )?),
+            unrealised_pnl: Some(parse_number(&balance.cross_un_pnl, "unrealised pnl")?),
+        })
+    }
+}
+
+impl Position {
+    /// Returns `None` for a flat position.
+    fn from_binance_position_risk(position: &BinancePositionRisk) -> Result<Option<Self>> {
+        let amount = parse_number(&position.position_amt, "position amount")?;
+        if amount == 0.0 {
+            return Ok(None);
+        }
+        let liquidation_price = parse_number(&position.liquidation_price, "liquidation price")?;
+        Ok(Some(Position {
+            symbol: position.symbol.clone(),
+            side: Some(if amount > 0.0 { Side::Buy } else { Side::Sell }),
+            size: amount.abs(),
+            entry_price: parse_number(&position.entry_price, "entry price")?,
+            mark_price: Some(parse_number(&position.mark_price, "mark price")?),
+            unrealised_pnl: parse_number(&position.un_realized_profit, "unrealised pnl")?,
+            leverage: Some(parse_number(&position.leverage, "leverage")?),
+            liquidation_price: (liquidation_price > 0.0).then_some(liquidation_price),
+        }))
+    }
+}
+
+impl Order {
+    fn from_binance_order(order: &BinanceOrder) -> Result<Self> {
+        Ok(Order {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: Side::from_binance_side(&order.side),
+            price: parse_number(&order.price, "price")?,
+            amount: parse_number(&order.orig_qty, "qty")?,
+            filled_amount: parse_number(&order.executed_qty, "executed qty")?,
+            status: OrderStatus::from_binance_order_status(&order.status),
+            created_time_ms: order.time,
+            updated_time_ms: order.update_time,
+        })
+    }
+}
+
+impl Order {
+    /// Order updates do not carry the creation time.
+    fn from_binance_order_update(update: &OrderTradeUpdate) -> Result<Self> {
+        let order = &update.order;
+        Ok(Order {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: Side::from_binance_side(&order.side),
+            price: parse_number(&order.price, "price")?,
+            amount: parse_number(&order.orig_qty, "qty")?,
+            filled_amount: parse_number(&order.cum_filled_qty, "executed qty")?,
+            status: OrderStatus::from_binance_order_status(&order.status),
+            created_time_ms: None,
+            updated_time_ms: update.transaction_time,
+        })
+    }
+}
+
+impl Fill {
+    /// Returns `None` unless the update reports a trade.
+    fn from_binance_order_update(update: &OrderTradeUpdate) -> Result<Option<Self>> {
+        let order = &update.order;
+        if order.execution_type != "TRADE" {
+            return Ok(None);
+        }
+        Ok(Some(Fill {
+            order_id: order.order_id.to_string(),
+            order_link_id: order.client_order_id.clone(),
+            trade_id: order.trade_id.to_string(),
+            symbol: order.symbol.clone(),
+            side: Side::from_binance_side(&order.side),
+            price: parse_number(&order.last_filled_price, "last filled price")?,
+            amount: parse_number(&order.last_filled_qty, "last filled qty")?,
+            fee: parse_number(
+                order.commission.as_deref().unwrap_or_default(),
+                "commission",
+            )?,
+            fee_asset: order.commission_asset.clone(),
+            is_maker: order.is_maker,
+            timestamp_ms: order.trade_time,
+        }))
+    }
+}
+
+impl Balance {
+    /// Account updates carry neither the available balance nor the unrealised
+    /// pnl.
+    fn from_binance_balance_update(balance: &BalanceUpdate) -> Result<Self> {
+        Ok(Balance {
+            asset: balance.asset.clone(),
+            total: parse_number(&balance.wallet_balance, "wallet balance")?,
+            available: None,
+            unrealised_pnl: None,
+        })
+    }
+}
+
+impl Position {
+    /// Account updates carry neither the mark price, the leverage nor the
+    /// liquidation price.
+    fn from_binance_position_update(position: &PositionUpdate) -> Result<Self> {
+        let amount = parse_number(&position.position_amt, "position amount")?;
+        let side = match position.position_side {
+            _ if amount == 0.0 => None,
+            BinancePositionSide::Long => Some(Side::Buy),
+            BinancePositionSide::Short => Some(Side::Sell),
+            BinancePositionSide::Both if amount < 0.0 => Some(Side::Sell),
+            BinancePositionSide::Both => Some(Side::Buy),
+        };
+        Ok(Position {
+            symbol: position.symbol.clone(),
+            side,
+            size: amount.abs(),
+            entry_price: parse_number(&position.entry_price, "entry price")?,
+            mark_price: None,
+            unrealised_pnl: parse_number(&position.unrealized_pnl, "unrealised pnl")?,
+            leverage: None,
+            liquidation_price: None,
+        })
+    }
+}
+
+impl Trade {
+    fn from_binance_agg_trade(trade: &AggTrade) -> Result<Self> {
+        Ok(Trade {
+            symbol: trade.symbol.clone(),
+            trade_id: trade.agg_trade_id.to_string(),
+            // the taker sold into a resting buy order
+            side: if trade.is_buyer_maker {
+                Side::Sell
+            } else {
+                Side::Buy
+            },
+            price: parse_number(&trade.price, "price")?,
+            amount: parse_number(&trade.quantity, "quantity")?,
+            timestamp_ms: trade.trade_time,
+        })
+    }
+}
+
+impl Ticker {
+    fn from_binance_book_ticker(ticker: &BookTicker) -> Result<Self> {
+        Ok(Ticker {
+            symbol: ticker.symbol.clone(),
+            bid_price: Some(parse_number(&ticker.bid_price, "bid price")?),
+            bid_amount: Some(parse_number(&ticker.bid_qty, "bid qty")?),
+            ask_price: Some(parse_number(&ticker.ask_price, "ask price")?),
+            ask_amount: Some(parse_number(&ticker.ask_qty, "ask qty")?),
+            timestamp_ms: ticker.transaction_time,
+            ..Ticker::default()
+        })
+    }
+
+    fn from_binance_mark_price(update: &MarkPriceUpdate) -> Result<Self> {
+        Ok(Ticker {
+            symbol: update.symbol.clone(),
+            mark_price: Some(parse_number(&update.mark_price, "mark price")?),
+            funding_rate: Some(parse_number(&update.funding_rate, "funding rate")?),
+            timestamp_ms: update.event_time,
+            ..Ticker::default()
+        })
+    }
+}
+
+impl Error {
+    /// Maps a Binance futures error `code` to the matching variant.
+    fn from_binance_code(code: i64, msg: String) -> Self {
+        match code {
+            -1002 | -1022 | -2014 | -2015 => Error::AuthFailed(msg),
+            -1003 | -1015 => Error::RateLimited(msg),
+            -1007 => Error::Timeout(msg),
+            -1102 | -1106 | -1116 | -1117 | -1130 => Error::InvalidParams(msg),
+            -2011 | -2013 => Error::OrderNotFound(msg),
+            -2018 | -2019 => Error::InsufficientBalance(msg),
+            -1111 | -4003 | -4005 | -4164 => Error::InvalidQuantity(msg),
+            -4014 | -4016 | -4024 => Error::InvalidPrice(msg),
+            _ => Error::Api { code, msg },
+        }
+    }
+}
+
+impl From<BinanceError> for Error {
+    fn from(err: BinanceError) -> Self {
+        match err {
+            BinanceError::ApiError(code, msg) => Error::from_binance_code(code, msg),
+            BinanceError::HttpError(err) if err.is_timeout() => Error::Timeout(err.to_string()),
+            BinanceError::HttpError(err) => Error::Network(err.to_string()),
+            BinanceError::ServerError(status, body) => {
+                Error::Network(format!("status {status}: {body}"))
+            }
+            BinanceError::DeserializeError(msg) => Error::Deserialize(msg),
+            BinanceError::InvalidRequest(msg) => Error::InvalidParams(msg),
+            BinanceError::RateLimited(msg) => Error::RateLimited(msg),
+            BinanceError::UnconfirmedCancel(msg) => {
+                Error::OrderNotFound(format!("{msg}, probably cancelled by an earlier attempt"))
+            }
+            BinanceError::Unknown(msg) => Error::Unknown(msg),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::exchanges::binance::BinanceHttpWrapper;
+    use cefi_rs_binance::{transport::MockTransport, ws::WsConfig as BinanceWsConfig};
+    use futures::SinkExt;
+    use reqwest::Method;
+    use serde_json::json;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use super::*;
 
+    fn order_json(order_id: i64, price: &str, qty: &str) -> serde_json::Value {
+        json!({
+            "clientOrderId": "testOrder",
+            "cumQty": "0",
+            "cumQuote": "0",
+            "executedQty": "0",
+            "orderId": order_id,
+            "avgPrice": "0.00000",
+            "origQty": qty,
+            "price": price,
+            "reduceOnly": false,
+            "side": "SELL",
+            "positionSide": "BOTH",
+            "status": "NEW",
+            "stopPrice": "0",
+            "closePosition": false,
+            "symbol": "BTCUSDT",
+            "timeInForce": "GTC",
+            "type": "LIMIT",
+            "origType": "LIMIT",
+            "updateTime": 1566818724722i64,
+            "workingType": "CONTRACT_PRICE",
+            "priceProtect": false
+        })
+    }
+
+    fn mock_wrapper(server: &MockServer) -> BinanceHttpWrapper {
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        BinanceHttpWrapper::from_client(client)
+    }
+
     #[tokio::test]
     async fn test_get_orderbook() {
-        let binance = BinanceHttpWrapper::new("".to_string(), "".to_string());
-        let orderbook = binance
-            .get_orderbook(&"BTCUSDT".to_string(), Some(5))
+        let transport = Arc::new(MockTransport::new());
+        transport.push_ok(
+            Method::GET,
+            "fapi/v1/depth",
+            &json!({
+                "lastUpdateId": 1027024,
+                "E": 1589436922972i64,
+                "T": 1589436922959i64,
+                "bids": [["4.00000000", "431.00000000"]],
+                "asks": [["4.00000200", "12.00000000"]]
+            })
+            .to_string(),
+        );
+        let client = BinanceHttp::new("".to_string(), "".to_string())
+            .unwrap()
+            .with_transport(transport.clone());
+        let binance = BinanceHttpWrapper::from_client(client);
+
+        let orderbook = binance.get_orderbook("BTCUSDT", None).await.unwrap();
+        assert_eq!(orderbook.update_id, 1027024);
+        assert_eq!(orderbook.bids[0].amount, 431.0);
+        assert_eq!(orderbook.asks[0].price, 4.000002);
+        let requests = transport.requests();
+        assert_eq!(requests[0].query_param("symbol").unwrap(), "BTCUSDT");
+        assert_eq!(requests[0].query_param("limit").unwrap(), "5");
+    }
+
+    #[tokio::test]
+    async fn test_place_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/order"))
+            .and(header("X-MBX-APIKEY", "key"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("side", "SELL"))
+            .and(query_param("type", "LIMIT"))
+            .and(query_param("timeInForce", "GTC"))
+            .and(query_param("price", "65000.5"))
+            .and(query_param("quantity", "0.002"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(order_json(22542179, "65000.5", "0.002")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let res = binance
+            .place_order(&PlaceOrderParams::limit("BTCUSDT", false, 65000.5, 0.002))
+            .await
+            .unwrap();
+        assert_eq!(res.order_id, "22542179");
+        assert_eq!(res.order_link_id, "testOrder");
+    }
+
+    #[tokio::test]
+    async fn test_place_stop_market_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("side", "SELL"))
+            .and(query_param("type", "STOP_MARKET"))
+            .and(query_param("stopPrice", "60000"))
+            .and(query_param("closePosition", "true"))
+            .and(query_param("newClientOrderId", "sl-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order_json(22542180, "0", "0")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let params = PlaceOrderParams {
+            order_type: OrderType::StopMarket,
+            trigger_price: Some(60000.0),
+            close_on_trigger: true,
+            client_order_id: Some("sl-1".to_string()),
+            ..PlaceOrderParams::market("BTCUSDT", false, 0.002)
+        };
+        let res = binance.place_order(&params).await.unwrap();
+        assert_eq!(res.order_id, "22542180");
+    }
+
+    #[test]
+    fn test_new_order_request() {
+        let params = PlaceOrderParams {
+            time_in_force: TimeInForce::PostOnly,
+            reduce_only: true,
+            ..PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 0.01)
+        };
+        let request = new_order_request(&params).unwrap();
+        assert_eq!(request.order_type, BinanceOrderType::Limit);
+        assert_eq!(request.time_in_force, Some(BinanceTimeInForce::Gtx));
+        assert_eq!(request.price.as_deref(), Some("65000"));
+        assert_eq!(request.quantity.as_deref(), Some("0.01"));
+        assert_eq!(request.reduce_only, Some(true));
+        assert_eq!(request.position_side, None);
+
+        let market = new_order_request(&PlaceOrderParams::market("BTCUSDT", false, 0.01)).unwrap();
+        assert_eq!(market.order_type, BinanceOrderType::Market);
+        assert_eq!(market.side, BinanceSide::Sell);
+        assert_eq!(market.time_in_force, None);
+        assert_eq!(market.price, None);
+    }
+
+    #[test]
+    fn test_new_order_request_unsupported() {
+        let hedge_reduce_only = PlaceOrderParams {
+            position_idx: PositionIdx::HedgeLong,
+            reduce_only: true,
+            ..PlaceOrderParams::limit("BTCUSDT", false, 65000.0, 0.01)
+        };
+        assert!(new_order_request(&hedge_reduce_only).is_err());
+
+        let close_on_trigger_limit = PlaceOrderParams {
+            order_type: OrderType::StopLimit,
+            trigger_price: Some(60000.0),
+            close_on_trigger: true,
+            ..PlaceOrderParams::limit("BTCUSDT", false, 59900.0, 0.01)
+        };
+        assert!(new_order_request(&close_on_trigger_limit).is_err());
+
+        let missing_trigger = PlaceOrderParams {
+            order_type: OrderType::TakeProfitMarket,
+            ..PlaceOrderParams::market("BTCUSDT", false, 0.01)
+        };
+        assert!(new_order_request(&missing_trigger).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_place_order_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/order"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "code": -2019,
+                "msg": "Margin is insufficient."
+            })))
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let res = binance
+            .place_order(&PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 100.0))
+            .await;
+        assert_eq!(
+            res,
+            Err(Error::InsufficientBalance(
+                "Margin is insufficient.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_from_binance_code() {
+        assert!(matches!(
+            Error::from_binance_code(-1003, "Too many requests.".to_string()),
+            Error::RateLimited(_)
+        ));
+        assert!(matches!(
+            Error::from_binance_code(-2015, "Invalid API-key, IP, or permissions.".to_string()),
+            Error::AuthFailed(_)
+        ));
+        assert!(matches!(
+            Error::from_binance_code(-2013, "Order does not exist.".to_string()),
+            Error::OrderNotFound(_)
+        ));
+        assert!(matches!(
+            Error::from_binance_code(-4003, "Quantity less than or equal to zero.".to_string()),
+            Error::InvalidQuantity(_)
+        ));
+        assert_eq!(
+            Error::from_binance_code(-4046, "No need to change margin type.".to_string()),
+            Error::Api {
+                code: -4046,
+                msg: "No need to change margin type.".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("orderId", "22542179"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(order_json(22542179, "65000", "0.002")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let res = binance.cancel_order("BTCUSDT", "22542179").await.unwrap();
+        assert_eq!(res.order_id, "22542179");
+
+        assert!(binance
+            .cancel_order("BTCUSDT", "not-a-number")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/allOpenOrders"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "code": 200,
+                "msg": "The operation of cancel all open order is done."
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let res = binance.cancel_all_orders("BTCUSDT").await.unwrap();
+        assert!(res.cancelled.is_empty());
+    }
+
+    fn binance_orderbook(bids: &[[&str; 2]], asks: &[[&str; 2]]) -> BinanceOrderBook {
+        let levels = |levels: &[[&str; 2]]| {
+            levels
+                .iter()
+                .map(|[price, amount]| [price.to_string(), amount.to_string()])
+                .collect()
+        };
+        BinanceOrderBook {
+            last_update_id: 1027024,
+            event_time: 1589436922972,
+            transaction_time: 1589436922959,
+            bids: levels(bids),
+            asks: levels(asks),
+        }
+    }
+
+    #[test]
+    fn test_from_binance_orderbook() {
+        let orderbook = binance_orderbook(
+            &[["64999.90", "1.250"], ["64999.80", "0.004"]],
+            &[["65000.00", "3.001"]],
+        );
+        let orderbook =
+            Orderbook::from_binance_orderbook(orderbook, "BTCUSDT".to_string()).unwrap();
+        assert_eq!(orderbook.symbol, "BTCUSDT");
+        assert_eq!(orderbook.update_id, 1027024);
+        assert_eq!(orderbook.timestamp_ms, 1589436922972);
+        assert_eq!(orderbook.bids.len(), 2);
+        assert_eq!(orderbook.bids[0].price, 64999.9);
+        assert_eq!(orderbook.bids[1].amount, 0.004);
+        assert_eq!(orderbook.asks[0].price, 65000.0);
+    }
+
+    #[test]
+    fn test_from_binance_orderbook_malformed() {
+        let orderbook = binance_orderbook(&[["64999.90", "1.250"]], &[["not-a-price", "3.001"]]);
+        let res = Orderbook::from_binance_orderbook(orderbook, "BTCUSDT".to_string());
+        assert!(res.is_err());
+    }
+
+    /// Recorded from `btcusdt@depth@100ms`, the snapshot has `lastUpdateId`
+    /// 7401027024.
+    const DEPTH_UPDATES: [&str; 4] = [
+        r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1718000000100,"T":1718000000098,"s":"BTCUSDT","U":7401026990,"u":7401027010,"pu":7401026985,"b":[["64999.90","9.000"]],"a":[]}}"#,
+        r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1718000000200,"T":1718000000197,"s":"BTCUSDT","U":7401027011,"u":7401027030,"pu":7401027010,"b":[["64999.90","1.500"],["64999.70","0.300"]],"a":[["65000.00","0"]]}}"#,
+        r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1718000000300,"T":1718000000299,"s":"BTCUSDT","U":7401027031,"u":7401027044,"pu":7401027030,"b":[],"a":[["65000.10","2.200"]]}}"#,
+        r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1718000000400,"T":1718000000398,"s":"BTCUSDT","U":7401027060,"u":7401027071,"pu":7401027052,"b":[["64999.80","0"]],"a":[]}}"#,
+    ];
+
+    fn depth_update(message: &str) -> DepthUpdate {
+        match serde_json::from_str(message).unwrap() {
+            BinanceWsMessage::Stream {
+                data: BinanceMarketEvent::DepthUpdate(update),
+                ..
+            } => update,
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    fn depth_snapshot() -> serde_json::Value {
+        json!({
+            "lastUpdateId": 7401027024i64,
+            "E": 1718000000150i64,
+            "T": 1718000000149i64,
+            "bids": [["64999.90", "1.250"], ["64999.80", "0.004"]],
+            "asks": [["65000.00", "3.001"], ["65000.10", "0.500"]]
+        })
+    }
+
+    fn level(price: f64, amount: f64) -> OrderbookLevel {
+        OrderbookLevel { price, amount }
+    }
+
+    #[test]
+    fn test_depth_sync() {
+        let mut sync = BinanceDepthSync::new("BTCUSDT");
+        for message in &DEPTH_UPDATES[..2] {
+            sync.push(depth_update(message)).unwrap();
+        }
+        assert!(sync.needs_snapshot());
+
+        let snapshot = serde_json::from_value(depth_snapshot()).unwrap();
+        let snapshot = Orderbook::from_binance_orderbook(snapshot, "BTCUSDT".to_string()).unwrap();
+        // the first update is older than the snapshot, the second contains it
+        sync.apply_snapshot(&snapshot).unwrap();
+        assert!(!sync.needs_snapshot());
+        assert_eq!(sync.book().update_id(), 7401027030);
+        assert_eq!(
+            sync.book().bids(5),
+            vec![
+                level(64999.9, 1.5),
+                level(64999.8, 0.004),
+                level(64999.7, 0.3)
+            ]
+        );
+        assert_eq!(sync.book().best_ask(), Some(level(65000.1, 0.5)));
+
+        sync.push(depth_update(DEPTH_UPDATES[2])).unwrap();
+        assert_eq!(sync.book().best_ask(), Some(level(65000.1, 2.2)));
+
+        let res = sync.push(depth_update(DEPTH_UPDATES[3]));
+        assert_eq!(
+            res,
+            Err(Error::SequenceGap {
+                expected: 7401027044,
+                received: 7401027052
+            })
+        );
+        assert!(sync.needs_snapshot());
+        assert!(sync.book().best_bid().is_none());
+    }
+
+    #[test]
+    fn test_depth_sync_stale_snapshot() {
+        let mut sync = BinanceDepthSync::new("BTCUSDT");
+        sync.push(depth_update(DEPTH_UPDATES[2])).unwrap();
+
+        let snapshot = serde_json::from_value(depth_snapshot()).unwrap();
+        let snapshot = Orderbook::from_binance_orderbook(snapshot, "BTCUSDT".to_string()).unwrap();
+        let res = sync.apply_snapshot(&snapshot);
+        assert!(matches!(res, Err(Error::SequenceGap { .. })));
+        assert!(sync.needs_snapshot());
+
+        let res = sync.push(DepthUpdate {
+            symbol: "ETHUSDT".to_string(),
+            ..depth_update(DEPTH_UPDATES[2])
+        });
+        assert!(matches!(res, Err(Error::InvalidParams(_))));
+    }
+
+    /// Handles an update the way `BinanceWsWrapper` does, with the snapshot
+    /// awaited in place.
+    async fn process(sync: &mut BinanceDepthSync, client: &BinanceHttp, update: DepthUpdate) {
+        if sync.process(update) {
+            let snapshot = BinanceDepthSync::fetch_snapshot(client, "BTCUSDT").await;
+            sync.on_snapshot(snapshot).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_depth_sync_resyncs_on_gap() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/depth"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("limit", "1000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(depth_snapshot()))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        // the snapshot taken after the gap
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/depth"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "lastUpdateId": 7401027065i64,
+                "E": 1718000000350i64,
+                "T": 1718000000349i64,
+                "bids": [["64999.80", "0.100"]],
+                "asks": [["65000.20", "1.000"]]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        let mut sync = BinanceDepthSync::new("BTCUSDT");
+        for message in &DEPTH_UPDATES[1..] {
+            process(&mut sync, &client, depth_update(message)).await;
+        }
+
+        assert!(!sync.needs_snapshot());
+        assert_eq!(sync.book().update_id(), 7401027071);
+        assert!(sync.book().best_bid().is_none());
+        assert_eq!(sync.book().best_ask(), Some(level(65000.2, 1.0)));
+    }
+
+    #[tokio::test]
+    async fn test_depth_sync_snapshot_backoff() {
+        let server = MockServer::start().await;
+        // always older than the updates
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/depth"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(depth_snapshot()))
+            .mount(&server)
+            .await;
+
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        let mut sync = BinanceDepthSync::new("BTCUSDT");
+        for message in &DEPTH_UPDATES[2..] {
+            process(&mut sync, &client, depth_update(message)).await;
+        }
+        assert!(sync.needs_snapshot());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        tokio::time::sleep(SNAPSHOT_MIN_BACKOFF).await;
+        process(&mut sync, &client, depth_update(DEPTH_UPDATES[3])).await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_amend_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("orderId", "22542179"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(order_json(22542179, "65000", "0.002")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("orderId", "22542179"))
+            .and(query_param("side", "SELL"))
+            .and(query_param("price", "64000"))
+            .and(query_param("quantity", "0.002"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(order_json(22542179, "64000", "0.002")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let res = binance
+            .amend_order(
+                "22542179",
+                &AmendOrderParams {
+                    symbol: "BTCUSDT".to_string(),
+                    price: Some(64000.0),
+                    amount: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.order_id, "22542179");
+    }
+
+    #[tokio::test]
+    async fn test_get_balances() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v2/balance"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "accountAlias": "SgsR",
+                "asset": "USDT",
+                "balance": "122607.35137903",
+                "crossWalletBalance": "23.72469206",
+                "crossUnPnl": "-1.5",
+                "availableBalance": "23.72469206",
+                "maxWithdrawAmount": "23.72469206",
+                "marginAvailable": true,
+                "updateTime": 1617939110373i64
+            }])))
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let balances = binance.get_balances().await.unwrap();
+        assert_eq!(
+            balances,
+            vec![Balance {
+                asset: "USDT".to_string(),
+                total: 122607.35137903,
+                available: Some(23.72469206),
+                unrealised_pnl: Some(-1.5),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_positions() {
+        let server = MockServer::start().await;
+        let position = |symbol: &str, amount: &str| {
+            json!({
+                "entryPrice": "6563.6", "breakEvenPrice": "6565.0", "marginType": "cross",
+                "isAutoAddMargin": "false", "isolatedMargin": "0", "leverage": "10",
+                "liquidationPrice": "0", "markPrice": "6679.50671178", "maxNotionalValue": "20000000",
+                "positionAmt": amount, "notional": "0", "isolatedWallet": "0", "symbol": symbol,
+                "unRealizedProfit": "2.1", "positionSide": "BOTH", "updateTime": 0
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/fapi/v2/positionRisk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                position("BTCUSDT", "-0.010"),
+                position("ETHUSDT", "0.000")
+            ])))
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let positions = binance.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].side, Some(Side::Sell));
+        assert_eq!(positions[0].size, 0.01);
+        assert_eq!(positions[0].liquidation_price, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_open_orders() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/openOrders"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([order_json(22542179, "65000", "0.002")])),
+            )
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let orders = binance.get_open_orders("BTCUSDT").await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, Side::Sell);
+        assert_eq!(orders[0].status, OrderStatus::New);
+        assert_eq!(orders[0].amount, 0.002);
+    }
+
+    #[tokio::test]
+    async fn test_get_instrument() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/exchangeInfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "rateLimits": [],
+                "assets": [],
+                "timezone": "UTC",
+                "symbols": [{
+                    "symbol": "BTCUSDT",
+                    "pair": "BTCUSDT",
+                    "contractType": "PERPETUAL",
+                    "deliveryDate": 4133404800000i64,
+                    "onboardDate": 1569398400000i64,
+                    "status": "TRADING",
+                    "baseAsset": "BTC",
+                    "quoteAsset": "USDT",
+                    "marginAsset": "USDT",
+                    "pricePrecision": 2,
+                    "quantityPrecision": 3,
+                    "baseAssetPrecision": 8,
+                    "quotePrecision": 8,
+                    "underlyingType": "COIN",
+                    "underlyingSubType": ["PoW"],
+                    "settlePlan": 0,
+                    "triggerProtect": "0.0500",
+                    "filters": [
+                        {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+                        {"filterType": "LOT_SIZE", "minQty": "0.001", "maxQty": "1000", "stepSize": "0.001"},
+                        {"filterType": "MIN_NOTIONAL", "notional": "100"}
+                    ],
+                    "timeInForce": ["GTC", "IOC", "FOK", "GTX"],
+                    "liquidationFee": "0.012500",
+                    "marketTakeBound": "0.05"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let binance = mock_wrapper(&server);
+        let instrument = binance.get_instrument("BTCUSDT").await.unwrap();
+        assert_eq!(instrument.contract_type, ContractType::Perpetual);
+        assert_eq!(instrument.tick_size, 0.1);
+        assert_eq!(instrument.qty_step, 0.001);
+        assert_eq!(instrument.max_qty, 1000.0);
+        assert_eq!(instrument.min_notional, 100.0);
+
+        let res = binance.get_instrument("ETHUSDT").await;
+        assert!(matches!(res, Err(Error::InvalidParams(_))));
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        accept_async(stream).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper_reads_streams_during_snapshot() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/depth"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(depth_snapshot())
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = BinanceWsConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            ..BinanceWsConfig::default()
+        };
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        let mut ws =
+            BinanceWsWrapper::from_clients(client, BinanceWs::with_config(vec![], config), None);
+        ws.subscribe(&[Subscription::Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            depth: 2,
+        }]);
+        let mut market = accept(&listener).await;
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+
+        for message in [
+            DEPTH_UPDATES[1],
+            r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1718000000250,"s":"BTCUSDT","a":5933014,"p":"65000.0","q":"0.010","f":100,"l":105,"T":1718000000249,"m":true}}"#,
+        ] {
+            market
+                .send(Message::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+        // the trade is not held back by the snapshot request
+        assert!(matches!(ws.next().await, Some(WsEvent::Trade(_))));
+        match ws.next().await {
+            Some(WsEvent::BookUpdate(book)) => assert_eq!(book.update_id, 7401027030),
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/depth"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(depth_snapshot()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "listenKey": "listen-key" })),
+            )
+            .mount(&server)
+            .await;
+
+        let market_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let market_config = BinanceWsConfig {
+            url: format!("ws://{}", market_listener.local_addr().unwrap()),
+            ..BinanceWsConfig::default()
+        };
+        let user_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let user_config = BinanceWsConfig {
+            url: format!("ws://{}/ws", user_listener.local_addr().unwrap()),
+            ..BinanceWsConfig::user_data()
+        };
+        let client = || {
+            BinanceHttp::new("key".to_string(), "secret".to_string())
+                .unwrap()
+                .with_host(server.uri())
+        };
+        let user = BinanceUserWs::with_config(client(), user_config)
+            .await
+            .unwrap();
+        let mut ws = BinanceWsWrapper::from_clients(
+            client(),
+            BinanceWs::with_config(vec![], market_config),
+            Some(user),
+        );
+        ws.subscribe(&[Subscription::Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            depth: 2,
+        }]);
+
+        let mut market = accept(&market_listener).await;
+        let mut user = accept(&user_listener).await;
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+
+        market
+            .send(Message::Text(DEPTH_UPDATES[1].to_string()))
+            .await
+            .unwrap();
+        match ws.next().await {
+            Some(WsEvent::BookUpdate(book)) => {
+                assert_eq!(book.update_id, 7401027030);
+                assert_eq!(book.bids, vec![level(64999.9, 1.5), level(64999.8, 0.004)]);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        user.send(Message::Text(r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"TEST","S":"BUY","o":"LIMIT","f":"GTC","q":"0.002","p":"65000","ap":"65000","sp":"0","x":"TRADE","X":"PARTIALLY_FILLED","i":8886774,"l":"0.001","z":"0.001","L":"65000","N":"USDT","n":"0.013","T":1568879465649,"t":12,"b":"0","a":"0","m":true,"R":false,"wt":"CONTRACT_PRICE","ot":"LIMIT","ps":"BOTH","cp":false,"rp":"0"}}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::OrderUpdate(order)) => {
+                assert_eq!(order.order_id, "8886774");
+                assert_eq!(order.status, OrderStatus::PartiallyFilled);
+                assert_eq!(order.filled_amount, 0.001);
+                assert_eq!(order.created_time_ms, None);
+                assert_eq!(order.updated_time_ms, 1568879465650);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        assert_eq!(
+            ws.next().await,
+            Some(WsEvent::Fill(Fill {
+                order_id: "8886774".to_string(),
+                order_link_id: "TEST".to_string(),
+                trade_id: "12".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                side: Side::Buy,
+                price: 65000.0,
+                amount: 0.001,
+                fee: 0.013,
+                fee_asset: Some("USDT".to_string()),
+                is_maker: true,
+                timestamp_ms: 1568879465649,
+            }))
+        );
+
+        user.send(Message::Text(r#"{"e":"ACCOUNT_UPDATE","E":1564745798939,"T":1564745798938,"a":{"m":"ORDER","B":[{"a":"USDT","wb":"122624.12345678","cw":"100.12345678","bc":"50.12345678"}],"P":[{"s":"BTCUSDT","pa":"-0.001","ep":"65000","bep":"65010","cr":"200","up":"-0.5","mt":"cross","iw":"0","ps":"BOTH"}]}}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::BalanceUpdate(balance)) => {
+                assert_eq!(balance.total, 122624.12345678);
+                assert_eq!(balance.available, None);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        match ws.next().await {
+            Some(WsEvent::PositionUpdate(position)) => {
+                assert_eq!(position.side, Some(Side::Sell));
+                assert_eq!(position.size, 0.001);
+                assert_eq!(position.unrealised_pnl, -0.5);
+                assert_eq!(position.mark_price, None);
+                assert_eq!(position.leverage, None);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        market
+            .send(Message::Text(
+                r#"{"error":{"code":2,"msg":"Invalid request: unknown variant"},"id":3}"#
+                    .to_string(),
+            ))
             .await
             .unwrap();
-        println!("orderbook: {:?}", orderbook);
+        assert_eq!(
+            ws.next().await,
+            Some(WsEvent::Error(Error::Api {
+                code: 2,
+                msg: "Invalid request: unknown variant".to_string()
+            }))
+        );
     }
 }
@@ -1,42 +1,144 @@
+use std::collections::{HashMap, VecDeque};
+
 use async_trait::async_trait;
-use cefi_rs_bybit::{http::BybitHttp, rest::market::OrderbookResponse};
+use cefi_rs_bybit::{
+    config::ClientConfig,
+    errors::{BybitError, BybitResult},
+    http::BybitHttp,
+    rest::market::{InstrumentInfo, OrderbookResponse},
+    types::{
+        BybitCategory, BybitLinearPosition, BybitOrderStatus, BybitOrderType, BybitSide,
+        BybitTimeInForce, BybitTriggerDirection,
+        CancelAllOrdersResponse as BybitCancelAllOrdersResponse, GetOrderResponseData,
+        OrderResponse, PlaceOrderRequest, WalletBalanceCoin,
+    },
+    ws::BybitWs,
+    ws_public::BybitPublicWs,
+    ws_types::{
+        BybitExecutionData, BybitPublicData, BybitPublicTopic, BybitPublicTradeData,
+        BybitPublicUpdate, BybitPublicWsEvent, BybitPublicWsUpdate, BybitTickerData,
+        BybitUpdateData, BybitUpdateType, BybitWsEvent, ConnectionState,
+    },
+};
+use tracing::warn;
 
 use crate::{
+    errors::{Error, Result},
     interface_http::InterfaceHttp,
-    types::{Orderbook, OrderbookLevel},
+    interface_ws::{InterfaceWs, Subscription, WsEvent},
+    orderbook::{LocalOrderbook, OrderbookDelta},
+    trade::*,
+    types::{
+        parse_number, parse_optional_number, Balance, ContractType, Fill, Instrument, Order,
+        OrderStatus, Orderbook, OrderbookLevel, Position, Side, Ticker, Trade,
+    },
 };
 
+/// Private topics streamed by `BybitWsWrapper`.
+const BYBIT_PRIVATE_TOPICS: [&str; 4] = [
+    "order.linear",
+    "execution.linear",
+    "position.linear",
+    "wallet",
+];
+
+/// Depths of the linear `orderbook` topic.
+const BYBIT_BOOK_DEPTHS: [u32; 4] = [1, 50, 200, 500];
+
 pub struct BybitHttpWrapper {
     client: BybitHttp,
 }
 
 impl BybitHttpWrapper {
-    pub fn new(api_key: String, api_secret: String) -> Self {
-        Self {
-            client: BybitHttp::new(api_key, api_secret),
+    pub fn new(api_key: String, api_secret: String) -> Result<Self> {
+        Ok(Self {
+            client: BybitHttp::new(api_key, api_secret)?,
+        })
+    }
+
+    /// Environment, urls, timeout, proxy, user agent and clock from `config`.
+    pub fn with_config(api_key: String, api_secret: String, config: &ClientConfig) -> Result<Self> {
+        Ok(Self {
+            client: BybitHttp::with_config(api_key, api_secret, config)?,
+        })
+    }
+
+    pub fn from_client(client: BybitHttp) -> Self {
+        Self { client }
+    }
+}
+
+/// Translates unified order params into a Bybit `PlaceOrderRequest`. Stop and
+/// take-profit orders become conditional market or limit orders whose trigger
+/// direction follows from the side.
+fn place_order_request(params: &PlaceOrderParams) -> Result<PlaceOrderRequest> {
+    params.validate()?;
+
+    let side = if params.is_buy {
+        BybitSide::Buy
+    } else {
+        BybitSide::Sell
+    };
+    let order_type = if params.order_type.is_limit() {
+        BybitOrderType::Limit
+    } else {
+        BybitOrderType::Market
+    };
+    let position_idx = match params.position_idx {
+        PositionIdx::OneWay => 0,
+        PositionIdx::HedgeLong => 1,
+        PositionIdx::HedgeShort => 2,
+    };
+
+    let mut request =
+        PlaceOrderRequest::new(&params.symbol, side, order_type, &params.amount.to_string())
+            .position_idx(position_idx);
+
+    if params.order_type.is_limit() {
+        let time_in_force = match params.time_in_force {
+            TimeInForce::Gtc => BybitTimeInForce::Gtc,
+            TimeInForce::Ioc => BybitTimeInForce::Ioc,
+            TimeInForce::Fok => BybitTimeInForce::Fok,
+            TimeInForce::PostOnly => BybitTimeInForce::PostOnly,
+        };
+        if let Some(price) = params.price {
+            request = request
+                .price(&price.to_string())
+                .time_in_force(time_in_force);
         }
     }
+    if let Some(trigger_price) = params.trigger_price {
+        // a stop buys on the way up and sells on the way down, take profit the opposite
+        let direction = match (params.order_type.is_stop(), params.is_buy) {
+            (true, true) | (false, false) => BybitTriggerDirection::Rise,
+            (true, false) | (false, true) => BybitTriggerDirection::Fall,
+        };
+        request = request.trigger(&trigger_price.to_string(), direction);
+    }
+    if params.reduce_only {
+        request = request.reduce_only(true);
+    }
+    if params.close_on_trigger {
+        request = request.close_on_trigger(true);
+    }
+    if let Some(client_order_id) = &params.client_order_id {
+        request = request.order_link_id(client_order_id);
+    }
+
+    Ok(request)
 }
 
 #[async_trait]
 impl InterfaceHttp for BybitHttpWrapper {
-    async fn get_server_time(&self) -> anyhow::Result<u64> {
-        let server_time = self
-            .client
-            .get_server_time()
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    async fn get_server_time(&self) -> Result<u64> {
+        let server_time = self.client.get_server_time().await?;
 
-        let nanos = server_time.time_nano.parse::<u64>()?;
+        let nanos = parse_number(&server_time.time_nano, "server time")? as u64;
 
         Ok(nanos / 1_000_000)
     }
 
-    async fn get_orderbook(
-        &self,
-        symbol: &String,
-        limit: Option<i32>,
-    ) -> anyhow::Result<Orderbook> {
+    async fn get_orderbook(&self, symbol: &str, limit: Option<i32>) -> Result<Orderbook> {
         let orderbook = self
             .client
             .get_orderbook(
@@ -44,83 +146,1388 @@ impl InterfaceHttp for BybitHttpWrapper {
                 symbol.to_string(),
                 limit.unwrap_or(10),
             )
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+            .await?;
 
-        Ok(Orderbook::from_bybit_orderbook(
-            orderbook,
-            symbol.to_string(),
-        ))
+        Orderbook::from_bybit_orderbook(orderbook, symbol.to_string())
     }
 
-    async fn place_order(
-        &self,
-        params: &crate::trade::PlaceOrderParams,
-    ) -> anyhow::Result<crate::trade::PlaceOrderResponse> {
-        todo!()
+    async fn get_instrument(&self, symbol: &str) -> Result<Instrument> {
+        let res = self.client.get_instrument_info(symbol).await?;
+        match res.list.first() {
+            Some(info) => Instrument::from_bybit_instrument(info),
+            None => Err(Error::InvalidParams(format!("unknown symbol {symbol}"))),
+        }
     }
 
-    async fn cancel_order(
-        &self,
-        order_id: &String,
-    ) -> anyhow::Result<crate::trade::CancelOrderResponse> {
-        todo!()
+    async fn place_order(&self, params: &PlaceOrderParams) -> Result<PlaceOrderResponse> {
+        let request = place_order_request(params)?;
+        let res = self.client.create_order(&request).await?;
+
+        Ok(PlaceOrderResponse {
+            order_id: res.order_id,
+            order_link_id: res.order_link_id,
+        })
     }
 
-    async fn cancel_all_orders(
-        &self,
-        symbol: &String,
-    ) -> anyhow::Result<crate::trade::CancelAllOrdersResponse> {
-        todo!()
+    async fn cancel_order(&self, symbol: &str, order_id: &str) -> Result<CancelOrderResponse> {
+        let res = self.client.cancel_order_by_id(symbol, order_id).await?;
+
+        Ok(CancelOrderResponse::from_bybit_order_response(res))
+    }
+
+    async fn cancel_all_orders(&self, symbol: &str) -> Result<CancelAllOrdersResponse> {
+        let res = self.client.cancel_all_orders(symbol).await?;
+
+        Ok(CancelAllOrdersResponse::from_bybit_cancel_all_orders_response(res))
     }
 
     async fn amend_order(
         &self,
-        order_id: &String,
-        params: &crate::trade::AmendOrderParams,
-    ) -> anyhow::Result<crate::trade::AmendOrderResponse> {
-        todo!()
+        order_id: &str,
+        params: &AmendOrderParams,
+    ) -> Result<AmendOrderResponse> {
+        if params.price.is_none() && params.amount.is_none() {
+            return Err(Error::InvalidParams(format!(
+                "amend order {order_id}: nothing to amend, price and amount are both empty"
+            )));
+        }
+
+        let res = self
+            .client
+            .amend_order_by_id(
+                &params.symbol,
+                order_id,
+                params.price.map(|price| price.to_string()),
+                params.amount.map(|amount| amount.to_string()),
+            )
+            .await?;
+
+        Ok(AmendOrderResponse {
+            order_id: res.order_id,
+            order_link_id: res.order_link_id,
+        })
+    }
+
+    async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let res = self.client.get_wallet_balance().await?;
+
+        res.list
+            .iter()
+            .flat_map(|wallet| wallet.coin.iter())
+            .map(Balance::from_bybit_coin)
+            .collect()
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>> {
+        let res = self.client.get_positions().await?;
+
+        res.list
+            .iter()
+            .filter(|position| position.side != BybitSide::Flat)
+            .map(Position::from_bybit_position)
+            .collect()
+    }
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<Order>> {
+        let res = self.client.get_open_order(symbol, None).await?;
+
+        res.list.iter().map(Order::from_bybit_order).collect()
+    }
+
+    async fn get_order(&self, symbol: &str, order_id: &str) -> Result<Order> {
+        let res = self.client.get_order_by_id(symbol, order_id).await?;
+
+        match res.list.first() {
+            Some(order) => Order::from_bybit_order(order),
+            None => Err(Error::OrderNotFound(format!("order {order_id}"))),
+        }
+    }
+}
+
+/// `InterfaceWs` over the linear public stream and, with credentials, the
+/// private stream.
+pub struct BybitWsWrapper {
+    public: BybitPublicWs,
+    private: Option<BybitWs>,
+    books: HashMap<String, BybitBook>,
+    /// Last ticker per symbol, deltas are merged into it.
+    tickers: HashMap<String, Ticker>,
+    pending: VecDeque<WsEvent>,
+}
+
+/// Local book of a symbol, maintained from one orderbook topic.
+struct BybitBook {
+    book: LocalOrderbook,
+    topic: BybitPublicTopic,
+    /// Depth the book is published with.
+    depth: usize,
+}
+
+enum BybitStreamEvent {
+    Public(Option<BybitPublicWsEvent>),
+    Private(Option<BybitResult<BybitWsEvent>>),
+}
+
+impl Default for BybitWsWrapper {
+    fn default() -> Self {
+        Self::from_clients(BybitPublicWs::new(BybitCategory::Linear, vec![]), None)
+    }
+}
+
+impl BybitWsWrapper {
+    /// Market data only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_credentials(api_key: String, api_secret: String) -> Self {
+        let topics = BYBIT_PRIVATE_TOPICS.map(String::from).to_vec();
+        Self::from_clients(
+            BybitPublicWs::new(BybitCategory::Linear, vec![]),
+            Some(BybitWs::new(api_key, api_secret, topics)),
+        )
+    }
+
+    /// Streams of the environment of `config`, the private one only with
+    /// `credentials`.
+    pub fn with_config(config: &ClientConfig, credentials: Option<(String, String)>) -> Self {
+        let private = credentials.map(|(api_key, api_secret)| {
+            let topics = BYBIT_PRIVATE_TOPICS.map(String::from).to_vec();
+            BybitWs::with_config(api_key, api_secret, topics, config.ws_private())
+        });
+        Self::from_clients(
+            BybitPublicWs::with_config(vec![], config.ws_public(BybitCategory::Linear)),
+            private,
+        )
+    }
+
+    /// The private client has to be subscribed to `BYBIT_PRIVATE_TOPICS`.
+    pub fn from_clients(public: BybitPublicWs, private: Option<BybitWs>) -> Self {
+        Self {
+            public,
+            private,
+            books: HashMap::new(),
+            tickers: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn on_state(&mut self, state: ConnectionState) {
+        match state {
+            ConnectionState::Connected => self.pending.push_back(WsEvent::Connected),
+            ConnectionState::Disconnected(reason) => {
+                self.pending.push_back(WsEvent::Disconnected(reason))
+            }
+            ConnectionState::Reconnecting { .. } => {}
+        }
+    }
+
+    fn on_public_update(&mut self, update: &BybitPublicUpdate) -> Result<()> {
+        match &update.data {
+            BybitPublicData::Orderbook(data) => {
+                let Some(book) = self.books.get_mut(&data.symbol) else {
+                    return Ok(());
+                };
+                // updates of a topic replaced by a deeper one may still arrive
+                if update.topic != book.topic.to_string() {
+                    return Ok(());
+                }
+                if let Err(err) = book.book.apply_bybit_update(update) {
+                    // the book is rebuilt from the snapshot sent on resubscription,
+                    // deltas are ignored until then
+                    warn!("{} book out of sync: {err}, resubscribing", data.symbol);
+                    self.public.resubscribe(vec![book.topic.clone()]);
+                }
+                if book.book.is_synced() {
+                    let orderbook = book.book.to_orderbook(Some(book.depth));
+                    self.pending.push_back(WsEvent::BookUpdate(orderbook));
+                }
+            }
+            BybitPublicData::PublicTrade(trades) => {
+                for trade in trades {
+                    let trade = Trade::from_bybit_public_trade(trade)?;
+                    self.pending.push_back(WsEvent::Trade(trade));
+                }
+            }
+            BybitPublicData::Ticker(ticker) => {
+                let ticker = Ticker::from_bybit_ticker(ticker, update.ts)?;
+                let ticker = match (&update.update_type, self.tickers.get_mut(&ticker.symbol)) {
+                    (BybitUpdateType::Delta, Some(last)) => {
+                        last.merge(ticker);
+                        last.clone()
+                    }
+                    _ => {
+                        self.tickers.insert(ticker.symbol.clone(), ticker.clone());
+                        ticker
+                    }
+                };
+                self.pending.push_back(WsEvent::Ticker(ticker));
+            }
+            BybitPublicData::Kline(_) | BybitPublicData::Liquidation(_) => {}
+        }
+        Ok(())
+    }
+
+    fn on_private_update(&mut self, data: &BybitUpdateData) -> Result<()> {
+        match data {
+            BybitUpdateData::OrderLinear(orders) => {
+                for order in orders {
+                    let order = Order::from_bybit_order(&order.order)?;
+                    self.pending.push_back(WsEvent::OrderUpdate(order));
+                }
+            }
+            BybitUpdateData::ExecutionLinear(executions) => {
+                // funding, ADL and settlement executions are not fills of our orders
+                for execution in executions.iter().filter(|e| e.exec_type == "Trade") {
+                    let fill = Fill::from_bybit_execution(execution)?;
+                    self.pending.push_back(WsEvent::Fill(fill));
+                }
+            }
+            BybitUpdateData::PositionLinear(positions) => {
+                for position in positions {
+                    let position = Position::from_bybit_ws_position(position)?;
+                    self.pending.push_back(WsEvent::PositionUpdate(position));
+                }
+            }
+            BybitUpdateData::Wallet(wallets) => {
+                for coin in wallets.iter().flat_map(|wallet| wallet.coin.iter()) {
+                    let balance = Balance::from_bybit_coin(coin)?;
+                    self.pending.push_back(WsEvent::BalanceUpdate(balance));
+                }
+            }
+            // other categories are not subscribed
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InterfaceWs for BybitWsWrapper {
+    fn subscribe(&mut self, subscriptions: &[Subscription]) {
+        let mut topics = vec![];
+        for subscription in subscriptions {
+            match subscription {
+                Subscription::Orderbook { symbol, depth } => {
+                    let venue_depth = BYBIT_BOOK_DEPTHS
+                        .into_iter()
+                        .find(|venue_depth| *venue_depth as usize >= *depth)
+                        .unwrap_or(BYBIT_BOOK_DEPTHS[3]);
+                    let topic = BybitPublicTopic::Orderbook {
+                        depth: venue_depth,
+                        symbol: symbol.clone(),
+                    };
+                    if let Some(book) = self.books.get_mut(symbol) {
+                        book.depth = *depth;
+                        let BybitPublicTopic::Orderbook {
+                            depth: current_depth,
+                            ..
+                        } = book.topic
+                        else {
+                            continue;
+                        };
+                        if venue_depth > current_depth {
+                            // rebuilt from the snapshot of the deeper topic
+                            self.public.unsubscribe(vec![book.topic.clone()]);
+                            book.book.reset();
+                            book.topic = topic.clone();
+                            topics.push(topic);
+                        }
+                        continue;
+                    }
+                    self.books.insert(
+                        symbol.clone(),
+                        BybitBook {
+                            book: LocalOrderbook::new(symbol),
+                            topic: topic.clone(),
+                            depth: *depth,
+                        },
+                    );
+                    topics.push(topic);
+                }
+                Subscription::Trades(symbol) => {
+                    topics.push(BybitPublicTopic::PublicTrade(symbol.clone()))
+                }
+                Subscription::Ticker(symbol) => {
+                    topics.push(BybitPublicTopic::Tickers(symbol.clone()))
+                }
+            }
+        }
+        if !topics.is_empty() {
+            self.public.subscribe(topics);
+        }
+    }
+
+    async fn next(&mut self) -> Option<WsEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            let event = match self.private.as_mut() {
+                Some(private) => tokio::select! {
+                    event = self.public.next() => BybitStreamEvent::Public(event),
+                    event = private.next() => BybitStreamEvent::Private(event),
+                },
+                None => BybitStreamEvent::Public(self.public.next().await),
+            };
+            let res = match event {
+                BybitStreamEvent::Public(Some(BybitPublicWsEvent::Update(update))) => {
+                    match update {
+                        BybitPublicWsUpdate::Data(update) => self.on_public_update(&update),
+                        BybitPublicWsUpdate::OpResponse(_) => Ok(()),
+                    }
+                }
+                BybitStreamEvent::Private(Some(Ok(event))) => match event {
+                    BybitWsEvent::Update(data) => self.on_private_update(&data),
+                    BybitWsEvent::AuthFailed(msg) => Err(Error::AuthFailed(msg)),
+                    BybitWsEvent::Subscribed(response) if !response.success => {
+                        Err(Error::InvalidParams(response.ret_msg))
+                    }
+                    BybitWsEvent::Authenticated | BybitWsEvent::Subscribed(_) => Ok(()),
+                    BybitWsEvent::State(state) => {
+                        self.on_state(state);
+                        Ok(())
+                    }
+                },
+                BybitStreamEvent::Private(Some(Err(err))) => Err(err.into()),
+                BybitStreamEvent::Public(Some(BybitPublicWsEvent::State(state))) => {
+                    self.on_state(state);
+                    Ok(())
+                }
+                BybitStreamEvent::Public(None) | BybitStreamEvent::Private(None) => return None,
+            };
+            if let Err(err) = res {
+                self.pending.push_back(WsEvent::Error(err));
+            }
+        }
     }
 }
 
 impl Orderbook {
-    fn from_bybit_orderbook(orderbook: OrderbookResponse, symbol: String) -> Self {
-        Orderbook {
-            symbol: symbol,
-            asks: orderbook
-                .asks
-                .into_iter()
-                .map(|ask| OrderbookLevel {
-                    price: ask[0].parse::<f64>().unwrap(),
-                    amount: ask[1].parse::<f64>().unwrap(),
-                })
-                .collect(),
-            bids: orderbook
-                .bids
+    fn from_bybit_orderbook(orderbook: OrderbookResponse, symbol: String) -> Result<Self> {
+        Ok(Orderbook {
+            symbol,
+            asks: OrderbookLevel::parse_levels(&orderbook.asks)?,
+            bids: OrderbookLevel::parse_levels(&orderbook.bids)?,
+            timestamp_ms: orderbook.timestamp,
+            update_id: orderbook.update_id,
+        })
+    }
+}
+
+impl LocalOrderbook {
+    /// Applies a message of the `orderbook.{depth}.{symbol}` topic. Bybit
+    /// increments `u` by one per message, a snapshot resets the book.
+    pub fn apply_bybit_update(&mut self, update: &BybitPublicUpdate) -> Result<()> {
+        let BybitPublicData::Orderbook(data) = &update.data else {
+            return Err(Error::InvalidParams(format!(
+                "{} is not an orderbook topic",
+                update.topic
+            )));
+        };
+        let bids = OrderbookLevel::parse_levels(&data.bids)?;
+        let asks = OrderbookLevel::parse_levels(&data.asks)?;
+        match update.update_type {
+            BybitUpdateType::Snapshot => {
+                self.apply_snapshot(&Orderbook {
+                    symbol: data.symbol.clone(),
+                    asks,
+                    bids,
+                    timestamp_ms: update.ts,
+                    update_id: data.update_id,
+                });
+                Ok(())
+            }
+            BybitUpdateType::Delta => self.apply_delta(&OrderbookDelta {
+                bids,
+                asks,
+                prev_update_id: data.update_id - 1,
+                update_id: data.update_id,
+                timestamp_ms: update.ts,
+            }),
+        }
+    }
+}
+
+impl Instrument {
+    fn from_bybit_instrument(info: &InstrumentInfo) -> Result<Self> {
+        let contract_type = match info.contract_type.as_str() {
+            "LinearPerpetual" | "InversePerpetual" => ContractType::Perpetual,
+            "LinearFutures" | "InverseFutures" => ContractType::Futures,
+            other => ContractType::Other(other.to_string()),
+        };
+        let lot_size = &info.lot_size_filter;
+        Ok(Instrument {
+            symbol: info.symbol.clone(),
+            contract_type,
+            settle_coin: info.settle_coin.clone(),
+            tick_size: parse_number(&info.price_filter.tick_size, "tick size")?,
+            qty_step: parse_number(&lot_size.qty_step, "qty step")?,
+            min_qty: parse_number(&lot_size.min_order_qty, "min qty")?,
+            max_qty: parse_number(&lot_size.max_order_qty, "max qty")?,
+            min_notional: parse_number(&lot_size.min_notional_value, "min notional")?,
+        })
+    }
+}
+
+impl CancelOrderResponse {
+    fn from_bybit_order_response(res: OrderResponse) -> Self {
+        CancelOrderResponse {
+            order_id: res.order_id,
+            order_link_id: res.order_link_id,
+        }
+    }
+}
+
+impl CancelAllOrdersResponse {
+    fn from_bybit_cancel_all_orders_response(res: BybitCancelAllOrdersResponse) -> Self {
+        CancelAllOrdersResponse {
+            cancelled: res
+                .list
                 .into_iter()
-                .map(|bid| OrderbookLevel {
-                    price: bid[0].parse::<f64>().unwrap(),
-                    amount: bid[1].parse::<f64>().unwrap(),
-                })
+                .map(CancelOrderResponse::from_bybit_order_response)
                 .collect(),
-            timestamp_ms: orderbook.timestamp,
+        }
+    }
+}
+
+impl Side {
+    fn from_bybit_side(side: &BybitSide) -> Result<Self> {
+        match side {
+            BybitSide::Buy => Ok(Side::Buy),
+            BybitSide::Sell => Ok(Side::Sell),
+            BybitSide::Flat => Err(Error::Deserialize("unexpected empty side".to_string())),
+        }
+    }
+}
+
+impl OrderStatus {
+    fn from_bybit_order_status(status: &BybitOrderStatus) -> Self {
+        match status {
+            BybitOrderStatus::New | BybitOrderStatus::Triggered => OrderStatus::New,
+            BybitOrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+            BybitOrderStatus::Filled => OrderStatus::Filled,
+            BybitOrderStatus::Cancelled
+            | BybitOrderStatus::PartiallyFilledCanceled
+            | BybitOrderStatus::Deactivated => OrderStatus::Cancelled,
+            BybitOrderStatus::Rejected => OrderStatus::Rejected,
+            BybitOrderStatus::Untriggered => OrderStatus::Untriggered,
+        }
+    }
+}
+
+impl Balance {
+    /// `availableToWithdraw` is deprecated and empty on unified accounts, the
+    /// available balance is what the orders and positions do not lock.
+    fn from_bybit_coin(coin: &WalletBalanceCoin) -> Result<Self> {
+        let total = parse_number(&coin.wallet_balance, "wallet balance")?;
+        let used = parse_number(&coin.locked, "locked balance")?
+            + parse_number(&coin.total_order_im, "order initial margin")?
+            + parse_number(&coin.total_position_im, "position initial margin")?;
+        Ok(Balance {
+            asset: coin.coin.clone(),
+            total,
+            available: Some((total - used).max(0.0)),
+            unrealised_pnl: parse_optional_number(
+                coin.unrealised_pnl.as_deref(),
+                "unrealised pnl",
+            )?,
+        })
+    }
+}
+
+impl Position {
+    fn from_bybit_position(position: &BybitLinearPosition) -> Result<Self> {
+        let liquidation_price = parse_number(&position.liq_price, "liquidation price")?;
+        Ok(Position {
+            symbol: position.symbol.clone(),
+            side: Some(Side::from_bybit_side(&position.side)?),
+            size: parse_number(&position.size, "size")?,
+            entry_price: parse_number(&position.avg_price, "entry price")?,
+            mark_price: Some(parse_number(&position.mark_price, "mark price")?),
+            unrealised_pnl: parse_number(&position.unrealised_pnl, "unrealised pnl")?,
+            leverage: Some(parse_number(&position.leverage, "leverage")?),
+            liquidation_price: (liquidation_price > 0.0).then_some(liquidation_price),
+        })
+    }
+}
+
+impl Order {
+    fn from_bybit_order(order: &GetOrderResponseData) -> Result<Self> {
+        Ok(Order {
+            order_id: order.order_id.clone(),
+            order_link_id: order.order_link_id.clone(),
+            symbol: order.symbol.clone(),
+            side: Side::from_bybit_side(&order.side)?,
+            price: parse_number(&order.price, "price")?,
+            amount: parse_number(&order.qty, "qty")?,
+            filled_amount: parse_number(&order.cum_exec_qty, "executed qty")?,
+            status: OrderStatus::from_bybit_order_status(&order.order_status),
+            created_time_ms: Some(parse_number(&order.created_time, "created time")? as i64),
+            updated_time_ms: parse_number(&order.updated_time, "updated time")? as i64,
+        })
+    }
+}
+
+impl Fill {
+    fn from_bybit_execution(execution: &BybitExecutionData) -> Result<Self> {
+        Ok(Fill {
+            order_id: execution.order_id.clone(),
+            order_link_id: execution.order_link_id.clone(),
+            trade_id: execution.exec_id.clone(),
+            symbol: execution.symbol.clone(),
+            side: Side::from_bybit_side(&execution.side)?,
+            price: parse_number(&execution.exec_price, "exec price")?,
+            amount: parse_number(&execution.exec_qty, "exec qty")?,
+            fee: parse_number(&execution.exec_fee, "exec fee")?,
+            fee_asset: None,
+            is_maker: execution.is_maker,
+            timestamp_ms: parse_number(&execution.exec_time, "exec time")? as i64,
+        })
+    }
+}
+
+impl Position {
+    /// Unlike REST, the stream reports closed positions, with an empty side.
+    fn from_bybit_ws_position(position: &BybitLinearPosition) -> Result<Self> {
+        if position.side != BybitSide::Flat {
+            return Self::from_bybit_position(position);
+        }
+        Ok(Position {
+            symbol: position.symbol.clone(),
+            side: None,
+            size: 0.0,
+            entry_price: 0.0,
+            mark_price: Some(parse_number(&position.mark_price, "mark price")?),
+            unrealised_pnl: 0.0,
+            leverage: Some(parse_number(&position.leverage, "leverage")?),
+            liquidation_price: None,
+        })
+    }
+}
+
+impl Trade {
+    fn from_bybit_public_trade(trade: &BybitPublicTradeData) -> Result<Self> {
+        Ok(Trade {
+            symbol: trade.symbol.clone(),
+            trade_id: trade.trade_id.clone(),
+            side: Side::from_bybit_side(&trade.side)?,
+            price: parse_number(&trade.price, "price")?,
+            amount: parse_number(&trade.size, "size")?,
+            timestamp_ms: trade.timestamp,
+        })
+    }
+}
+
+impl Ticker {
+    fn from_bybit_ticker(ticker: &BybitTickerData, timestamp_ms: i64) -> Result<Self> {
+        Ok(Ticker {
+            symbol: ticker.symbol.clone(),
+            bid_price: parse_optional_number(ticker.bid1_price.as_deref(), "bid price")?,
+            bid_amount: parse_optional_number(ticker.bid1_size.as_deref(), "bid size")?,
+            ask_price: parse_optional_number(ticker.ask1_price.as_deref(), "ask price")?,
+            ask_amount: parse_optional_number(ticker.ask1_size.as_deref(), "ask size")?,
+            last_price: parse_optional_number(ticker.last_price.as_deref(), "last price")?,
+            mark_price: parse_optional_number(ticker.mark_price.as_deref(), "mark price")?,
+            funding_rate: parse_optional_number(ticker.funding_rate.as_deref(), "funding rate")?,
+            timestamp_ms,
+        })
+    }
+}
+
+impl Error {
+    /// Maps a Bybit v5 `retCode` to the matching variant.
+    fn from_bybit_code(code: i64, msg: String) -> Self {
+        match code {
+            10003 | 10004 | 10005 | 10007 | 10009 | 10010 | 33004 => Error::AuthFailed(msg),
+            10006 | 10018 => Error::RateLimited(msg),
+            10000 | 10016 => Error::Timeout(msg),
+            10001 => Error::InvalidParams(msg),
+            110001 => Error::OrderNotFound(msg),
+            110003 | 110094 => Error::InvalidPrice(msg),
+            110004 | 110006 | 110007 | 110012 | 110045 => Error::InsufficientBalance(msg),
+            110017 | 110018 => Error::InvalidQuantity(msg),
+            _ => Error::Api { code, msg },
+        }
+    }
+}
+
+impl From<BybitError> for Error {
+    fn from(err: BybitError) -> Self {
+        match err {
+            BybitError::ApiError(code, msg) => Error::from_bybit_code(code, msg),
+            BybitError::HttpError(err) if err.is_timeout() => Error::Timeout(err.to_string()),
+            BybitError::HttpError(err) => Error::Network(err.to_string()),
+            BybitError::ServerError(status, body) => {
+                Error::Network(format!("status {status}: {body}"))
+            }
+            BybitError::DeserializeError(msg) => Error::Deserialize(msg),
+            BybitError::WsError(msg) => Error::Network(msg),
+            BybitError::Timeout(msg) => Error::Timeout(msg),
+            BybitError::RateLimited(msg) => Error::RateLimited(msg),
+            BybitError::Unknown(msg) => Error::Unknown(msg),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::exchanges::bybit::BybitHttpWrapper;
+    use cefi_rs_bybit::{transport::MockTransport, ws::WsConfig as BybitWsConfig};
+    use futures::{SinkExt, StreamExt};
+    use reqwest::Method;
+    use serde_json::json;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+    use wiremock::{
+        matchers::{body_partial_json, header_exists, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use super::*;
 
+    fn ok_response(result: serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "retCode": 0,
+            "retMsg": "OK",
+            "result": result,
+            "retExtInfo": {},
+            "time": 1727663866609u64
+        }))
+    }
+
+    fn mock_wrapper(server: &MockServer) -> BybitHttpWrapper {
+        let client = BybitHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        BybitHttpWrapper::from_client(client)
+    }
+
     #[tokio::test]
     async fn test_get_orderbook() {
-        let bybit = BybitHttpWrapper::new("".to_string(), "".to_string());
-        let orderbook = bybit
-            .get_orderbook(&"BTCUSDT".to_string(), Some(10))
+        let transport = Arc::new(MockTransport::new());
+        transport.push_ok(
+            Method::GET,
+            "v5/market/orderbook",
+            &json!({
+                "retCode": 0,
+                "retMsg": "OK",
+                "result": {
+                    "s": "BTCUSDT",
+                    "a": [["65557.7", "16.606555"]],
+                    "b": [["65485.47", "47.081829"], ["65485.1", "0.002"]],
+                    "ts": 1716863719031u64,
+                    "u": 230704,
+                    "seq": 1432604333,
+                    "cts": 1716863718905u64
+                },
+                "retExtInfo": {},
+                "time": 1716863719382u64
+            })
+            .to_string(),
+        );
+        let client = BybitHttp::new("".to_string(), "".to_string())
+            .unwrap()
+            .with_transport(transport.clone());
+        let bybit = BybitHttpWrapper::from_client(client);
+
+        let orderbook = bybit.get_orderbook("BTCUSDT", None).await.unwrap();
+        assert_eq!(orderbook.update_id, 230704);
+        assert_eq!(orderbook.bids[1].price, 65485.1);
+        assert_eq!(orderbook.asks[0].amount, 16.606555);
+        let requests = transport.requests();
+        assert_eq!(requests[0].query_param("limit").unwrap(), "10");
+    }
+
+    #[tokio::test]
+    async fn test_place_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .and(header_exists("X-BAPI-SIGN"))
+            .and(body_partial_json(json!({
+                "category": "linear",
+                "symbol": "SOLUSDT",
+                "side": "Sell",
+                "orderType": "Limit",
+                "price": "110.5",
+                "qty": "0.1",
+                "timeInForce": "GTC",
+                "positionIdx": 0,
+            })))
+            .respond_with(ok_response(json!({
+                "orderId": "29c242ff-7da2-47d4-a243-ba6ff034005e",
+                "orderLinkId": ""
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit
+            .place_order(&PlaceOrderParams::limit("SOLUSDT", false, 110.5, 0.1))
+            .await
+            .unwrap();
+        assert_eq!(res.order_id, "29c242ff-7da2-47d4-a243-ba6ff034005e");
+        assert_eq!(res.order_link_id, "");
+    }
+
+    #[tokio::test]
+    async fn test_place_take_profit_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .and(body_partial_json(json!({
+                "side": "Sell",
+                "orderType": "Market",
+                "qty": "0.1",
+                "triggerPrice": "150",
+                "triggerDirection": 1,
+                "reduceOnly": true,
+                "positionIdx": 1,
+                "orderLinkId": "tp-1",
+            })))
+            .respond_with(ok_response(json!({
+                "orderId": "9d1c2a9e-2b5e-4d8f-a2c1-1f0e0d4b7c11",
+                "orderLinkId": "tp-1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let params = PlaceOrderParams {
+            order_type: OrderType::TakeProfitMarket,
+            trigger_price: Some(150.0),
+            reduce_only: true,
+            position_idx: PositionIdx::HedgeLong,
+            client_order_id: Some("tp-1".to_string()),
+            ..PlaceOrderParams::market("SOLUSDT", false, 0.1)
+        };
+        let res = bybit.place_order(&params).await.unwrap();
+        assert_eq!(res.order_link_id, "tp-1");
+    }
+
+    #[test]
+    fn test_place_order_request() {
+        let post_only = PlaceOrderParams {
+            time_in_force: TimeInForce::PostOnly,
+            ..PlaceOrderParams::limit("SOLUSDT", true, 110.0, 0.1)
+        };
+        let request = place_order_request(&post_only).unwrap();
+        assert_eq!(request.time_in_force, Some(BybitTimeInForce::PostOnly));
+        assert_eq!(request.trigger_direction, None);
+
+        let stop_limit = PlaceOrderParams {
+            order_type: OrderType::StopLimit,
+            trigger_price: Some(90.0),
+            ..PlaceOrderParams::limit("SOLUSDT", false, 89.5, 0.1)
+        };
+        let request = place_order_request(&stop_limit).unwrap();
+        assert_eq!(request.order_type, BybitOrderType::Limit);
+        assert_eq!(request.price.as_deref(), Some("89.5"));
+        assert_eq!(request.trigger_price.as_deref(), Some("90"));
+        assert_eq!(request.trigger_direction, Some(BybitTriggerDirection::Fall));
+
+        let market = place_order_request(&PlaceOrderParams::market("SOLUSDT", true, 0.1)).unwrap();
+        assert_eq!(market.order_type, BybitOrderType::Market);
+        assert_eq!(market.time_in_force, None);
+
+        let post_only_market = PlaceOrderParams {
+            time_in_force: TimeInForce::PostOnly,
+            ..PlaceOrderParams::market("SOLUSDT", true, 0.1)
+        };
+        assert!(place_order_request(&post_only_market).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_place_order_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "retCode": 10001,
+                "retMsg": "Qty invalid",
+                "result": {},
+                "retExtInfo": {},
+                "time": 1727663049561u64
+            })))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit
+            .place_order(&PlaceOrderParams::limit("SOLUSDT", true, 110.0, 1e9))
+            .await;
+        assert_eq!(res, Err(Error::InvalidParams("Qty invalid".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/cancel"))
+            .and(body_partial_json(json!({
+                "symbol": "SOLUSDT",
+                "orderId": "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+            })))
+            .respond_with(ok_response(json!({
+                "orderId": "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+                "orderLinkId": "linear-004"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit
+            .cancel_order("SOLUSDT", "c6f055d9-7f21-4079-913d-e6523a9cfffa")
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            CancelOrderResponse {
+                order_id: "c6f055d9-7f21-4079-913d-e6523a9cfffa".to_string(),
+                order_link_id: "linear-004".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/cancel-all"))
+            .and(body_partial_json(json!({"symbol": "SOLUSDT"})))
+            .respond_with(ok_response(json!({
+                "list": [
+                    {"orderId": "1616024329462743808", "orderLinkId": "1616024329462743809"},
+                    {"orderId": "1616024287544869632", "orderLinkId": "1616024287544869633"}
+                ],
+                "success": "1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit.cancel_all_orders("SOLUSDT").await.unwrap();
+        assert_eq!(res.cancelled.len(), 2);
+        assert_eq!(res.cancelled[1].order_id, "1616024287544869632");
+    }
+
+    #[tokio::test]
+    async fn test_amend_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/amend"))
+            .and(body_partial_json(json!({
+                "symbol": "SOLUSDT",
+                "orderId": "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+                "price": "105",
+            })))
+            .respond_with(ok_response(json!({
+                "orderId": "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+                "orderLinkId": ""
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit
+            .amend_order(
+                "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+                &AmendOrderParams {
+                    symbol: "SOLUSDT".to_string(),
+                    price: Some(105.0),
+                    amount: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.order_id, "c6f055d9-7f21-4079-913d-e6523a9cfffa");
+    }
+
+    #[tokio::test]
+    async fn test_amend_order_without_changes() {
+        let bybit = BybitHttpWrapper::new("".to_string(), "".to_string()).unwrap();
+        let res = bybit
+            .amend_order(
+                "c6f055d9-7f21-4079-913d-e6523a9cfffa",
+                &AmendOrderParams {
+                    symbol: "SOLUSDT".to_string(),
+                    price: None,
+                    amount: None,
+                },
+            )
+            .await;
+        assert!(matches!(res, Err(Error::InvalidParams(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_order_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "retCode": 110001,
+                "retMsg": "order not exists or too late to cancel",
+                "result": {},
+                "retExtInfo": {},
+                "time": 1727663049561u64
+            })))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let res = bybit.cancel_order("SOLUSDT", "unknown").await;
+        assert!(matches!(res, Err(Error::OrderNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_network_error() {
+        // nothing listens on the discard port
+        let client = BybitHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host("http://127.0.0.1:9".to_string());
+        let res = BybitHttpWrapper::from_client(client)
+            .cancel_all_orders("SOLUSDT")
+            .await;
+        assert!(matches!(res, Err(Error::Network(_))));
+    }
+
+    #[test]
+    fn test_from_bybit_code() {
+        assert!(matches!(
+            Error::from_bybit_code(10006, "Too many visits".to_string()),
+            Error::RateLimited(_)
+        ));
+        assert!(matches!(
+            Error::from_bybit_code(10003, "API key is invalid.".to_string()),
+            Error::AuthFailed(_)
+        ));
+        assert!(matches!(
+            Error::from_bybit_code(110007, "ab not enough for new order".to_string()),
+            Error::InsufficientBalance(_)
+        ));
+        assert_eq!(
+            Error::from_bybit_code(110025, "Position mode is not modified".to_string()),
+            Error::Api {
+                code: 110025,
+                msg: "Position mode is not modified".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_balances() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/account/wallet-balance"))
+            .and(query_param("accountType", "UNIFIED"))
+            .respond_with(ok_response(json!({"list": [{
+                "totalEquity": "3.31216591", "accountIMRate": "0", "totalMarginBalance": "3.00326056",
+                "totalInitialMargin": "0", "accountType": "UNIFIED", "totalAvailableBalance": "3.00326056",
+                "accountMMRate": "0", "totalPerpUPL": "0", "totalWalletBalance": "3.00326056",
+                "accountLTV": "0", "totalMaintenanceMargin": "0",
+                "coin": [{
+                    "availableToBorrow": "", "bonus": "0", "accruedInterest": "0",
+                    "availableToWithdraw": "", "totalOrderIM": "100", "equity": "1000.5",
+                    "totalPositionMM": "2.5", "usdValue": "1000.45", "unrealisedPnl": "-1.5",
+                    "collateralSwitch": true, "spotHedgingQty": "0", "borrowAmount": "0",
+                    "totalPositionIM": "50.5", "walletBalance": "1002", "cumRealisedPnl": "0",
+                    "locked": "0", "marginCollateral": true, "coin": "USDT"
+                }]
+            }]})))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let balances = bybit.get_balances().await.unwrap();
+        assert_eq!(
+            balances,
+            vec![Balance {
+                asset: "USDT".to_string(),
+                total: 1002.0,
+                available: Some(851.5),
+                unrealised_pnl: Some(-1.5),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_positions() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/position/list"))
+            .respond_with(ok_response(json!({
+                "category": "linear",
+                "nextPageCursor": "",
+                "list": [
+                    {"avgPrice":"149.127428","createdTime":"1715069854842","leverage":"10","liqPrice":"","markPrice":"173.732","positionBalance":"7.49328044","positionIM":"0.9916974","positionIdx":0,"positionMM":"0.0000325","positionStatus":"Normal","positionValue":"74.563714","riskLimitValue":"210000","side":"Buy","size":"0.5","symbol":"SOLUSDT","unrealisedPnl":"12.302286","updatedTime":"1730087396775"},
+                    {"avgPrice":"0","createdTime":"1715069854842","leverage":"10","liqPrice":"","markPrice":"1.69352","positionBalance":"0","positionIM":"0","positionIdx":0,"positionMM":"0","positionStatus":"Normal","positionValue":"0","riskLimitValue":"200000","side":"","size":"0","symbol":"SUIUSDT","unrealisedPnl":"0","updatedTime":"1730087396775"}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let positions = bybit.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].symbol, "SOLUSDT");
+        assert_eq!(positions[0].side, Some(Side::Buy));
+        assert_eq!(positions[0].entry_price, 149.127428);
+        assert_eq!(positions[0].liquidation_price, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/order/realtime"))
+            .and(query_param("orderId", "fd4300ae-7847-404e-b947-b46980a4d140"))
+            .respond_with(ok_response(json!({
+                "category": "linear",
+                "nextPageCursor": "",
+                "list": [{
+                    "orderId": "fd4300ae-7847-404e-b947-b46980a4d140", "orderLinkId": "test-000005",
+                    "symbol": "ETHUSDT", "price": "1600.00", "qty": "0.10", "side": "Buy",
+                    "orderStatus": "PartiallyFilled", "createType": "CreateByUser", "cancelType": "UNKNOWN",
+                    "rejectReason": "EC_NoError", "leavesQty": "0.05", "cumExecQty": "0.05",
+                    "createdTime": "1684738540559", "updatedTime": "1684738540561"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let order = bybit
+            .get_order("ETHUSDT", "fd4300ae-7847-404e-b947-b46980a4d140")
             .await
             .unwrap();
-        println!("orderbook: {:?}", orderbook);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(order.filled_amount, 0.05);
+        assert_eq!(order.created_time_ms, Some(1684738540559));
+    }
+
+    #[tokio::test]
+    async fn test_get_instrument() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/instruments-info"))
+            .and(query_param("category", "linear"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .respond_with(ok_response(json!({
+                "category": "linear",
+                "list": [{
+                    "symbol": "BTCUSDT",
+                    "contractType": "LinearPerpetual",
+                    "status": "Trading",
+                    "baseCoin": "BTC",
+                    "quoteCoin": "USDT",
+                    "launchTime": "1585526400000",
+                    "deliveryTime": "0",
+                    "deliveryFeeRate": "",
+                    "priceScale": "2",
+                    "leverageFilter": {"minLeverage": "1", "maxLeverage": "100.00", "leverageStep": "0.01"},
+                    "priceFilter": {"minPrice": "0.10", "maxPrice": "1999999.80", "tickSize": "0.10"},
+                    "lotSizeFilter": {
+                        "maxOrderQty": "1190.000",
+                        "minOrderQty": "0.001",
+                        "qtyStep": "0.001",
+                        "postOnlyMaxOrderQty": "1190.000",
+                        "maxMktOrderQty": "500.000",
+                        "minNotionalValue": "5"
+                    },
+                    "unifiedMarginTrade": true,
+                    "fundingInterval": 480,
+                    "settleCoin": "USDT",
+                    "copyTrading": "both",
+                    "upperFundingRate": "0.00375",
+                    "lowerFundingRate": "-0.00375",
+                    "isPreListing": false,
+                    "preListingInfo": null,
+                    "riskParameters": {"priceLimitRatioX": "0.01", "priceLimitRatioY": "0.02"}
+                }],
+                "nextPageCursor": ""
+            })))
+            .mount(&server)
+            .await;
+
+        let bybit = mock_wrapper(&server);
+        let instrument = bybit.get_instrument("BTCUSDT").await.unwrap();
+        assert_eq!(instrument.contract_type, ContractType::Perpetual);
+        assert_eq!(instrument.settle_coin, "USDT");
+        assert_eq!(instrument.tick_size, 0.1);
+        assert_eq!(instrument.qty_step, 0.001);
+        assert_eq!(instrument.max_qty, 1190.0);
+        assert_eq!(instrument.min_notional, 5.0);
+    }
+
+    #[test]
+    fn test_apply_bybit_orderbook_updates() {
+        let snapshot = r#"{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"],["30245.40","0.224"]],"a":[["30248.70","0.5"],["30249.30","1.2"]],"u":177400506,"seq":66544703341},"cts":1687940967464}"#;
+        let delta = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967566,"data":{"s":"BTCUSDT","b":[["30247.20","0"],["30246.00","1.5"]],"a":[["30248.70","0"]],"u":177400507,"seq":66544703342},"cts":1687940967564}"#;
+        let gap = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967666,"data":{"s":"BTCUSDT","b":[],"a":[],"u":177400509,"seq":66544703350},"cts":1687940967664}"#;
+
+        let mut book = LocalOrderbook::new("BTCUSDT");
+        for message in [snapshot, delta] {
+            let update = serde_json::from_str::<BybitPublicUpdate>(message).unwrap();
+            book.apply_bybit_update(&update).unwrap();
+        }
+        assert_eq!(
+            book.best_bid(),
+            Some(OrderbookLevel {
+                price: 30246.0,
+                amount: 1.5
+            })
+        );
+        assert_eq!(book.best_ask().unwrap().price, 30249.3);
+        assert_eq!(book.update_id(), 177400507);
+
+        let update = serde_json::from_str::<BybitPublicUpdate>(gap).unwrap();
+        assert!(matches!(
+            book.apply_bybit_update(&update),
+            Err(Error::SequenceGap { .. })
+        ));
+        assert!(!book.is_synced());
+    }
+
+    async fn local_ws_server() -> (TcpListener, BybitWsConfig) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = BybitWsConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            ..BybitWsConfig::public(BybitCategory::Linear)
+        };
+        (listener, config)
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        accept_async(stream).await.unwrap()
+    }
+
+    async fn next_text(server: &mut WebSocketStream<TcpStream>) -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper_resyncs_book_on_gap() {
+        let (listener, config) = local_ws_server().await;
+        let mut ws = BybitWsWrapper::from_clients(BybitPublicWs::with_config(vec![], config), None);
+        ws.subscribe(&[Subscription::Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            depth: 1,
+        }]);
+
+        let mut public = accept(&listener).await;
+        assert_eq!(
+            next_text(&mut public).await["args"],
+            json!(["orderbook.1.BTCUSDT"])
+        );
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+        public.send(Message::Text(r#"{"topic":"orderbook.1.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"]],"a":[["30248.70","0.5"]],"u":177400506,"seq":66544703341},"cts":1687940967464}"#.to_string())).await.unwrap();
+        assert!(matches!(ws.next().await, Some(WsEvent::BookUpdate(_))));
+
+        // u 177400507 is missing
+        public.send(Message::Text(r#"{"topic":"orderbook.1.BTCUSDT","type":"delta","ts":1687940967566,"data":{"s":"BTCUSDT","b":[],"a":[["30248.80","1.2"]],"u":177400508,"seq":66544703350},"cts":1687940967564}"#.to_string())).await.unwrap();
+        let server = tokio::spawn(async move {
+            let unsubscribe = next_text(&mut public).await;
+            assert_eq!(unsubscribe["op"], "unsubscribe");
+            assert_eq!(unsubscribe["args"], json!(["orderbook.1.BTCUSDT"]));
+            let subscribe = next_text(&mut public).await;
+            assert_eq!(subscribe["op"], "subscribe");
+            assert_eq!(subscribe["args"], json!(["orderbook.1.BTCUSDT"]));
+            public.send(Message::Text(r#"{"topic":"orderbook.1.BTCUSDT","type":"snapshot","ts":1687940968466,"data":{"s":"BTCUSDT","b":[["30250.10","2.5"]],"a":[["30250.20","0.1"]],"u":177400600,"seq":66544703400},"cts":1687940968464}"#.to_string())).await.unwrap();
+            public
+        });
+        match ws.next().await {
+            Some(WsEvent::BookUpdate(book)) => {
+                assert_eq!(book.update_id, 177400600);
+                assert_eq!(book.bids[0].price, 30250.1);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper_deeper_book() {
+        let (listener, config) = local_ws_server().await;
+        let mut ws = BybitWsWrapper::from_clients(BybitPublicWs::with_config(vec![], config), None);
+        ws.subscribe(&[Subscription::Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            depth: 1,
+        }]);
+
+        let mut public = accept(&listener).await;
+        assert_eq!(
+            next_text(&mut public).await["args"],
+            json!(["orderbook.1.BTCUSDT"])
+        );
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+
+        ws.subscribe(&[Subscription::Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            depth: 100,
+        }]);
+        let unsubscribe = next_text(&mut public).await;
+        assert_eq!(unsubscribe["op"], "unsubscribe");
+        assert_eq!(unsubscribe["args"], json!(["orderbook.1.BTCUSDT"]));
+        let subscribe = next_text(&mut public).await;
+        assert_eq!(subscribe["op"], "subscribe");
+        assert_eq!(subscribe["args"], json!(["orderbook.200.BTCUSDT"]));
+
+        // the shallower topic is ignored once replaced
+        public.send(Message::Text(r#"{"topic":"orderbook.1.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"]],"a":[["30248.70","0.5"]],"u":177400506,"seq":66544703341},"cts":1687940967464}"#.to_string())).await.unwrap();
+        public.send(Message::Text(r#"{"topic":"orderbook.200.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"],["30247.10","1.5"]],"a":[["30248.70","0.5"]],"u":2203,"seq":66544703341},"cts":1687940967464}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::BookUpdate(book)) => {
+                assert_eq!(book.update_id, 2203);
+                assert_eq!(book.bids.len(), 2);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper_merges_tickers() {
+        let (listener, config) = local_ws_server().await;
+        let mut ws = BybitWsWrapper::from_clients(BybitPublicWs::with_config(vec![], config), None);
+        ws.subscribe(&[Subscription::Ticker("BTCUSDT".to_string())]);
+
+        let mut public = accept(&listener).await;
+        assert_eq!(
+            next_text(&mut public).await["args"],
+            json!(["tickers.BTCUSDT"])
+        );
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+
+        public.send(Message::Text(r#"{"topic":"tickers.BTCUSDT","type":"snapshot","data":{"symbol":"BTCUSDT","tickDirection":"PlusTick","price24hPcnt":"0.017103","lastPrice":"17216.00","prevPrice24h":"16926.50","highPrice24h":"17281.50","lowPrice24h":"16915.00","prevPrice1h":"17238.00","markPrice":"17217.33","indexPrice":"17227.36","openInterest":"68744.761","openInterestValue":"1183601235.91","turnover24h":"1570383121.943499","volume24h":"91705.276","nextFundingTime":"1673280000000","fundingRate":"-0.000212","bid1Price":"17215.50","bid1Size":"84.489","ask1Price":"17216.00","ask1Size":"83.020"},"cs":24987956059,"ts":1673272861686}"#.to_string())).await.unwrap();
+        public.send(Message::Text(r#"{"topic":"tickers.BTCUSDT","type":"delta","data":{"symbol":"BTCUSDT","bid1Price":"17215.60","bid1Size":"1.5"},"cs":24987956060,"ts":1673272861786}"#.to_string())).await.unwrap();
+
+        assert!(matches!(ws.next().await, Some(WsEvent::Ticker(_))));
+        match ws.next().await {
+            Some(WsEvent::Ticker(ticker)) => {
+                assert_eq!(ticker.bid_price, Some(17215.6));
+                assert_eq!(ticker.bid_amount, Some(1.5));
+                assert_eq!(ticker.ask_price, Some(17216.0));
+                assert_eq!(ticker.last_price, Some(17216.0));
+                assert_eq!(ticker.funding_rate, Some(-0.000212));
+                assert_eq!(ticker.timestamp_ms, 1673272861786);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_wrapper() {
+        let (public_listener, public_config) = local_ws_server().await;
+        let (private_listener, private_config) = local_ws_server().await;
+        let topics = BYBIT_PRIVATE_TOPICS.map(String::from).to_vec();
+        let mut ws = BybitWsWrapper::from_clients(
+            BybitPublicWs::with_config(vec![], public_config),
+            Some(BybitWs::with_config(
+                "key".to_string(),
+                "secret".to_string(),
+                topics,
+                private_config,
+            )),
+        );
+        ws.subscribe(&[
+            Subscription::Orderbook {
+                symbol: "BTCUSDT".to_string(),
+                depth: 1,
+            },
+            Subscription::Trades("BTCUSDT".to_string()),
+        ]);
+
+        let mut public = accept(&public_listener).await;
+        assert_eq!(
+            next_text(&mut public).await["args"],
+            json!(["orderbook.1.BTCUSDT", "publicTrade.BTCUSDT"])
+        );
+        let mut private = accept(&private_listener).await;
+        assert_eq!(next_text(&mut private).await["op"], "auth");
+        assert_eq!(
+            next_text(&mut private).await["args"],
+            json!([
+                "order.linear",
+                "execution.linear",
+                "position.linear",
+                "wallet"
+            ])
+        );
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+        assert_eq!(ws.next().await, Some(WsEvent::Connected));
+
+        public.send(Message::Text(r#"{"topic":"orderbook.1.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"]],"a":[["30248.70","0.5"]],"u":177400506,"seq":66544703341},"cts":1687940967464}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::BookUpdate(book)) => {
+                assert_eq!(book.bids[0].price, 30247.2);
+                assert_eq!(book.asks.len(), 1);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        public.send(Message::Text(r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false}]}"#.to_string())).await.unwrap();
+        assert_eq!(
+            ws.next().await,
+            Some(WsEvent::Trade(Trade {
+                symbol: "BTCUSDT".to_string(),
+                trade_id: "20f43950-d8dd-5b31-9112-a178eb6023af".to_string(),
+                side: Side::Buy,
+                price: 16578.5,
+                amount: 0.001,
+                timestamp_ms: 1672304486865,
+            }))
+        );
+
+        private.send(Message::Text(r#"{"id":"5923240c6880ab-c59f-420b-9adb-3639adc9dd90","topic":"execution.linear","creationTime":1672364174455,"data":[{"category":"linear","symbol":"XRPUSDT","execFee":"0.005061","execId":"7e2ae69c-4edf-5800-a352-893d52b446aa","execPrice":"0.3374","execQty":"25","execType":"Trade","execValue":"8.435","isMaker":false,"feeRate":"0.0006","markPrice":"0.3391","orderId":"f6e324ff-99c2-4e89-9739-3086e47f9381","orderLinkId":"","orderPrice":"0.3207","orderQty":"25","orderType":"Market","side":"Sell","execTime":"1672364174443","leavesQty":"0"},{"category":"linear","symbol":"XRPUSDT","execFee":"-0.001","execId":"0d6ba1c4-2d0a-5cae-9c5a-e7ff5d2de6f4","execPrice":"0.3374","execQty":"25","execType":"Funding","execValue":"8.435","isMaker":false,"feeRate":"-0.0001","markPrice":"0.3374","orderId":"","orderLinkId":"","orderPrice":"0","orderQty":"0","orderType":"UNKNOWN","side":"Sell","execTime":"1672364200000","leavesQty":"0"}]}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::Fill(fill)) => {
+                assert_eq!(fill.side, Side::Sell);
+                assert_eq!(fill.amount, 25.0);
+                assert_eq!(fill.fee, 0.005061);
+                assert!(!fill.is_maker);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        // the funding execution is not a fill
+        private.send(Message::Text(r#"{"topic":"position.linear","data":[{"avgPrice":"0","createdTime":"1676538056258","leverage":"10","liqPrice":"","markPrice":"0.3374","positionBalance":"0","positionIM":"0","positionIdx":0,"positionMM":"0","positionStatus":"Normal","positionValue":"0","riskLimitValue":"200000","side":"","size":"0","symbol":"XRPUSDT","unrealisedPnl":"0","updatedTime":"1676538056258"}]}"#.to_string())).await.unwrap();
+        match ws.next().await {
+            Some(WsEvent::PositionUpdate(position)) => {
+                assert_eq!(position.symbol, "XRPUSDT");
+                assert_eq!(position.side, None);
+                assert_eq!(position.size, 0.0);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        private.send(Message::Text(r#"{"id":"592324d2bce751-ad38-48eb-8f42-4671d1fb4d4e","topic":"wallet","creationTime":1700034722104,"data":[{"accountIMRate":"0","accountMMRate":"0","totalEquity":"10262.91335023","totalWalletBalance":"9684.46297164","totalMarginBalance":"9684.46297164","totalAvailableBalance":"9556.6056555","totalPerpUPL":"0","totalInitialMargin":"0","totalMaintenanceMargin":"0","coin":[{"coin":"USDT","equity":"1002.3","usdValue":"1002.1","walletBalance":"1002","availableToWithdraw":"1001.5","availableToBorrow":"","borrowAmount":"0","accruedInterest":"0","totalOrderIM":"0","totalPositionIM":"0.5","totalPositionMM":"0.01","unrealisedPnl":"0.3","cumRealisedPnl":"2","bonus":"0","collateralSwitch":true,"marginCollateral":true,"locked":"0","spotHedgingQty":"0"}],"accountLTV":"0","accountType":"UNIFIED"}]}"#.to_string())).await.unwrap();
+        assert_eq!(
+            ws.next().await,
+            Some(WsEvent::BalanceUpdate(Balance {
+                asset: "USDT".to_string(),
+                total: 1002.0,
+                available: Some(1001.5),
+                unrealised_pnl: Some(0.3),
+            }))
+        );
+
+        private.send(Message::Text(r#"{"success":false,"ret_msg":"Request not authorized","op":"auth","conn_id":"cnj7ulkfpa38vo3ucsjg-5"}"#.to_string())).await.unwrap();
+        assert_eq!(
+            ws.next().await,
+            Some(WsEvent::Error(Error::AuthFailed(
+                "Request not authorized".to_string()
+            )))
+        );
     }
 }
@@ -1,29 +1,52 @@
 use async_trait::async_trait;
 
-use crate::{trade::*, types::Orderbook};
+use crate::{
+    errors::Result,
+    trade::*,
+    types::{Balance, Instrument, Order, Orderbook, Position},
+};
 
 #[async_trait]
 pub trait InterfaceHttp {
-    async fn get_server_time(&self) -> anyhow::Result<u64>;
+    async fn get_server_time(&self) -> Result<u64>;
 
-    async fn get_orderbook(&self, symbol: &String, limit: Option<i32>)
-        -> anyhow::Result<Orderbook>;
+    async fn get_orderbook(&self, symbol: &str, limit: Option<i32>) -> Result<Orderbook>;
 
-    async fn place_order(&self, params: &PlaceOrderParams) -> anyhow::Result<PlaceOrderResponse>;
+    /// Returns the trading rules of a symbol, use them to round prices and
+    /// quantities before `place_order`.
+    async fn get_instrument(&self, symbol: &str) -> Result<Instrument>;
 
-    async fn cancel_order(&self, order_id: &String) -> anyhow::Result<CancelOrderResponse>;
+    async fn place_order(&self, params: &PlaceOrderParams) -> Result<PlaceOrderResponse>;
 
-    async fn cancel_all_orders(&self, symbol: &String) -> anyhow::Result<CancelAllOrdersResponse>;
+    async fn cancel_order(&self, symbol: &str, order_id: &str) -> Result<CancelOrderResponse>;
+
+    async fn cancel_all_orders(&self, symbol: &str) -> Result<CancelAllOrdersResponse>;
 
     async fn amend_order(
         &self,
-        order_id: &String,
+        order_id: &str,
         params: &AmendOrderParams,
-    ) -> anyhow::Result<AmendOrderResponse>;
+    ) -> Result<AmendOrderResponse>;
+
+    async fn get_balances(&self) -> Result<Vec<Balance>>;
+
+    /// Returns the open positions, flat ones are left out.
+    async fn get_positions(&self) -> Result<Vec<Position>>;
+
+    async fn get_open_orders(&self, symbol: &str) -> Result<Vec<Order>>;
+
+    async fn get_order(&self, symbol: &str, order_id: &str) -> Result<Order>;
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use cefi_rs_binance::{http::BinanceHttp, transport::MockTransport as BinanceMockTransport};
+    use cefi_rs_bybit::{http::BybitHttp, transport::MockTransport as BybitMockTransport};
+    use reqwest::Method;
+    use serde_json::json;
+
     use crate::exchanges::{binance::BinanceHttpWrapper, bybit::BybitHttpWrapper};
 
     use super::*;
@@ -32,15 +55,38 @@ mod tests {
     async fn test_get_server_time() {
         let mut exchanges: Vec<Box<dyn InterfaceHttp>> = vec![];
 
-        let bybit = BybitHttpWrapper::new("".to_string(), "".to_string());
-        exchanges.push(Box::new(bybit));
+        let transport = Arc::new(BybitMockTransport::new());
+        transport.push_ok(
+            Method::GET,
+            "v5/market/time",
+            &json!({
+                "retCode": 0,
+                "retMsg": "OK",
+                "result": {"timeSecond": "1688639403", "timeNano": "1688639403423213947"},
+                "retExtInfo": {},
+                "time": 1688639403423u64
+            })
+            .to_string(),
+        );
+        let bybit = BybitHttp::new("".to_string(), "".to_string())
+            .unwrap()
+            .with_transport(transport);
+        exchanges.push(Box::new(BybitHttpWrapper::from_client(bybit)));
 
-        let binance = BinanceHttpWrapper::new("".to_string(), "".to_string());
-        exchanges.push(Box::new(binance));
+        let transport = Arc::new(BinanceMockTransport::new());
+        transport.push_ok(
+            Method::GET,
+            "fapi/v1/time",
+            &json!({"serverTime": 1688639403423u64}).to_string(),
+        );
+        let binance = BinanceHttp::new("".to_string(), "".to_string())
+            .unwrap()
+            .with_transport(transport);
+        exchanges.push(Box::new(BinanceHttpWrapper::from_client(binance)));
 
         for exchange in exchanges {
             let server_time = exchange.get_server_time().await.unwrap();
-            println!("server_time: {:?}", server_time);
+            assert_eq!(server_time, 1688639403423);
         }
     }
 }
@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+
+use crate::{
+    errors::Error,
+    types::{Balance, Fill, Order, Orderbook, Position, Ticker, Trade},
+};
+
+/// Public market data streams.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subscription {
+    /// Local book maintained from the venue depth stream, published as its
+    /// best `depth` levels per side after every update. Subscribing again
+    /// changes the depth, the book is rebuilt if the venue stream it comes
+    /// from is too shallow.
+    Orderbook {
+        symbol: String,
+        depth: usize,
+    },
+    Trades(String),
+    Ticker(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsEvent {
+    OrderUpdate(Order),
+    Fill(Fill),
+    /// Position after a change, a closed one has no side and a size of zero.
+    /// Fields a venue does not stream, e.g. the mark price on Binance, are
+    /// zero.
+    PositionUpdate(Position),
+    BalanceUpdate(Balance),
+    BookUpdate(Orderbook),
+    Trade(Trade),
+    Ticker(Ticker),
+    /// A connection is open, also after a drop. Authentication and
+    /// subscriptions are sent but not acknowledged yet, a rejection is
+    /// reported as `Error`.
+    Connected,
+    /// A connection dropped, updates may have been missed until `Connected`.
+    Disconnected(String),
+    /// The venue rejected the credentials or a subscription, or an update
+    /// could not be converted. The stream goes on.
+    Error(Error),
+}
+
+/// Venue independent streaming client. Private updates are streamed when the
+/// client was created with credentials, market data once subscribed.
+#[async_trait]
+pub trait InterfaceWs: Send {
+    /// Subscribes to market data, subscriptions are kept across reconnections.
+    fn subscribe(&mut self, subscriptions: &[Subscription]);
+
+    /// Returns the next event, `None` once the client has stopped.
+    async fn next(&mut self) -> Option<WsEvent>;
+}
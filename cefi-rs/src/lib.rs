@@ -1,4 +1,9 @@
+pub mod errors;
 pub mod exchanges;
 pub mod interface_http;
+pub mod interface_ws;
+pub mod orderbook;
 pub mod trade;
 pub mod types;
+
+pub use errors::{Error, Result};
@@ -0,0 +1,351 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::{
+    errors::{Error, Result},
+    types::{Orderbook, OrderbookLevel},
+};
+
+/// Price used as a map key, ordered with `f64::total_cmp`.
+#[derive(Debug, Clone, Copy)]
+struct PriceKey(f64);
+
+impl PartialEq for PriceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Incremental change of a book. Each level replaces the amount at its price,
+/// an amount of zero removes the level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderbookDelta {
+    pub bids: Vec<OrderbookLevel>,
+    pub asks: Vec<OrderbookLevel>,
+    /// Update id of the book this delta applies on top of.
+    pub prev_update_id: i64,
+    pub update_id: i64,
+    pub timestamp_ms: i64,
+}
+
+/// L2 book maintained from a snapshot followed by deltas.
+///
+/// A delta that does not follow the last applied update clears the book and
+/// returns `Error::SequenceGap`. Deltas are then ignored until the next
+/// snapshot, see `is_synced`.
+#[derive(Debug, Clone)]
+pub struct LocalOrderbook {
+    symbol: String,
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    update_id: i64,
+    timestamp_ms: i64,
+    synced: bool,
+}
+
+fn apply_levels(side: &mut BTreeMap<PriceKey, f64>, levels: &[OrderbookLevel]) {
+    for level in levels {
+        if level.amount == 0.0 {
+            side.remove(&PriceKey(level.price));
+        } else {
+            side.insert(PriceKey(level.price), level.amount);
+        }
+    }
+}
+
+fn to_levels<'a>(
+    levels: impl Iterator<Item = (&'a PriceKey, &'a f64)>,
+    depth: usize,
+) -> Vec<OrderbookLevel> {
+    levels
+        .take(depth)
+        .map(|(price, amount)| OrderbookLevel {
+            price: price.0,
+            amount: *amount,
+        })
+        .collect()
+}
+
+impl LocalOrderbook {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            update_id: 0,
+            timestamp_ms: 0,
+            synced: false,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn update_id(&self) -> i64 {
+        self.update_id
+    }
+
+    pub fn timestamp_ms(&self) -> i64 {
+        self.timestamp_ms
+    }
+
+    /// Whether a snapshot was applied and no gap was detected since.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Replaces the whole book.
+    pub fn apply_snapshot(&mut self, snapshot: &Orderbook) {
+        self.bids.clear();
+        self.asks.clear();
+        apply_levels(&mut self.bids, &snapshot.bids);
+        apply_levels(&mut self.asks, &snapshot.asks);
+        self.update_id = snapshot.update_id;
+        self.timestamp_ms = snapshot.timestamp_ms;
+        self.synced = true;
+    }
+
+    pub fn apply_delta(&mut self, delta: &OrderbookDelta) -> Result<()> {
+        if !self.synced {
+            return Ok(());
+        }
+        if delta.prev_update_id != self.update_id {
+            self.reset();
+            return Err(Error::SequenceGap {
+                expected: self.update_id,
+                received: delta.prev_update_id,
+            });
+        }
+        apply_levels(&mut self.bids, &delta.bids);
+        apply_levels(&mut self.asks, &delta.asks);
+        self.update_id = delta.update_id;
+        self.timestamp_ms = delta.timestamp_ms;
+        Ok(())
+    }
+
+    /// Clears the book, deltas are ignored until the next snapshot.
+    pub fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.synced = false;
+    }
+
+    pub fn best_bid(&self) -> Option<OrderbookLevel> {
+        self.bids(1).pop()
+    }
+
+    pub fn best_ask(&self) -> Option<OrderbookLevel> {
+        self.asks(1).pop()
+    }
+
+    /// Best `depth` bids, highest price first.
+    pub fn bids(&self, depth: usize) -> Vec<OrderbookLevel> {
+        to_levels(self.bids.iter().rev(), depth)
+    }
+
+    /// Best `depth` asks, lowest price first.
+    pub fn asks(&self, depth: usize) -> Vec<OrderbookLevel> {
+        to_levels(self.asks.iter(), depth)
+    }
+
+    /// Snapshot of the best `depth` levels per side, or the full book.
+    pub fn to_orderbook(&self, depth: Option<usize>) -> Orderbook {
+        let depth = depth.unwrap_or(usize::MAX);
+        Orderbook {
+            symbol: self.symbol.clone(),
+            asks: self.asks(depth),
+            bids: self.bids(depth),
+            timestamp_ms: self.timestamp_ms,
+            update_id: self.update_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn level(price: f64, amount: f64) -> OrderbookLevel {
+        OrderbookLevel { price, amount }
+    }
+
+    fn snapshot(bids: Vec<OrderbookLevel>, asks: Vec<OrderbookLevel>, update_id: i64) -> Orderbook {
+        Orderbook {
+            symbol: "BTCUSDT".to_string(),
+            asks,
+            bids,
+            timestamp_ms: 1,
+            update_id,
+        }
+    }
+
+    fn delta(
+        bids: Vec<OrderbookLevel>,
+        asks: Vec<OrderbookLevel>,
+        update_id: i64,
+    ) -> OrderbookDelta {
+        OrderbookDelta {
+            bids,
+            asks,
+            prev_update_id: update_id - 1,
+            update_id,
+            timestamp_ms: update_id,
+        }
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let mut book = LocalOrderbook::new("BTCUSDT");
+        book.apply_snapshot(&snapshot(
+            vec![level(100.0, 1.0), level(99.5, 2.0)],
+            vec![level(100.5, 1.5), level(101.0, 3.0)],
+            10,
+        ));
+        book.apply_delta(&delta(
+            vec![level(100.0, 0.0), level(99.8, 0.4)],
+            vec![level(100.2, 0.1)],
+            11,
+        ))
+        .unwrap();
+
+        assert_eq!(book.best_bid(), Some(level(99.8, 0.4)));
+        assert_eq!(book.best_ask(), Some(level(100.2, 0.1)));
+        assert_eq!(book.bids(5), vec![level(99.8, 0.4), level(99.5, 2.0)]);
+        assert_eq!(book.asks(2), vec![level(100.2, 0.1), level(100.5, 1.5)]);
+
+        let orderbook = book.to_orderbook(Some(1));
+        assert_eq!(orderbook.update_id, 11);
+        assert_eq!(orderbook.timestamp_ms, 11);
+        assert_eq!(orderbook.asks.len(), 1);
+    }
+
+    #[test]
+    fn test_sequence_gap() {
+        let mut book = LocalOrderbook::new("BTCUSDT");
+        // deltas before the first snapshot are dropped
+        book.apply_delta(&delta(vec![level(100.0, 1.0)], vec![], 9))
+            .unwrap();
+        assert!(book.best_bid().is_none());
+
+        book.apply_snapshot(&snapshot(
+            vec![level(100.0, 1.0)],
+            vec![level(101.0, 1.0)],
+            10,
+        ));
+        let res = book.apply_delta(&delta(vec![level(100.0, 2.0)], vec![], 12));
+        assert_eq!(
+            res,
+            Err(Error::SequenceGap {
+                expected: 10,
+                received: 11
+            })
+        );
+        assert!(!book.is_synced());
+        assert!(book.best_bid().is_none());
+
+        book.apply_snapshot(&snapshot(vec![level(100.0, 3.0)], vec![], 20));
+        assert!(book.is_synced());
+        assert_eq!(book.best_bid(), Some(level(100.0, 3.0)));
+    }
+
+    fn levels() -> impl Strategy<Value = Vec<OrderbookLevel>> {
+        // a small price grid so deltas often hit existing levels
+        prop::collection::vec((0u32..20, 0u32..4), 0..10).prop_map(|levels| {
+            levels
+                .into_iter()
+                .map(|(price, amount)| level(100.0 + price as f64 * 0.5, amount as f64))
+                .collect()
+        })
+    }
+
+    fn apply_model(model: &mut HashMap<u64, f64>, levels: &[OrderbookLevel]) {
+        for level in levels {
+            if level.amount == 0.0 {
+                model.remove(&level.price.to_bits());
+            } else {
+                model.insert(level.price.to_bits(), level.amount);
+            }
+        }
+    }
+
+    fn check_side(levels: &[OrderbookLevel], model: &HashMap<u64, f64>, descending: bool) {
+        assert_eq!(levels.len(), model.len());
+        for pair in levels.windows(2) {
+            if descending {
+                assert!(pair[0].price > pair[1].price);
+            } else {
+                assert!(pair[0].price < pair[1].price);
+            }
+        }
+        for level in levels {
+            assert!(level.amount > 0.0);
+            assert_eq!(model.get(&level.price.to_bits()), Some(&level.amount));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_deltas_match_model(
+            bids in levels(),
+            asks in levels(),
+            deltas in prop::collection::vec((levels(), levels()), 0..20),
+        ) {
+            let mut book = LocalOrderbook::new("BTCUSDT");
+            let mut bid_model = HashMap::new();
+            let mut ask_model = HashMap::new();
+            book.apply_snapshot(&snapshot(bids.clone(), asks.clone(), 0));
+            apply_model(&mut bid_model, &bids);
+            apply_model(&mut ask_model, &asks);
+
+            for (i, (bids, asks)) in deltas.iter().enumerate() {
+                book.apply_delta(&delta(bids.clone(), asks.clone(), i as i64 + 1)).unwrap();
+                apply_model(&mut bid_model, bids);
+                apply_model(&mut ask_model, asks);
+            }
+
+            let orderbook = book.to_orderbook(None);
+            check_side(&orderbook.bids, &bid_model, true);
+            check_side(&orderbook.asks, &ask_model, false);
+            prop_assert_eq!(orderbook.update_id, deltas.len() as i64);
+            prop_assert_eq!(book.best_bid(), orderbook.bids.first().cloned());
+            prop_assert_eq!(book.best_ask(), orderbook.asks.first().cloned());
+        }
+
+        #[test]
+        fn prop_gap_forces_resync(
+            bids in levels(),
+            deltas in prop::collection::vec(levels(), 1..10),
+            gap in 2i64..5,
+        ) {
+            let mut book = LocalOrderbook::new("BTCUSDT");
+            book.apply_snapshot(&snapshot(bids, vec![], 0));
+            for (i, bids) in deltas.iter().enumerate() {
+                book.apply_delta(&delta(bids.clone(), vec![], i as i64 + 1)).unwrap();
+            }
+
+            let last = deltas.len() as i64;
+            let res = book.apply_delta(&delta(vec![level(100.0, 1.0)], vec![], last + gap));
+            prop_assert!(matches!(res, Err(Error::SequenceGap { .. })), "expected a gap");
+            prop_assert!(!book.is_synced());
+            prop_assert!(book.to_orderbook(None).bids.is_empty());
+        }
+    }
+}
@@ -1,21 +1,212 @@
+use crate::errors::{Error, Result};
+
 pub type ExchangeSymbol = String;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderType {
+    Market,
+    Limit,
+    /// Market order placed once `trigger_price` is crossed against the position.
+    StopMarket,
+    /// Limit order placed once `trigger_price` is crossed against the position.
+    StopLimit,
+    /// Market order placed once `trigger_price` is crossed in favour of the position.
+    TakeProfitMarket,
+    /// Limit order placed once `trigger_price` is crossed in favour of the position.
+    TakeProfitLimit,
+}
+
+impl OrderType {
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            OrderType::Limit | OrderType::StopLimit | OrderType::TakeProfitLimit
+        )
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        !matches!(self, OrderType::Market | OrderType::Limit)
+    }
+
+    pub fn is_stop(&self) -> bool {
+        matches!(self, OrderType::StopMarket | OrderType::StopLimit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeInForce {
+    Gtc,
+    Ioc,
+    Fok,
+    PostOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionIdx {
+    OneWay,
+    HedgeLong,
+    HedgeShort,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlaceOrderParams {
     pub symbol: ExchangeSymbol,
     pub is_buy: bool,
-    pub price: f64,
+    /// Limit price, required by limit order types and ignored otherwise.
+    pub price: Option<f64>,
     pub amount: f64,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    /// Trigger price, required by stop and take-profit order types.
+    pub trigger_price: Option<f64>,
+    pub reduce_only: bool,
+    pub close_on_trigger: bool,
+    pub position_idx: PositionIdx,
+    pub client_order_id: Option<String>,
+}
+
+impl PlaceOrderParams {
+    pub fn limit(symbol: &str, is_buy: bool, price: f64, amount: f64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            is_buy,
+            price: Some(price),
+            amount,
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::Gtc,
+            trigger_price: None,
+            reduce_only: false,
+            close_on_trigger: false,
+            position_idx: PositionIdx::OneWay,
+            client_order_id: None,
+        }
+    }
+
+    pub fn market(symbol: &str, is_buy: bool, amount: f64) -> Self {
+        Self {
+            price: None,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::Ioc,
+            ..Self::limit(symbol, is_buy, 0.0, amount)
+        }
+    }
+
+    /// Checks the venue independent rules, venues may reject more combinations.
+    pub fn validate(&self) -> Result<()> {
+        if self.amount <= 0.0 {
+            return Err(Error::InvalidQuantity(format!(
+                "amount must be positive, got {}",
+                self.amount
+            )));
+        }
+        if self.order_type.is_limit() && self.price.is_none() {
+            return Err(Error::InvalidParams(format!(
+                "{:?} order requires a price",
+                self.order_type
+            )));
+        }
+        if self.order_type.is_conditional() && self.trigger_price.is_none() {
+            return Err(Error::InvalidParams(format!(
+                "{:?} order requires a trigger price",
+                self.order_type
+            )));
+        }
+        if !self.order_type.is_conditional() && self.trigger_price.is_some() {
+            return Err(Error::InvalidParams(format!(
+                "{:?} order does not take a trigger price",
+                self.order_type
+            )));
+        }
+        if !self.order_type.is_limit() && self.time_in_force == TimeInForce::PostOnly {
+            return Err(Error::InvalidParams(format!(
+                "{:?} order cannot be post only",
+                self.order_type
+            )));
+        }
+        if self.close_on_trigger && !self.order_type.is_conditional() {
+            return Err(Error::InvalidParams(format!(
+                "close on trigger requires a conditional order, got {:?}",
+                self.order_type
+            )));
+        }
+        Ok(())
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlaceOrderResponse {
     pub order_id: String,
     pub order_link_id: String,
 }
 
-pub struct CancelOrderResponse {}
+#[derive(Debug, Clone, PartialEq)]
+pub struct CancelOrderResponse {
+    pub order_id: String,
+    pub order_link_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CancelAllOrdersResponse {
+    /// Orders reported as cancelled. Binance does not list them, so it is
+    /// always empty there.
+    pub cancelled: Vec<CancelOrderResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmendOrderParams {
+    pub symbol: ExchangeSymbol,
+    pub price: Option<f64>,
+    pub amount: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmendOrderResponse {
+    pub order_id: String,
+    pub order_link_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-pub struct CancelAllOrdersResponse {}
+    #[test]
+    fn test_validate_place_order_params() {
+        assert!(PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 0.01)
+            .validate()
+            .is_ok());
+        assert!(PlaceOrderParams::market("BTCUSDT", true, 0.01)
+            .validate()
+            .is_ok());
 
-pub struct AmendOrderParams {}
+        let no_price = PlaceOrderParams {
+            price: None,
+            ..PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 0.01)
+        };
+        assert!(no_price.validate().is_err());
 
-pub struct AmendOrderResponse {}
+        let post_only_market = PlaceOrderParams {
+            time_in_force: TimeInForce::PostOnly,
+            ..PlaceOrderParams::market("BTCUSDT", true, 0.01)
+        };
+        assert!(post_only_market.validate().is_err());
+
+        let stop_without_trigger = PlaceOrderParams {
+            order_type: OrderType::StopMarket,
+            ..PlaceOrderParams::market("BTCUSDT", false, 0.01)
+        };
+        assert!(stop_without_trigger.validate().is_err());
+
+        let stop = PlaceOrderParams {
+            trigger_price: Some(60000.0),
+            close_on_trigger: true,
+            ..stop_without_trigger
+        };
+        assert!(stop.validate().is_ok());
+
+        let close_on_trigger_limit = PlaceOrderParams {
+            close_on_trigger: true,
+            ..PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 0.01)
+        };
+        assert!(close_on_trigger_limit.validate().is_err());
+    }
+}
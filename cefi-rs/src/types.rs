@@ -1,15 +1,359 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::{
+    errors::{Error, Result},
+    trade::PlaceOrderParams,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OrderbookLevel {
     pub price: f64,
     pub amount: f64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Orderbook {
     pub symbol: String,
     pub asks: Vec<OrderbookLevel>,
     pub bids: Vec<OrderbookLevel>,
     pub timestamp_ms: i64,
+    /// Venue sequence number of the book, used to line it up with stream updates.
+    pub update_id: i64,
+}
+
+impl OrderbookLevel {
+    pub(crate) fn parse(level: &[String; 2]) -> Result<Self> {
+        let [price, amount] = level;
+        Ok(OrderbookLevel {
+            price: price
+                .parse::<f64>()
+                .map_err(|e| Error::Deserialize(format!("invalid orderbook price {price}: {e}")))?,
+            amount: amount.parse::<f64>().map_err(|e| {
+                Error::Deserialize(format!("invalid orderbook amount {amount}: {e}"))
+            })?,
+        })
+    }
+
+    pub(crate) fn parse_levels(levels: &[[String; 2]]) -> Result<Vec<Self>> {
+        levels.iter().map(Self::parse).collect()
+    }
+}
+
+/// Parses a decimal string from a venue response. Venues send an empty string
+/// for values that do not apply, which is read as zero.
+pub(crate) fn parse_number(value: &str, name: &str) -> Result<f64> {
+    if value.is_empty() {
+        return Ok(0.0);
+    }
+    value
+        .parse::<f64>()
+        .map_err(|e| Error::Deserialize(format!("invalid {name} {value}: {e}")))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+    /// Conditional order waiting for its trigger price.
+    Untriggered,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Balance {
+    pub asset: String,
+    pub total: f64,
+    /// `None` when the venue does not report it, e.g. in Binance account updates.
+    pub available: Option<f64>,
+    pub unrealised_pnl: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    /// `Buy` for a long position, `Sell` for a short one, `None` once closed.
+    pub side: Option<Side>,
+    /// Absolute position size in base asset.
+    pub size: f64,
+    pub entry_price: f64,
+    /// `None` when the venue does not report it, e.g. in Binance account updates.
+    pub mark_price: Option<f64>,
+    pub unrealised_pnl: f64,
+    pub leverage: Option<f64>,
+    pub liquidation_price: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Order {
+    pub order_id: String,
+    pub order_link_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price: f64,
+    pub amount: f64,
+    pub filled_amount: f64,
+    pub status: OrderStatus,
+    /// `None` when the venue does not report it, e.g. in Binance order updates.
+    pub created_time_ms: Option<i64>,
+    pub updated_time_ms: i64,
+}
+
+/// Execution of one of our orders.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub order_id: String,
+    pub order_link_id: String,
+    pub trade_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price: f64,
+    pub amount: f64,
+    pub fee: f64,
+    /// Asset the fee is charged in, `None` when it is the settle coin.
+    pub fee_asset: Option<String>,
+    pub is_maker: bool,
+    pub timestamp_ms: i64,
+}
+
+/// Public trade.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub symbol: String,
+    pub trade_id: String,
+    /// Taker side.
+    pub side: Side,
+    pub price: f64,
+    pub amount: f64,
+    pub timestamp_ms: i64,
+}
+
+/// Latest ticker of a symbol. Venues only send the fields that changed or that
+/// belong to the stream an update comes from, the streams merge them into the
+/// last ticker. Fields a venue has not sent yet are `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Ticker {
+    pub symbol: String,
+    pub bid_price: Option<f64>,
+    pub bid_amount: Option<f64>,
+    pub ask_price: Option<f64>,
+    pub ask_amount: Option<f64>,
+    pub last_price: Option<f64>,
+    pub mark_price: Option<f64>,
+    pub funding_rate: Option<f64>,
+    pub timestamp_ms: i64,
+}
+
+impl Ticker {
+    /// Overwrites the fields `update` carries, the others are kept.
+    pub(crate) fn merge(&mut self, update: Ticker) {
+        self.bid_price = update.bid_price.or(self.bid_price);
+        self.bid_amount = update.bid_amount.or(self.bid_amount);
+        self.ask_price = update.ask_price.or(self.ask_price);
+        self.ask_amount = update.ask_amount.or(self.ask_amount);
+        self.last_price = update.last_price.or(self.last_price);
+        self.mark_price = update.mark_price.or(self.mark_price);
+        self.funding_rate = update.funding_rate.or(self.funding_rate);
+        self.timestamp_ms = update.timestamp_ms;
+    }
+}
+
+/// Parses an optional decimal string, see `parse_number`.
+pub(crate) fn parse_optional_number(value: Option<&str>, name: &str) -> Result<Option<f64>> {
+    value.map(|value| parse_number(value, name)).transpose()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ContractType {
+    Perpetual,
+    Futures,
+    /// Venue contract type without a unified equivalent.
+    Other(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+    pub contract_type: ContractType,
+    pub settle_coin: String,
+    pub tick_size: f64,
+    pub qty_step: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    /// Minimum order value in settle coin, zero if the venue sets none.
+    pub min_notional: f64,
+}
+
+/// Number of decimals of a step size, used to drop the float noise left by
+/// rounding to a multiple of it.
+fn step_decimals(step: f64) -> usize {
+    let step = step.to_string();
+    step.split_once('.')
+        .map_or(0, |(_, decimals)| decimals.len())
+}
+
+fn round_to_step(value: f64, step: f64, round: fn(f64) -> f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    // the epsilon keeps values already on the step, e.g. 0.3 / 0.1, from being floored below it
+    let steps = round(value / step + 1e-9);
+    format!("{:.*}", step_decimals(step), steps * step)
+        .parse()
+        .unwrap_or(value)
+}
+
+fn is_on_step(value: f64, step: f64) -> bool {
+    step <= 0.0 || (round_to_step(value, step, f64::round) - value).abs() <= step * 1e-6
+}
+
+impl Instrument {
+    /// Rounds a price to the nearest tick.
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to_step(price, self.tick_size, f64::round)
+    }
+
+    /// Rounds a quantity down to the qty step, so the order never grows.
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        round_to_step(qty, self.qty_step, f64::floor)
+    }
+
+    /// Returns a copy of `params` with price, trigger price and amount rounded
+    /// to the instrument precision.
+    pub fn round_order(&self, params: &PlaceOrderParams) -> PlaceOrderParams {
+        PlaceOrderParams {
+            price: params.price.map(|price| self.round_price(price)),
+            trigger_price: params.trigger_price.map(|price| self.round_price(price)),
+            amount: self.round_qty(params.amount),
+            ..params.clone()
+        }
+    }
+
+    /// Checks that an order matches the instrument precision and size limits.
+    /// The notional check is skipped for orders without a price.
+    pub fn validate_order(&self, params: &PlaceOrderParams) -> Result<()> {
+        for price in [params.price, params.trigger_price].into_iter().flatten() {
+            if !is_on_step(price, self.tick_size) {
+                return Err(Error::InvalidPrice(format!(
+                    "{price} is not a multiple of the tick size {}",
+                    self.tick_size
+                )));
+            }
+        }
+        if !is_on_step(params.amount, self.qty_step) {
+            return Err(Error::InvalidQuantity(format!(
+                "{} is not a multiple of the qty step {}",
+                params.amount, self.qty_step
+            )));
+        }
+        if params.amount < self.min_qty || (self.max_qty > 0.0 && params.amount > self.max_qty) {
+            return Err(Error::InvalidQuantity(format!(
+                "{} is outside [{}, {}]",
+                params.amount, self.min_qty, self.max_qty
+            )));
+        }
+        if let Some(price) = params.price {
+            let notional = price * params.amount;
+            if notional < self.min_notional {
+                return Err(Error::InvalidQuantity(format!(
+                    "order value {notional} is below the minimum {}",
+                    self.min_notional
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn btcusdt() -> Instrument {
+        Instrument {
+            symbol: "BTCUSDT".to_string(),
+            contract_type: ContractType::Perpetual,
+            settle_coin: "USDT".to_string(),
+            tick_size: 0.1,
+            qty_step: 0.001,
+            min_qty: 0.001,
+            max_qty: 1190.0,
+            min_notional: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_round_price_and_qty() {
+        let instrument = btcusdt();
+        assert_eq!(instrument.round_price(65000.06), 65000.1);
+        assert_eq!(instrument.round_price(65000.04), 65000.0);
+        assert_eq!(instrument.round_qty(0.0129), 0.012);
+        assert_eq!(instrument.round_qty(0.3), 0.3);
+        assert_eq!(instrument.round_price(65000.3), 65000.3);
+    }
+
+    #[test]
+    fn test_round_order() {
+        let instrument = btcusdt();
+        let params = instrument.round_order(&PlaceOrderParams::limit(
+            "BTCUSDT", true, 65000.123, 0.01234,
+        ));
+        assert_eq!(params.price, Some(65000.1));
+        assert_eq!(params.amount, 0.012);
+        assert!(instrument.validate_order(&params).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order() {
+        let instrument = btcusdt();
+        assert!(matches!(
+            instrument.validate_order(&PlaceOrderParams::limit("BTCUSDT", true, 65000.05, 0.01)),
+            Err(Error::InvalidPrice(_))
+        ));
+        assert!(matches!(
+            instrument.validate_order(&PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 0.0105)),
+            Err(Error::InvalidQuantity(_))
+        ));
+        assert!(matches!(
+            instrument.validate_order(&PlaceOrderParams::limit("BTCUSDT", true, 65000.0, 2000.0)),
+            Err(Error::InvalidQuantity(_))
+        ));
+        assert!(matches!(
+            instrument.validate_order(&PlaceOrderParams::limit("BTCUSDT", true, 1000.0, 0.001)),
+            Err(Error::InvalidQuantity(_))
+        ));
+        assert!(instrument
+            .validate_order(&PlaceOrderParams::market("BTCUSDT", true, 0.001))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_merge_ticker() {
+        let mut ticker = Ticker {
+            symbol: "BTCUSDT".to_string(),
+            bid_price: Some(65000.0),
+            last_price: Some(65000.5),
+            timestamp_ms: 1,
+            ..Ticker::default()
+        };
+        ticker.merge(Ticker {
+            symbol: "BTCUSDT".to_string(),
+            bid_price: Some(65001.0),
+            mark_price: Some(65000.8),
+            timestamp_ms: 2,
+            ..Ticker::default()
+        });
+        assert_eq!(ticker.bid_price, Some(65001.0));
+        assert_eq!(ticker.last_price, Some(65000.5));
+        assert_eq!(ticker.mark_price, Some(65000.8));
+        assert_eq!(ticker.timestamp_ms, 2);
+    }
 }
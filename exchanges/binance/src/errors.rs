@@ -1,11 +1,23 @@
 use std::fmt;
 
+use crate::transport::TransportError;
+
 pub type BinanceResult<T> = Result<T, BinanceError>;
 
 #[derive(Debug)]
 pub enum BinanceError {
     ApiError(i64, String),
+    HttpError(reqwest::Error),
+    /// 5xx status, with the body of the response.
+    ServerError(u16, String),
     DeserializeError(String),
+    /// Request refused locally as the venue would reject it.
+    InvalidRequest(String),
+    /// Refused locally by the rate limiter.
+    RateLimited(String),
+    /// Unknown order on a retried cancel, an earlier attempt without a
+    /// response has probably cancelled it.
+    UnconfirmedCancel(String),
     Unknown(String),
 }
 
@@ -15,6 +27,21 @@ impl From<anyhow::Error> for BinanceError {
     }
 }
 
+impl From<reqwest::Error> for BinanceError {
+    fn from(value: reqwest::Error) -> Self {
+        BinanceError::HttpError(value)
+    }
+}
+
+impl From<TransportError> for BinanceError {
+    fn from(value: TransportError) -> Self {
+        match value {
+            TransportError::Http(err) => BinanceError::HttpError(err),
+            err => BinanceError::Unknown(err.to_string()),
+        }
+    }
+}
+
 impl From<serde_json::Error> for BinanceError {
     fn from(value: serde_json::Error) -> Self {
         BinanceError::DeserializeError(format!("{}", value))
@@ -23,6 +50,75 @@ impl From<serde_json::Error> for BinanceError {
 
 impl fmt::Display for BinanceError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            BinanceError::ApiError(code, msg) => write!(f, "binance api error {code}: {msg}"),
+            BinanceError::HttpError(err) => write!(f, "binance http error: {err}"),
+            BinanceError::ServerError(status, body) => {
+                write!(f, "binance server error {status}: {body}")
+            }
+            BinanceError::DeserializeError(msg) => write!(f, "binance deserialize error: {msg}"),
+            BinanceError::InvalidRequest(msg) => write!(f, "binance invalid request: {msg}"),
+            BinanceError::RateLimited(msg) => write!(f, "binance rate limited: {msg}"),
+            BinanceError::UnconfirmedCancel(msg) => {
+                write!(f, "binance cancel unconfirmed after retry: {msg}")
+            }
+            BinanceError::Unknown(msg) => write!(f, "binance error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for BinanceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BinanceError::HttpError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            BinanceError::ApiError(-2019, "Margin is insufficient.".to_string()).to_string(),
+            "binance api error -2019: Margin is insufficient."
+        );
+        assert_eq!(
+            BinanceError::DeserializeError("expected value at line 1 column 1".to_string())
+                .to_string(),
+            "binance deserialize error: expected value at line 1 column 1"
+        );
+        assert_eq!(
+            BinanceError::Unknown("invalid side Flat".to_string()).to_string(),
+            "binance error: invalid side Flat"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_error_source() {
+        // nothing listens on the discard port
+        let err: BinanceError = reqwest::get("http://127.0.0.1:9").await.unwrap_err().into();
+        assert!(err.to_string().starts_with("binance http error: "));
+        assert!(err.source().is_some());
+        assert!(BinanceError::Unknown("".to_string()).source().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_from_transport_error() {
+        let err = TransportError::from(reqwest::get("http://127.0.0.1:9").await.unwrap_err());
+        assert!(matches!(
+            BinanceError::from(err),
+            BinanceError::HttpError(_)
+        ));
+        let err = TransportError::NoMockResponse("GET fapi/v1/time".to_string());
+        assert_eq!(
+            BinanceError::from(err).to_string(),
+            "binance error: no mock response for GET fapi/v1/time"
+        );
     }
 }
@@ -1,185 +1,535 @@
-#![allow(dead_code, unused_imports, unused_variables)]
-
-use crate::{errors::BinanceResult, types::ServerTimeResponse};
-use hmac::Hmac;
-use reqwest::{Client, RequestBuilder};
-use serde_json::{Map, Value};
+use crate::{
+    clock::ServerClock,
+    config::ClientConfig,
+    errors::{BinanceError, BinanceResult},
+    rate_limit::RateLimiter,
+    retry::{is_transient, RetryPolicy},
+    transport::{HttpRequest, ReqwestTransport, Transport},
+    types::BinanceErrorResponse,
+};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Method, StatusCode,
+};
+use serde::Serialize;
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::sleep;
+use tracing::{debug, warn};
 
-static BINANCE_HOST_HOST: &'static str = "https://fapi.binance.com";
+/// Code of a cancel of an order that is not open.
+const UNKNOWN_ORDER_CODE: i64 = -2011;
 
 pub struct BinanceHttp {
     api_key: String,
     api_secret: String,
-    client: Client,
+    host: String,
+    recv_window: String,
+    transport: Arc<dyn Transport>,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    clock: Arc<ServerClock>,
 }
 
 impl BinanceHttp {
-    pub fn new(api_key: String, api_secret: String) -> Self {
-        Self {
+    /// Client of mainnet with the default settings, fails if the HTTP client
+    /// cannot be built.
+    pub fn new(api_key: String, api_secret: String) -> BinanceResult<Self> {
+        Self::with_config(api_key, api_secret, &ClientConfig::default())
+    }
+
+    /// Fails if the proxy of `config` is not a valid url.
+    pub fn with_config(
+        api_key: String,
+        api_secret: String,
+        config: &ClientConfig,
+    ) -> BinanceResult<Self> {
+        Ok(Self {
             api_key,
             api_secret,
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(10))
-                .build()
-                .expect("reqwest client"),
+            host: config.rest_url().to_string(),
+            recv_window: config.recv_window().as_millis().to_string(),
+            transport: Arc::new(ReqwestTransport::new(config.http_client()?)),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            retry_policy: RetryPolicy::default(),
+            clock: Arc::new(ServerClock::new()),
+        })
+    }
+
+    /// Overrides the REST base url, e.g. to point the client at a mock server.
+    pub fn with_host(mut self, host: String) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Replaces the transport requests are sent with, e.g. by a
+    /// `MockTransport` serving recorded responses.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Replaces the default limiter, which waits for the limits. Clients of
+    /// the same account and IP should share one.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Replaces the clock signed requests are timestamped with, e.g. to share
+    /// it between the clients of a host.
+    pub fn with_clock(mut self, clock: Arc<ServerClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Clock corrected by `sync_clock`, its offset can be monitored.
+    pub fn clock(&self) -> &Arc<ServerClock> {
+        &self.clock
+    }
+
+    fn generate_signature(&self, query_str: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(query_str.as_bytes());
+
+        let result = mac.finalize();
+        let code_bytes = result.into_bytes();
+        hex::encode(code_bytes)
+    }
+
+    fn generate_query_str<P: Serialize>(params: &P) -> BinanceResult<String> {
+        serde_urlencoded::to_string(params).map_err(|err| BinanceError::Unknown(format!("{}", err)))
+    }
+
+    /// Appends `recvWindow`, `timestamp` and the HMAC-SHA256 `signature` of the
+    /// resulting query string, as required by `USER_DATA` and `TRADE` endpoints.
+    fn generate_signed_query_str<P: Serialize>(&self, params: &P) -> BinanceResult<String> {
+        let timestamp = self.clock.now_ms().to_string();
+        let mut query_str = Self::generate_query_str(params)?;
+        if !query_str.is_empty() {
+            query_str.push('&');
+        }
+        query_str.push_str(&format!(
+            "recvWindow={}&timestamp={timestamp}",
+            self.recv_window
+        ));
+
+        let signature = self.generate_signature(&query_str);
+        Ok(format!("{query_str}&signature={signature}"))
+    }
+
+    fn auth_headers(&self) -> BinanceResult<HeaderMap> {
+        let api_key = HeaderValue::from_str(&self.api_key)
+            .map_err(|err| BinanceError::Unknown(format!("invalid api key header: {err}")))?;
+        let mut headers = HeaderMap::new();
+        headers.insert("X-MBX-APIKEY", api_key);
+        Ok(headers)
+    }
+
+    /// Sends a request built by `build`, once per attempt so that every
+    /// attempt is signed with a fresh timestamp. Transient failures are
+    /// retried following the retry policy if the request is `idempotent`.
+    ///
+    /// A cancel answered with an unknown order on a retry was most likely
+    /// executed by an earlier attempt whose response was lost, it fails with
+    /// `UnconfirmedCancel` rather than the api error.
+    async fn send_with_retry<T>(
+        &self,
+        method: &Method,
+        endpoint: &str,
+        query_str: &str,
+        idempotent: bool,
+        build: impl Fn() -> BinanceResult<HttpRequest>,
+    ) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        let mut attempt = 1;
+        loop {
+            debug!("binance {method} {endpoint} attempt {attempt}");
+            self.rate_limiter
+                .acquire(method, endpoint, query_str)
+                .await?;
+            let res = self.send_attempt(build()?).await;
+            match res {
+                Err(err)
+                    if idempotent
+                        && attempt <= self.retry_policy.max_retries
+                        && is_transient(&err) =>
+                {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!("binance {method} {endpoint} attempt {attempt} failed: {err}, retrying in {delay:?}");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(BinanceError::ApiError(UNKNOWN_ORDER_CODE, msg))
+                    if attempt > 1 && *method == Method::DELETE =>
+                {
+                    return Err(BinanceError::UnconfirmedCancel(msg))
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn send_attempt<T>(&self, request: HttpRequest) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        let response = self.transport.send(request).await?;
+        self.rate_limiter
+            .on_response(response.status, &response.headers);
+        if response.status.is_server_error() {
+            return Err(BinanceError::ServerError(
+                response.status.as_u16(),
+                response.body,
+            ));
+        }
+        Self::parse_response(response.status, &response.body)
+    }
+
+    async fn send_request<T, P>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        params: P,
+        is_auth: bool,
+    ) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+        P: Serialize,
+    {
+        let query_str = Self::generate_query_str(&params)?;
+        // GETs, cancels and modifications of an order by client order id can
+        // be repeated. Order placement is never retried: a client order id only
+        // has to be unique among open orders, so a second attempt after a lost
+        // response could place the order again once the first one filled.
+        let has_orig_client_order_id = query_str
+            .split('&')
+            .any(|pair| pair.starts_with("origClientOrderId="));
+        let idempotent = matches!(method, Method::GET | Method::DELETE) || has_orig_client_order_id;
+
+        self.send_with_retry(&method, endpoint, &query_str, idempotent, || {
+            let query_str = if is_auth {
+                self.generate_signed_query_str(&params)?
+            } else {
+                query_str.clone()
+            };
+            Ok(HttpRequest {
+                method: method.clone(),
+                url: format!("{}/{}?{}", self.host, endpoint, query_str),
+                headers: if is_auth {
+                    self.auth_headers()?
+                } else {
+                    HeaderMap::new()
+                },
+                body: None,
+            })
+        })
+        .await
+    }
+
+    /// `USER_STREAM` endpoints take the api key header but are not signed.
+    /// Creating a listen key returns the current one if it is still valid, so
+    /// all of them are safe to retry.
+    pub(crate) async fn send_user_stream_request<T>(
+        &self,
+        method: Method,
+        endpoint: &str,
+    ) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_with_retry(&method, endpoint, "", true, || {
+            Ok(HttpRequest {
+                method: method.clone(),
+                url: format!("{}/{}", self.host, endpoint),
+                headers: self.auth_headers()?,
+                body: None,
+            })
+        })
+        .await
+    }
+
+    fn parse_response<T>(status: StatusCode, res: &str) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        if !status.is_success() {
+            return match serde_json::from_str::<BinanceErrorResponse>(res) {
+                Ok(err) => Err(BinanceError::ApiError(err.code, err.msg)),
+                Err(_) => Err(BinanceError::Unknown(format!("{status}: {res}"))),
+            };
         }
+
+        serde_json::from_str::<T>(res)
+            .map_err(|err| BinanceError::DeserializeError(format!("{res}: {err}")))
+    }
+
+    pub(crate) async fn send_get_request<T, P>(
+        &self,
+        endpoint: &str,
+        params: P,
+        is_auth: bool,
+    ) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+        P: Serialize,
+    {
+        self.send_request(Method::GET, endpoint, params, is_auth)
+            .await
+    }
+
+    pub(crate) async fn send_post_request<T, P>(
+        &self,
+        endpoint: &str,
+        params: P,
+    ) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+        P: Serialize,
+    {
+        self.send_request(Method::POST, endpoint, params, true)
+            .await
     }
 
-    fn generate_query_str(params: &HashMap<&str, &str>) -> String {
-        params
-            .iter()
-            .map(|(key, value)| format!("{}={}", key, value))
-            .collect::<Vec<String>>()
-            .join("&")
+    pub(crate) async fn send_put_request<T, P>(&self, endpoint: &str, params: P) -> BinanceResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+        P: Serialize,
+    {
+        self.send_request(Method::PUT, endpoint, params, true).await
     }
 
-    pub(crate) async fn send_get_request<T>(
+    pub(crate) async fn send_delete_request<T, P>(
         &self,
         endpoint: &str,
-        params: HashMap<&str, &str>,
-    ) -> anyhow::Result<T>
+        params: P,
+    ) -> BinanceResult<T>
     where
         T: for<'a> serde::Deserialize<'a>,
+        P: Serialize,
     {
-        let query_str = Self::generate_query_str(&params);
-
-        let response = self
-            .client
-            .get(&format!("{}/{}?{}", BINANCE_HOST_HOST, endpoint, query_str))
-            .send()
-            .await?;
-        let body = response.text().await?;
-        match serde_json::from_str::<T>(&body) {
-            Ok(res) => Ok(res),
-            Err(e) => Err(anyhow::anyhow!("{:?} {:?}", body, e)),
+        self.send_request(Method::DELETE, endpoint, params, true)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Environment,
+        types::{
+            BinanceOrderRef, BinanceOrderType, BinanceSide, NewOrderRequest, ServerTimeResponse,
+        },
+    };
+    use serde_json::json;
+    use std::{collections::HashMap, time::Duration};
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn test_client(server: &MockServer) -> BinanceHttp {
+        BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                min_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(20),
+            })
+    }
+
+    #[test]
+    fn test_generate_signature() {
+        // example from https://developers.binance.com/docs/derivatives/usds-margined-futures/general-info
+        let client = BinanceHttp::new(
+            "dbefbc809e3e83c283a984c3a1459732ea7db1360ca80c5c2c8867408d28cc83".to_string(),
+            "2b5eb11e18796d12d88f13dc27dbbd02c2cc51ff7059765ed9821957d82bb4d9".to_string(),
+        )
+        .unwrap();
+        let query_str = "symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=1&price=9000&timeInForce=GTC&recvWindow=5000&timestamp=1591702613943";
+        assert_eq!(
+            client.generate_signature(query_str),
+            "3c661234138461fcc7a7d8746c6558c9842d4e10870d2ecbedf7777cad694af9"
+        );
+    }
+
+    #[test]
+    fn test_generate_signed_query_str() {
+        let client = BinanceHttp::new("api_key".to_string(), "api_secret".to_string()).unwrap();
+        let params = HashMap::from([("symbol", "BTCUSDT")]);
+        let query_str = client.generate_signed_query_str(&params).unwrap();
+
+        let (payload, signature) = query_str.split_once("&signature=").unwrap();
+        assert!(payload.starts_with("symbol=BTCUSDT&recvWindow=5000&timestamp="));
+        assert_eq!(signature, client.generate_signature(payload));
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let res = r#"{"code":-2019,"msg":"Margin is insufficient."}"#;
+        let err = BinanceHttp::parse_response::<ServerTimeResponse>(StatusCode::BAD_REQUEST, res)
+            .unwrap_err();
+        match err {
+            BinanceError::ApiError(code, msg) => {
+                assert_eq!(code, -2019);
+                assert_eq!(msg, "Margin is insufficient.");
+            }
+            _ => panic!("unexpected error: {:?}", err),
         }
     }
 
-    // fn generate_get_signature(
-    //     &self,
-    //     timestamp: &str,
-    //     params: &HashMap<&str, &str>,
-    // ) -> BinanceResult<String> {
-    //     todo!()
-    // }
-
-    // fn generate_post_signature(
-    //     &self,
-    //     timestamp: &str,
-    //     params: &Map<String, Value>,
-    // ) -> BinanceResult<String> {
-    //     todo!()
-    // }
-
-    // fn generate_query_str(params: &HashMap<&str, &str>) -> String {
-    //     params
-    //         .iter()
-    //         .map(|(key, value)| format!("{}={}", key, value))
-    //         .collect::<Vec<String>>()
-    //         .join("&")
-    // }
-
-    // fn add_headers(
-    //     &self,
-    //     builder: RequestBuilder,
-    //     timestamp: &String,
-    //     signature: &String,
-    // ) -> RequestBuilder {
-    //     builder
-    //         .header("X-BAPI-API-KEY", self.api_key.clone())
-    //         .header("X-BAPI-SIGN", signature)
-    //         .header("X-BAPI-SIGN-TYPE", "2")
-    //         .header("X-BAPI-TIMESTAMP", timestamp.clone())
-    //         .header("X-BAPI-RECV-WINDOW", RECV_WINDOW)
-    //         .header("Content-Type", "application/json")
-    // }
-
-    // async fn send_get_request<T>(
-    //     &self,
-    //     endpoint: &str,
-    //     params: HashMap<&str, &str>,
-    //     is_auth: bool,
-    // ) -> BinanceResult<T>
-    // where
-    //     T: for<'a> serde::Deserialize<'a>,
-    // {
-    //     todo!()
-    // }
-
-    // async fn send_post_request<T>(
-    //     &self,
-    //     endpoint: &str,
-    //     params: Map<String, Value>,
-    // ) -> BinanceResult<T>
-    // where
-    //     T: for<'a> serde::Deserialize<'a>,
-    // {
-    //     todo!()
-    // }
-
-    // pub async fn get_open_order(
-    //     &self,
-    //     symbol: &String,
-    //     cloid: Option<String>,
-    // ) -> BinanceResult<GetOrderResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn place_order(
-    //     &self,
-    //     cloid: &String,
-    //     symbol: &String,
-    //     side: &String,
-    //     price: &String,
-    //     qty: &String,
-    // ) -> BinanceResult<OrderResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn cancel_order(
-    //     &self,
-    //     symbol: &String,
-    //     cloid: &String,
-    // ) -> BinanceResult<OrderResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn cancel_all_orders(
-    //     &self,
-    //     symbol: &String,
-    // ) -> BinanceResult<CancelAllOrdersResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn amend_order(
-    //     &self,
-    //     symbol: &String,
-    //     cloid: &String,
-    //     price: &String,
-    //     // qty: &String,
-    // ) -> BinanceResult<OrderResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn get_orderbook(&self, symbol: &String) -> BinanceResult<OrderbookResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn get_positions(&self) -> BinanceResult<GetPositionResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn get_tickers(&self) -> BinanceResult<GetTickersResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn get_wallet_balance(&self) -> BinanceResult<GetWalletBalanceResponse> {
-    //     todo!()
-    // }
-
-    // pub async fn get_account_info(&self) -> BinanceResult<BybitAccountInfo> {
-    //     todo!()
-    // }
+    #[test]
+    fn test_parse_response() {
+        let res = r#"{"serverTime":1499827319559}"#;
+        let server_time =
+            BinanceHttp::parse_response::<ServerTimeResponse>(StatusCode::OK, res).unwrap();
+        assert_eq!(server_time.server_time, 1499827319559);
+    }
+
+    #[tokio::test]
+    async fn test_retry_get() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/time"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/time"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"serverTime": 1499827319559u64})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let server_time = test_client(&server).check_server_time().await.unwrap();
+        assert_eq!(server_time.server_time, 1499827319559);
+    }
+
+    #[tokio::test]
+    async fn test_client_config() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/openOrders"))
+            .and(header("user-agent", "cefi-rs-test"))
+            .and(query_param("recvWindow", "10000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = ClientConfig::new(Environment::Testnet)
+            .with_rest_url(server.uri())
+            .with_recv_window(Duration::from_secs(10))
+            .with_user_agent("cefi-rs-test".to_string());
+        let client =
+            BinanceHttp::with_config("key".to_string(), "secret".to_string(), &config).unwrap();
+        let orders = client.get_open_orders(None).await.unwrap();
+        assert!(orders.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_clock() {
+        let server = MockServer::start().await;
+        let server_ms = chrono::Utc::now().timestamp_millis() + 2000;
+        Mock::given(method("GET"))
+            .and(path("/fapi/v1/time"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"serverTime": server_ms})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = test_client(&server);
+        let offset_ms = client.sync_clock().await.unwrap();
+        assert!((offset_ms - 2000).abs() < 500);
+        assert_eq!(client.clock().offset_ms(), offset_ms);
+        assert!(client.clock().rtt_ms().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_of_order_placement() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/order"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "code": -1007,
+                "msg": "Timeout waiting for response from backend server. Send status unknown; execution status unknown."
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = NewOrderRequest::new("BTCUSDT", BinanceSide::Buy, BinanceOrderType::Market)
+            .quantity("0.001")
+            .new_client_order_id("my-order-1");
+        match test_client(&server).new_order(&request).await {
+            Err(BinanceError::ApiError(code, _)) => assert_eq!(code, -1007),
+            res => panic!("unexpected result {res:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_order_on_cancel_retry() {
+        let server = MockServer::start().await;
+        let unknown_order = ResponseTemplate::new(400)
+            .set_body_json(json!({"code": -2011, "msg": "Unknown order sent."}));
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("orderId", "1"))
+            .respond_with(unknown_order.clone())
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("orderId", "2"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/order"))
+            .and(query_param("orderId", "2"))
+            .respond_with(unknown_order)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = test_client(&server);
+        match client
+            .cancel_order("BTCUSDT", &BinanceOrderRef::OrderId(1))
+            .await
+        {
+            Err(BinanceError::ApiError(code, _)) => assert_eq!(code, -2011),
+            res => panic!("unexpected result {res:?}"),
+        }
+        // the first attempt may have cancelled the order before failing
+        match client
+            .cancel_order("BTCUSDT", &BinanceOrderRef::OrderId(2))
+            .await
+        {
+            Err(BinanceError::UnconfirmedCancel(msg)) => assert_eq!(msg, "Unknown order sent."),
+            res => panic!("unexpected result {res:?}"),
+        }
+    }
 }
@@ -1,4 +1,13 @@
+pub mod config;
 pub mod errors;
 pub mod http;
+pub mod rate_limit;
 pub mod rest;
+pub mod retry;
+pub mod transport;
 pub mod types;
+pub mod ws;
+pub mod ws_types;
+pub mod ws_user;
+
+pub use cefi_rs_common::clock;
@@ -0,0 +1,382 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use tokio::time::{sleep, Instant};
+
+use crate::{
+    errors::{BinanceError, BinanceResult},
+    types::RateLimit,
+};
+
+/// Limits of USDⓈ-M futures, replaced by the ones of `get_exchange_info`.
+/// Keys are the rate limit type followed by the interval as in the
+/// `X-MBX-USED-WEIGHT-1M` and `X-MBX-ORDER-COUNT-10S` headers.
+const DEFAULT_LIMITS: [(&str, u32, Duration); 3] = [
+    ("REQUEST_WEIGHT_1M", 2400, Duration::from_secs(60)),
+    ("ORDERS_10S", 300, Duration::from_secs(10)),
+    ("ORDERS_1M", 1200, Duration::from_secs(60)),
+];
+
+/// Used when a 429 or 418 answer has no `Retry-After` header.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// What a request does when its limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    /// Waits until the request fits in the limit.
+    #[default]
+    Wait,
+    /// Returns `BinanceError::RateLimited` right away.
+    FailFast,
+}
+
+/// Token bucket refilled continuously, `capacity` tokens per `interval`.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    interval: Duration,
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, interval: Duration, now: Instant) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity: capacity as f64,
+            interval,
+            tokens: capacity as f64,
+            updated: now,
+            blocked_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refilled = elapsed * self.capacity / self.interval.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Time until `cost` tokens are available, zero if they are.
+    fn wait_time(&mut self, cost: f64, now: Instant) -> Duration {
+        self.refill(now);
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return until - now;
+            }
+            self.blocked_until = None;
+        }
+        let missing = cost.min(self.capacity) - self.tokens;
+        if missing <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(missing * self.interval.as_secs_f64() / self.capacity)
+    }
+
+    fn take(&mut self, cost: f64) {
+        self.tokens -= cost.min(self.capacity);
+    }
+
+    fn set_limit(&mut self, capacity: u32, interval: Duration, now: Instant) {
+        self.refill(now);
+        self.capacity = capacity.max(1) as f64;
+        self.interval = interval;
+        self.tokens = self.tokens.min(self.capacity);
+    }
+
+    fn set_used(&mut self, used: f64, now: Instant) {
+        self.refill(now);
+        self.tokens = (self.capacity - used).min(self.capacity);
+    }
+}
+
+/// Weight of a request on the `REQUEST_WEIGHT` limits.
+fn request_weight(endpoint: &str, query_str: &str) -> f64 {
+    let param = |name: &str| {
+        query_str
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    };
+    match endpoint {
+        "fapi/v1/depth" => match param("limit").and_then(|limit| limit.parse().ok()) {
+            Some(..=50) => 2.0,
+            Some(51..=100) => 5.0,
+            Some(101..=500) | None => 10.0,
+            Some(_) => 20.0,
+        },
+        "fapi/v1/openOrders" if param("symbol").is_none() => 40.0,
+        "fapi/v1/batchOrders" | "fapi/v2/account" | "fapi/v2/balance" | "fapi/v2/positionRisk" => {
+            5.0
+        }
+        "fapi/v1/commissionRate" => 20.0,
+        _ => 1.0,
+    }
+}
+
+/// Count of a request on the `ORDERS` limits.
+fn order_count(method: &Method, endpoint: &str) -> f64 {
+    match (method, endpoint) {
+        (&Method::POST | &Method::PUT, "fapi/v1/order") => 1.0,
+        (&Method::POST, "fapi/v1/batchOrders") => 5.0,
+        _ => 0.0,
+    }
+}
+
+/// Key of a limit, e.g. `REQUEST_WEIGHT_1M` for 2400 per minute.
+fn limit_key(rate_limit: &RateLimit) -> Option<(String, Duration)> {
+    let (unit, seconds) = match rate_limit.interval.as_str() {
+        "SECOND" => ("S", 1),
+        "MINUTE" => ("M", 60),
+        "HOUR" => ("H", 3600),
+        "DAY" => ("D", 86400),
+        _ => return None,
+    };
+    let num = rate_limit.interval_num.max(1) as u64;
+    let key = format!("{}_{num}{unit}", rate_limit.rate_limit_type);
+    Some((key, Duration::from_secs(seconds * num)))
+}
+
+/// Client side throttling of REST requests, with one token bucket per limit
+/// of the venue. A request takes its weight from the `REQUEST_WEIGHT` buckets
+/// and, when it places or modifies orders, its count from the `ORDERS`
+/// buckets. The buckets are corrected from the usage headers of every
+/// response.
+///
+/// Share one limiter between the clients of an account with
+/// `BinanceHttp::with_rate_limiter`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    mode: RateLimitMode,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitMode::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(mode: RateLimitMode) -> Self {
+        let now = Instant::now();
+        let buckets = DEFAULT_LIMITS
+            .into_iter()
+            .map(|(key, limit, interval)| (key.to_string(), TokenBucket::new(limit, interval, now)))
+            .collect();
+        Self {
+            mode,
+            buckets: Mutex::new(buckets),
+        }
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Applies the limits published in `ExchangeInfo::rate_limits`.
+    pub fn set_limits(&self, rate_limits: &[RateLimit]) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        for rate_limit in rate_limits {
+            let Some((key, interval)) = limit_key(rate_limit) else {
+                continue;
+            };
+            let limit = rate_limit.limit.max(0) as u32;
+            match buckets.get_mut(&key) {
+                Some(bucket) => bucket.set_limit(limit, interval, now),
+                None => {
+                    buckets.insert(key, TokenBucket::new(limit, interval, now));
+                }
+            }
+        }
+    }
+
+    /// Takes the cost of a request from every bucket, waiting for it or
+    /// failing depending on the mode.
+    pub(crate) async fn acquire(
+        &self,
+        method: &Method,
+        endpoint: &str,
+        query_str: &str,
+    ) -> BinanceResult<()> {
+        let weight = request_weight(endpoint, query_str);
+        let orders = order_count(method, endpoint);
+        let cost = |key: &str| {
+            if key.starts_with("REQUEST_WEIGHT") {
+                weight
+            } else if key.starts_with("ORDERS") {
+                orders
+            } else {
+                0.0
+            }
+        };
+        loop {
+            let (key, wait) = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().unwrap();
+                let mut limited = None;
+                for (key, bucket) in buckets.iter_mut() {
+                    let cost = cost(key);
+                    if cost == 0.0 {
+                        continue;
+                    }
+                    let wait = bucket.wait_time(cost, now);
+                    if !wait.is_zero() && limited.as_ref().map_or(true, |(_, max)| wait > *max) {
+                        limited = Some((key.clone(), wait));
+                    }
+                }
+                let Some(limited) = limited else {
+                    for (key, bucket) in buckets.iter_mut() {
+                        bucket.take(cost(key));
+                    }
+                    return Ok(());
+                };
+                limited
+            };
+            match self.mode {
+                RateLimitMode::Wait => sleep(wait).await,
+                RateLimitMode::FailFast => {
+                    return Err(BinanceError::RateLimited(format!(
+                        "{key} limit reached, retry in {wait:?}"
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Aligns the buckets with the usage reported by the venue, and stops all
+    /// requests for `Retry-After` once the venue throttles or bans the IP.
+    pub(crate) fn on_response(&self, status: StatusCode, headers: &HeaderMap) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        for (name, value) in headers {
+            let name = name.as_str();
+            let key = if let Some(interval) = name.strip_prefix("x-mbx-used-weight-") {
+                format!("REQUEST_WEIGHT_{}", interval.to_uppercase())
+            } else if let Some(interval) = name.strip_prefix("x-mbx-order-count-") {
+                format!("ORDERS_{}", interval.to_uppercase())
+            } else {
+                continue;
+            };
+            let used = value.to_str().ok().and_then(|used| used.parse().ok());
+            if let (Some(bucket), Some(used)) = (buckets.get_mut(&key), used) {
+                bucket.set_used(used, now);
+            }
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+            let retry_after = headers
+                .get("Retry-After")
+                .and_then(|value| value.to_str().ok()?.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            for bucket in buckets.values_mut() {
+                bucket.blocked_until = Some(now + retry_after);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_request_weight() {
+        assert_eq!(
+            request_weight("fapi/v1/depth", "symbol=BTCUSDT&limit=5"),
+            2.0
+        );
+        assert_eq!(request_weight("fapi/v1/depth", "symbol=BTCUSDT"), 10.0);
+        assert_eq!(request_weight("fapi/v1/depth", "limit=1000"), 20.0);
+        assert_eq!(request_weight("fapi/v1/openOrders", "symbol=BTCUSDT"), 1.0);
+        assert_eq!(
+            request_weight("fapi/v1/openOrders", "recvWindow=5000"),
+            40.0
+        );
+        assert_eq!(order_count(&Method::POST, "fapi/v1/order"), 1.0);
+        assert_eq!(order_count(&Method::PUT, "fapi/v1/order"), 1.0);
+        assert_eq!(order_count(&Method::DELETE, "fapi/v1/order"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_info_limits() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        let rate_limits = serde_json::from_str::<Vec<RateLimit>>(
+            r#"[{"rateLimitType":"REQUEST_WEIGHT","interval":"MINUTE","intervalNum":1,"limit":20},{"rateLimitType":"ORDERS","interval":"MINUTE","intervalNum":1,"limit":1200},{"rateLimitType":"ORDERS","interval":"SECOND","intervalNum":10,"limit":1}]"#,
+        )
+        .unwrap();
+        limiter.set_limits(&rate_limits);
+
+        limiter
+            .acquire(&Method::POST, "fapi/v1/order", "")
+            .await
+            .unwrap();
+        match limiter.acquire(&Method::POST, "fapi/v1/order", "").await {
+            Err(BinanceError::RateLimited(msg)) => assert!(msg.starts_with("ORDERS_10S")),
+            res => panic!("unexpected result {res:?}"),
+        }
+        // the weight limit is not reached, cancels do not count as orders
+        for _ in 0..10 {
+            limiter
+                .acquire(&Method::DELETE, "fapi/v1/order", "")
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_headers() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        let mut headers = HeaderMap::new();
+        headers.insert("X-MBX-USED-WEIGHT-1M", HeaderValue::from_static("2395"));
+        limiter.on_response(StatusCode::OK, &headers);
+
+        let query_str = "symbol=BTCUSDT&limit=500";
+        match limiter
+            .acquire(&Method::GET, "fapi/v1/depth", query_str)
+            .await
+        {
+            Err(BinanceError::RateLimited(msg)) => assert!(msg.starts_with("REQUEST_WEIGHT_1M")),
+            res => panic!("unexpected result {res:?}"),
+        }
+        limiter
+            .acquire(&Method::GET, "fapi/v1/time", "")
+            .await
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Retry-After", HeaderValue::from_static("30"));
+        limiter.on_response(StatusCode::IM_A_TEAPOT, &headers);
+        assert!(limiter
+            .acquire(&Method::GET, "fapi/v1/time", "")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait() {
+        let limiter = RateLimiter::new(RateLimitMode::Wait);
+        let rate_limits = serde_json::from_str::<Vec<RateLimit>>(
+            r#"[{"rateLimitType":"REQUEST_WEIGHT","interval":"SECOND","intervalNum":1,"limit":10}]"#,
+        )
+        .unwrap();
+        limiter.set_limits(&rate_limits);
+        // the default per minute bucket is still there
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 4);
+
+        let start = Instant::now();
+        // the last two requests wait 100ms each for a token
+        for _ in 0..12 {
+            limiter
+                .acquire(&Method::GET, "fapi/v1/time", "")
+                .await
+                .unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(190));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::BinanceResult,
+    http::BinanceHttp,
+    types::{
+        BinanceAccountInfo, BinanceBalance, BinanceMarginType, BinancePositionRisk,
+        ChangeLeverageResponse, ChangeMarginTypeResponse, CommissionRate,
+    },
+};
+
+impl BinanceHttp {
+    pub async fn get_balance(&self) -> BinanceResult<Vec<BinanceBalance>> {
+        self.send_get_request::<Vec<BinanceBalance>, _>(
+            "fapi/v2/balance",
+            HashMap::<&str, &str>::new(),
+            true,
+        )
+        .await
+    }
+
+    pub async fn get_account_info(&self) -> BinanceResult<BinanceAccountInfo> {
+        self.send_get_request::<BinanceAccountInfo, _>(
+            "fapi/v2/account",
+            HashMap::<&str, &str>::new(),
+            true,
+        )
+        .await
+    }
+
+    pub async fn get_position_risk(
+        &self,
+        symbol: Option<&str>,
+    ) -> BinanceResult<Vec<BinancePositionRisk>> {
+        let mut params = HashMap::new();
+        if let Some(symbol) = symbol {
+            params.insert("symbol", symbol);
+        }
+
+        self.send_get_request::<Vec<BinancePositionRisk>, _>("fapi/v2/positionRisk", params, true)
+            .await
+    }
+
+    pub async fn change_leverage(
+        &self,
+        symbol: &str,
+        leverage: u32,
+    ) -> BinanceResult<ChangeLeverageResponse> {
+        let leverage = leverage.to_string();
+        let params = HashMap::from([("symbol", symbol), ("leverage", leverage.as_str())]);
+
+        self.send_post_request::<ChangeLeverageResponse, _>("fapi/v1/leverage", params)
+            .await
+    }
+
+    pub async fn change_margin_type(
+        &self,
+        symbol: &str,
+        margin_type: BinanceMarginType,
+    ) -> BinanceResult<ChangeMarginTypeResponse> {
+        let margin_type = margin_type.to_string();
+        let params = HashMap::from([("symbol", symbol), ("marginType", margin_type.as_str())]);
+
+        self.send_post_request::<ChangeMarginTypeResponse, _>("fapi/v1/marginType", params)
+            .await
+    }
+
+    pub async fn get_commission_rate(&self, symbol: &str) -> BinanceResult<CommissionRate> {
+        let params = HashMap::from([("symbol", symbol)]);
+
+        self.send_get_request::<CommissionRate, _>("fapi/v1/commissionRate", params, true)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{header, method, path, query_param, query_param_contains},
+        Mock, MockBuilder, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn test_client(server: &MockServer) -> BinanceHttp {
+        BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri())
+    }
+
+    /// Matches signed `http_method` requests to `endpoint`.
+    fn signed_request(http_method: &str, endpoint: &str) -> MockBuilder {
+        Mock::given(method(http_method))
+            .and(path(format!("/{endpoint}")))
+            .and(header("X-MBX-APIKEY", "key"))
+            .and(query_param("recvWindow", "5000"))
+            .and(query_param_contains("timestamp", ""))
+            .and(query_param_contains("signature", ""))
+    }
+
+    fn ok(body: Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(body)
+    }
+
+    #[tokio::test]
+    async fn test_get_balance() {
+        let server = MockServer::start().await;
+        signed_request("GET", "fapi/v2/balance")
+            .respond_with(ok(json!([{
+                "accountAlias": "SgsR", "asset": "USDT", "balance": "122607.35137903",
+                "crossWalletBalance": "23.72469206", "crossUnPnl": "0.00000000",
+                "availableBalance": "23.72469206", "maxWithdrawAmount": "23.72469206",
+                "marginAvailable": true, "updateTime": 1617939110373i64
+            }])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let balances = test_client(&server).get_balance().await.unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].asset, "USDT");
+        assert_eq!(balances[0].balance, "122607.35137903");
+        assert_eq!(balances[0].available_balance, "23.72469206");
+    }
+
+    #[tokio::test]
+    async fn test_get_account_info() {
+        let server = MockServer::start().await;
+        signed_request("GET", "fapi/v2/account")
+            .respond_with(ok(json!({
+                "feeTier": 0, "canTrade": true, "canDeposit": true, "canWithdraw": true,
+                "updateTime": 0, "multiAssetsMargin": false,
+                "totalInitialMargin": "0.00000000", "totalMaintMargin": "0.00000000",
+                "totalWalletBalance": "23.72469206", "totalUnrealizedProfit": "0.00000000",
+                "totalMarginBalance": "23.72469206",
+                "totalPositionInitialMargin": "0.00000000",
+                "totalOpenOrderInitialMargin": "0.00000000",
+                "totalCrossWalletBalance": "23.72469206", "totalCrossUnPnl": "0.00000000",
+                "availableBalance": "23.72469206", "maxWithdrawAmount": "23.72469206",
+                "assets": [{
+                    "asset": "USDT", "walletBalance": "23.72469206",
+                    "unrealizedProfit": "0.00000000", "marginBalance": "23.72469206",
+                    "maintMargin": "0.00000000", "initialMargin": "0.00000000",
+                    "positionInitialMargin": "0.00000000",
+                    "openOrderInitialMargin": "0.00000000",
+                    "crossWalletBalance": "23.72469206", "crossUnPnl": "0.00000000",
+                    "availableBalance": "23.72469206", "maxWithdrawAmount": "23.72469206",
+                    "marginAvailable": true, "updateTime": 1625474304765i64
+                }],
+                "positions": [{
+                    "symbol": "BTCUSDT", "initialMargin": "0", "maintMargin": "0",
+                    "unrealizedProfit": "0.00000000", "positionInitialMargin": "0",
+                    "openOrderInitialMargin": "0", "leverage": "100", "isolated": true,
+                    "entryPrice": "0.00000", "maxNotional": "250000", "positionSide": "BOTH",
+                    "positionAmt": "0", "updateTime": 0
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let account = test_client(&server).get_account_info().await.unwrap();
+        assert!(account.can_trade);
+        assert_eq!(account.total_wallet_balance, "23.72469206");
+        assert_eq!(account.assets[0].asset, "USDT");
+        assert_eq!(account.positions[0].symbol, "BTCUSDT");
+        assert_eq!(account.positions[0].leverage, "100");
+    }
+
+    #[tokio::test]
+    async fn test_get_position_risk() {
+        let server = MockServer::start().await;
+        signed_request("GET", "fapi/v2/positionRisk")
+            .and(query_param("symbol", "BTCUSDT"))
+            .respond_with(ok(json!([{
+                "entryPrice": "0.00000", "breakEvenPrice": "0.0", "marginType": "isolated",
+                "isAutoAddMargin": "false", "isolatedMargin": "0.00000000", "leverage": "10",
+                "liquidationPrice": "0", "markPrice": "6679.50671178",
+                "maxNotionalValue": "20000000", "positionAmt": "0.000", "notional": "0",
+                "isolatedWallet": "0", "symbol": "BTCUSDT", "unRealizedProfit": "0.00000000",
+                "positionSide": "BOTH", "updateTime": 0
+            }])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let positions = test_client(&server)
+            .get_position_risk(Some("BTCUSDT"))
+            .await
+            .unwrap();
+        assert_eq!(positions[0].symbol, "BTCUSDT");
+        assert_eq!(positions[0].margin_type, "isolated");
+        assert_eq!(positions[0].mark_price, "6679.50671178");
+    }
+
+    #[tokio::test]
+    async fn test_change_leverage() {
+        let server = MockServer::start().await;
+        signed_request("POST", "fapi/v1/leverage")
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("leverage", "21"))
+            .respond_with(ok(
+                json!({"leverage": 21, "maxNotionalValue": "1000000", "symbol": "BTCUSDT"}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = test_client(&server)
+            .change_leverage("BTCUSDT", 21)
+            .await
+            .unwrap();
+        assert_eq!(res.leverage, 21);
+        assert_eq!(res.max_notional_value, "1000000");
+    }
+
+    #[tokio::test]
+    async fn test_change_margin_type() {
+        let server = MockServer::start().await;
+        signed_request("POST", "fapi/v1/marginType")
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("marginType", "ISOLATED"))
+            .respond_with(ok(json!({"code": 200, "msg": "success"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = test_client(&server)
+            .change_margin_type("BTCUSDT", BinanceMarginType::Isolated)
+            .await
+            .unwrap();
+        assert_eq!(res.code, 200);
+        assert_eq!(res.msg, "success");
+    }
+
+    #[tokio::test]
+    async fn test_get_commission_rate() {
+        let server = MockServer::start().await;
+        signed_request("GET", "fapi/v1/commissionRate")
+            .and(query_param("symbol", "BTCUSDT"))
+            .respond_with(ok(json!({
+                "symbol": "BTCUSDT",
+                "makerCommissionRate": "0.0002",
+                "takerCommissionRate": "0.0004"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rate = test_client(&server)
+            .get_commission_rate("BTCUSDT")
+            .await
+            .unwrap();
+        assert_eq!(rate.maker_commission_rate, "0.0002");
+        assert_eq!(rate.taker_commission_rate, "0.0004");
+    }
+}
@@ -1,36 +1,60 @@
 use std::collections::HashMap;
 
+use chrono::Utc;
+
+use crate::errors::BinanceResult;
 use crate::http::BinanceHttp;
 use crate::types::{ExchangeInfo, OrderBook, ServerTimeResponse};
 
 impl BinanceHttp {
-    pub async fn check_server_time(&self) -> anyhow::Result<ServerTimeResponse> {
+    pub async fn check_server_time(&self) -> BinanceResult<ServerTimeResponse> {
         let server_time = self
-            .send_get_request::<ServerTimeResponse>("fapi/v1/time", HashMap::new())
+            .send_get_request::<ServerTimeResponse, _>(
+                "fapi/v1/time",
+                HashMap::<&str, &str>::new(),
+                false,
+            )
             .await?;
         Ok(server_time)
     }
 
-    pub async fn get_exchange_info(&self) -> anyhow::Result<ExchangeInfo> {
+    /// Samples the server time into the clock of the client and returns the
+    /// resulting offset in milliseconds. Call it periodically to follow the
+    /// drift of the host clock.
+    pub async fn sync_clock(&self) -> BinanceResult<i64> {
+        let sent_ms = Utc::now().timestamp_millis();
+        let server_time = self.check_server_time().await?;
+        let received_ms = Utc::now().timestamp_millis();
+        self.clock()
+            .add_sample(sent_ms, server_time.server_time as i64, received_ms);
+        Ok(self.clock().offset_ms())
+    }
+
+    pub async fn get_exchange_info(&self) -> BinanceResult<ExchangeInfo> {
         let response = self
-            .send_get_request::<ExchangeInfo>("fapi/v1/exchangeInfo", HashMap::new())
+            .send_get_request::<ExchangeInfo, _>(
+                "fapi/v1/exchangeInfo",
+                HashMap::<&str, &str>::new(),
+                false,
+            )
             .await?;
+        self.rate_limiter().set_limits(&response.rate_limits);
         Ok(response)
     }
 
     pub async fn get_orderbook(
         &self,
-        symbol: &String,
+        symbol: &str,
         limit: Option<i32>,
-    ) -> anyhow::Result<OrderBook> {
+    ) -> BinanceResult<OrderBook> {
         let limit = limit.unwrap_or(5).to_string();
 
         let mut params = HashMap::new();
-        params.insert("symbol", symbol.as_str());
+        params.insert("symbol", symbol);
         params.insert("limit", limit.as_str());
 
         let response = self
-            .send_get_request::<OrderBook>("fapi/v1/depth", params)
+            .send_get_request::<OrderBook, _>("fapi/v1/depth", params, false)
             .await?;
         Ok(response)
     }
@@ -38,29 +62,69 @@ impl BinanceHttp {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::sync::Arc;
+
+    use reqwest::Method;
+
+    use crate::{http::BinanceHttp, transport::MockTransport, types::Filter};
+
+    /// Client answering `method` requests to `endpoint` with `fixture`.
+    fn mock_client(
+        method: Method,
+        endpoint: &str,
+        fixture: &str,
+    ) -> (BinanceHttp, Arc<MockTransport>) {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_ok(method, endpoint, fixture);
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_transport(transport.clone());
+        (client, transport)
+    }
 
     #[tokio::test]
     async fn test_check_server_time() {
-        let binance_http = BinanceHttp::new("api_key".to_string(), "api_secret".to_string());
+        let (binance_http, _) = mock_client(
+            Method::GET,
+            "fapi/v1/time",
+            include_str!("../../fixtures/server_time.json"),
+        );
         let server_time = binance_http.check_server_time().await.unwrap();
-        println!("server_time: {:?}", server_time);
+        assert_eq!(server_time.server_time, 1499827319559);
     }
 
     #[tokio::test]
     async fn test_get_exchange_info() {
-        let binance_http = BinanceHttp::new("api_key".to_string(), "api_secret".to_string());
+        let (binance_http, _) = mock_client(
+            Method::GET,
+            "fapi/v1/exchangeInfo",
+            include_str!("../../fixtures/exchange_info.json"),
+        );
         let exchange_info = binance_http.get_exchange_info().await.unwrap();
-        println!("exchange_info: {:?}", exchange_info);
+        assert_eq!(exchange_info.rate_limits.len(), 3);
+        let symbol = &exchange_info.symbols[0];
+        assert_eq!(symbol.symbol, "BTCUSDT");
+        assert!(matches!(
+            &symbol.filters[0],
+            Filter::PriceFilter { tick_size, .. } if tick_size == "0.10"
+        ));
     }
 
     #[tokio::test]
     async fn test_get_orderbook() {
-        let binance_http = BinanceHttp::new("api_key".to_string(), "api_secret".to_string());
+        let (binance_http, transport) = mock_client(
+            Method::GET,
+            "fapi/v1/depth",
+            include_str!("../../fixtures/depth.json"),
+        );
         let orderbook = binance_http
-            .get_orderbook(&"BTCUSDT".to_string(), Some(10))
+            .get_orderbook("BTCUSDT", Some(10))
             .await
             .unwrap();
-        println!("orderbook: {:?}", orderbook);
+        assert_eq!(orderbook.last_update_id, 1027024);
+        assert_eq!(orderbook.asks[0][0], "4.00000200");
+        let request = &transport.requests()[0];
+        assert_eq!(request.query_param("symbol").unwrap(), "BTCUSDT");
+        assert_eq!(request.query_param("limit").unwrap(), "10");
     }
 }
@@ -1,3 +1,4 @@
 pub mod account;
 pub mod market;
 pub mod trade;
+pub mod user_stream;
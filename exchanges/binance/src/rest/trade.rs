@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::{BinanceError, BinanceResult},
+    http::BinanceHttp,
+    types::{
+        BatchOrderResult, BinanceOrder, BinanceOrderRef, CancelAllOrdersResponse,
+        ModifyOrderRequest, NewOrderRequest,
+    },
+};
+
+/// Maximum number of orders accepted by `fapi/v1/batchOrders`.
+pub const MAX_BATCH_ORDERS: usize = 5;
+
+impl BinanceHttp {
+    pub async fn new_order(&self, request: &NewOrderRequest) -> BinanceResult<BinanceOrder> {
+        self.send_post_request::<BinanceOrder, _>("fapi/v1/order", request)
+            .await
+    }
+
+    pub async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_ref: &BinanceOrderRef,
+    ) -> BinanceResult<BinanceOrder> {
+        let (key, value) = order_ref.to_param();
+        let params = HashMap::from([("symbol", symbol), (key, value.as_str())]);
+
+        self.send_delete_request::<BinanceOrder, _>("fapi/v1/order", params)
+            .await
+    }
+
+    pub async fn cancel_all_orders(&self, symbol: &str) -> BinanceResult<CancelAllOrdersResponse> {
+        let params = HashMap::from([("symbol", symbol)]);
+
+        self.send_delete_request::<CancelAllOrdersResponse, _>("fapi/v1/allOpenOrders", params)
+            .await
+    }
+
+    pub async fn modify_order(&self, request: &ModifyOrderRequest) -> BinanceResult<BinanceOrder> {
+        self.send_put_request::<BinanceOrder, _>("fapi/v1/order", request)
+            .await
+    }
+
+    pub async fn query_order(
+        &self,
+        symbol: &str,
+        order_ref: &BinanceOrderRef,
+    ) -> BinanceResult<BinanceOrder> {
+        let (key, value) = order_ref.to_param();
+        let params = HashMap::from([("symbol", symbol), (key, value.as_str())]);
+
+        self.send_get_request::<BinanceOrder, _>("fapi/v1/order", params, true)
+            .await
+    }
+
+    pub async fn get_open_orders(&self, symbol: Option<&str>) -> BinanceResult<Vec<BinanceOrder>> {
+        let mut params = HashMap::new();
+        if let Some(symbol) = symbol {
+            params.insert("symbol", symbol);
+        }
+
+        self.send_get_request::<Vec<BinanceOrder>, _>("fapi/v1/openOrders", params, true)
+            .await
+    }
+
+    pub async fn place_batch_orders(
+        &self,
+        requests: &[NewOrderRequest],
+    ) -> BinanceResult<Vec<BatchOrderResult>> {
+        if requests.is_empty() || requests.len() > MAX_BATCH_ORDERS {
+            return Err(BinanceError::InvalidRequest(format!(
+                "batch orders accepts 1 to {MAX_BATCH_ORDERS} orders, got {}",
+                requests.len()
+            )));
+        }
+        let batch_orders = serde_json::to_string(requests)?;
+        let params = HashMap::from([("batchOrders", batch_orders.as_str())]);
+
+        self.send_post_request::<Vec<BatchOrderResult>, _>("fapi/v1/batchOrders", params)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BinanceOrderType, BinanceSide};
+
+    #[tokio::test]
+    async fn test_batch_size() {
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string()).unwrap();
+        let request = NewOrderRequest::new("BTCUSDT", BinanceSide::Buy, BinanceOrderType::Market)
+            .quantity("0.001");
+        for requests in [vec![], vec![request; MAX_BATCH_ORDERS + 1]] {
+            // refused before anything is sent
+            match client.place_batch_orders(&requests).await {
+                Err(BinanceError::InvalidRequest(_)) => {}
+                res => panic!("unexpected result {res:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use reqwest::Method;
+
+use crate::{errors::BinanceResult, http::BinanceHttp, types::ListenKeyResponse};
+
+impl BinanceHttp {
+    /// Starts a user data stream, or returns the active listen key and extends
+    /// its validity. A key expires 60 minutes after its last keepalive.
+    pub async fn create_listen_key(&self) -> BinanceResult<String> {
+        let response = self
+            .send_user_stream_request::<ListenKeyResponse>(Method::POST, "fapi/v1/listenKey")
+            .await?;
+        Ok(response.listen_key)
+    }
+
+    /// Extends the validity of the active listen key by 60 minutes.
+    pub async fn keepalive_listen_key(&self) -> BinanceResult<String> {
+        let response = self
+            .send_user_stream_request::<ListenKeyResponse>(Method::PUT, "fapi/v1/listenKey")
+            .await?;
+        Ok(response.listen_key)
+    }
+
+    pub async fn close_listen_key(&self) -> BinanceResult<()> {
+        self.send_user_stream_request::<serde_json::Value>(Method::DELETE, "fapi/v1/listenKey")
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{header, method, path, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listen_key() {
+        let server = MockServer::start().await;
+        let listen_key = "pqia91ma19a5s61cv6a81va65sdf19v8a65a1a5s61cv6a81va65sdf19v8a65a1";
+        for http_method in ["POST", "PUT"] {
+            Mock::given(method(http_method))
+                .and(path("/fapi/v1/listenKey"))
+                .and(header("X-MBX-APIKEY", "key"))
+                .and(query_param_is_missing("signature"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(serde_json::json!({ "listenKey": listen_key })),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        assert_eq!(client.create_listen_key().await.unwrap(), listen_key);
+        assert_eq!(client.keepalive_listen_key().await.unwrap(), listen_key);
+        client.close_listen_key().await.unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+use crate::errors::BinanceError;
+
+pub use cefi_rs_common::retry::RetryPolicy;
+
+/// Codes of failures that may go away on their own: unknown and internal
+/// errors, backend timeout, server busy and a timestamp outside of the receive
+/// window, which a new attempt signs again.
+const TRANSIENT_CODES: [i64; 5] = [-1000, -1001, -1007, -1008, -1021];
+
+/// Whether the request may succeed when sent again.
+pub(crate) fn is_transient(err: &BinanceError) -> bool {
+    match err {
+        BinanceError::HttpError(err) => err.is_timeout() || err.is_connect(),
+        BinanceError::ServerError(..) => true,
+        BinanceError::ApiError(code, _) => TRANSIENT_CODES.contains(code),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&BinanceError::ApiError(
+            -1007,
+            "Timeout waiting for response from backend server.".to_string()
+        )));
+        assert!(!is_transient(&BinanceError::ApiError(
+            -2019,
+            "Margin is insufficient.".to_string()
+        )));
+        assert!(is_transient(&BinanceError::ServerError(
+            503,
+            "Service Unavailable".to_string()
+        )));
+        assert!(!is_transient(&BinanceError::DeserializeError(
+            "expected value".to_string()
+        )));
+    }
+}
@@ -0,0 +1,3 @@
+pub use cefi_rs_common::transport::{
+    HttpRequest, HttpResponse, MockTransport, ReqwestTransport, Transport, TransportError,
+};
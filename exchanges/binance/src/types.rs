@@ -1,4 +1,306 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BinanceErrorResponse {
+    pub code: i64,
+    pub msg: String,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceSide {
+    Buy,
+    Sell,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinancePositionSide {
+    Both,
+    Long,
+    Short,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceOrderType {
+    Limit,
+    Market,
+    Stop,
+    StopMarket,
+    TakeProfit,
+    TakeProfitMarket,
+    TrailingStopMarket,
+    Liquidation,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceTimeInForce {
+    Gtc,
+    Ioc,
+    Fok,
+    /// Good till crossing, i.e. post only.
+    Gtx,
+    Gtd,
+    /// Set by the venue on `closePosition` orders, not accepted in requests.
+    #[serde(rename = "GTE_GTC")]
+    #[strum(serialize = "GTE_GTC")]
+    GteGtc,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceOrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+    /// Liquidation order filled by the insurance fund.
+    NewInsurance,
+    /// Liquidation order filled by auto-deleveraging.
+    NewAdl,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceWorkingType {
+    MarkPrice,
+    ContractPrice,
+}
+
+/// Identifies an existing order either by the exchange id or by the client id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinanceOrderRef {
+    OrderId(i64),
+    ClientOrderId(String),
+}
+
+impl BinanceOrderRef {
+    pub(crate) fn to_param(&self) -> (&'static str, String) {
+        match self {
+            BinanceOrderRef::OrderId(order_id) => ("orderId", order_id.to_string()),
+            BinanceOrderRef::ClientOrderId(cloid) => ("origClientOrderId", cloid.clone()),
+        }
+    }
+}
+
+/// Binance expects booleans as `"true"`/`"false"`, also inside `batchOrders`.
+fn serialize_bool_as_str<S>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(value) => serializer.serialize_str(if *value { "true" } else { "false" }),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Parameters of `POST fapi/v1/order`, also used as an item of `fapi/v1/batchOrders`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOrderRequest {
+    pub symbol: String,
+    pub side: BinanceSide,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_side: Option<BinancePositionSide>,
+    #[serde(rename = "type")]
+    pub order_type: BinanceOrderType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<BinanceTimeInForce>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_bool_as_str"
+    )]
+    pub reduce_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_client_order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_bool_as_str"
+    )]
+    pub close_position: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation_price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_rate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_type: Option<BinanceWorkingType>,
+}
+
+impl NewOrderRequest {
+    pub fn new(symbol: &str, side: BinanceSide, order_type: BinanceOrderType) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side,
+            position_side: None,
+            order_type,
+            time_in_force: None,
+            quantity: None,
+            reduce_only: None,
+            price: None,
+            new_client_order_id: None,
+            stop_price: None,
+            close_position: None,
+            activation_price: None,
+            callback_rate: None,
+            working_type: None,
+        }
+    }
+
+    pub fn position_side(mut self, position_side: BinancePositionSide) -> Self {
+        self.position_side = Some(position_side);
+        self
+    }
+
+    pub fn time_in_force(mut self, time_in_force: BinanceTimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn quantity(mut self, quantity: &str) -> Self {
+        self.quantity = Some(quantity.to_string());
+        self
+    }
+
+    pub fn reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = Some(reduce_only);
+        self
+    }
+
+    pub fn price(mut self, price: &str) -> Self {
+        self.price = Some(price.to_string());
+        self
+    }
+
+    pub fn new_client_order_id(mut self, cloid: &str) -> Self {
+        self.new_client_order_id = Some(cloid.to_string());
+        self
+    }
+
+    pub fn stop_price(mut self, stop_price: &str) -> Self {
+        self.stop_price = Some(stop_price.to_string());
+        self
+    }
+
+    pub fn close_position(mut self, close_position: bool) -> Self {
+        self.close_position = Some(close_position);
+        self
+    }
+
+    pub fn activation_price(mut self, activation_price: &str) -> Self {
+        self.activation_price = Some(activation_price.to_string());
+        self
+    }
+
+    pub fn callback_rate(mut self, callback_rate: &str) -> Self {
+        self.callback_rate = Some(callback_rate.to_string());
+        self
+    }
+
+    pub fn working_type(mut self, working_type: BinanceWorkingType) -> Self {
+        self.working_type = Some(working_type);
+        self
+    }
+}
+
+/// Parameters of `PUT fapi/v1/order`. Only limit orders can be modified and
+/// Binance requires side, quantity and price on every request.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifyOrderRequest {
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orig_client_order_id: Option<String>,
+    pub side: BinanceSide,
+    pub quantity: String,
+    pub price: String,
+}
+
+impl ModifyOrderRequest {
+    pub fn new(
+        symbol: &str,
+        order_ref: BinanceOrderRef,
+        side: BinanceSide,
+        quantity: &str,
+        price: &str,
+    ) -> Self {
+        let (order_id, orig_client_order_id) = match order_ref {
+            BinanceOrderRef::OrderId(order_id) => (Some(order_id), None),
+            BinanceOrderRef::ClientOrderId(cloid) => (None, Some(cloid)),
+        };
+        Self {
+            symbol: symbol.to_string(),
+            order_id,
+            orig_client_order_id,
+            side,
+            quantity: quantity.to_string(),
+            price: price.to_string(),
+        }
+    }
+}
+
+/// Order as returned by the new, cancel, modify and query order endpoints.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceOrder {
+    pub order_id: i64,
+    pub symbol: String,
+    pub status: BinanceOrderStatus,
+    pub client_order_id: String,
+    pub price: String,
+    pub avg_price: String,
+    pub orig_qty: String,
+    pub executed_qty: String,
+    pub cum_qty: Option<String>,
+    pub cum_quote: String,
+    pub time_in_force: BinanceTimeInForce,
+    #[serde(rename = "type")]
+    pub order_type: BinanceOrderType,
+    pub orig_type: BinanceOrderType,
+    pub reduce_only: bool,
+    pub close_position: bool,
+    pub side: BinanceSide,
+    pub position_side: BinancePositionSide,
+    pub stop_price: String,
+    pub working_type: BinanceWorkingType,
+    pub price_protect: bool,
+    pub activate_price: Option<String>,
+    pub price_rate: Option<String>,
+    pub time: Option<i64>,
+    pub update_time: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CancelAllOrdersResponse {
+    pub code: i64,
+    pub msg: String,
+}
+
+/// Every order of a batch succeeds or fails on its own.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum BatchOrderResult {
+    Order(Box<BinanceOrder>),
+    Error(BinanceErrorResponse),
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -6,6 +308,139 @@ pub struct ServerTimeResponse {
     pub server_time: u64,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenKeyResponse {
+    pub listen_key: String,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceMarginType {
+    Isolated,
+    Crossed,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceBalance {
+    pub account_alias: String,
+    pub asset: String,
+    pub balance: String,
+    pub cross_wallet_balance: String,
+    pub cross_un_pnl: String,
+    pub available_balance: String,
+    pub max_withdraw_amount: String,
+    pub margin_available: bool,
+    pub update_time: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceAccountInfo {
+    pub fee_tier: i32,
+    pub can_trade: bool,
+    pub can_deposit: bool,
+    pub can_withdraw: bool,
+    pub update_time: i64,
+    pub multi_assets_margin: bool,
+    pub total_initial_margin: String,
+    pub total_maint_margin: String,
+    pub total_wallet_balance: String,
+    pub total_unrealized_profit: String,
+    pub total_margin_balance: String,
+    pub total_position_initial_margin: String,
+    pub total_open_order_initial_margin: String,
+    pub total_cross_wallet_balance: String,
+    pub total_cross_un_pnl: String,
+    pub available_balance: String,
+    pub max_withdraw_amount: String,
+    pub assets: Vec<BinanceAccountAsset>,
+    pub positions: Vec<BinanceAccountPosition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceAccountAsset {
+    pub asset: String,
+    pub wallet_balance: String,
+    pub unrealized_profit: String,
+    pub margin_balance: String,
+    pub maint_margin: String,
+    pub initial_margin: String,
+    pub position_initial_margin: String,
+    pub open_order_initial_margin: String,
+    pub cross_wallet_balance: String,
+    pub cross_un_pnl: String,
+    pub available_balance: String,
+    pub max_withdraw_amount: String,
+    pub margin_available: Option<bool>,
+    pub update_time: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceAccountPosition {
+    pub symbol: String,
+    pub initial_margin: String,
+    pub maint_margin: String,
+    pub unrealized_profit: String,
+    pub position_initial_margin: String,
+    pub open_order_initial_margin: String,
+    pub leverage: String,
+    pub isolated: bool,
+    pub entry_price: String,
+    pub max_notional: String,
+    pub position_side: BinancePositionSide,
+    pub position_amt: String,
+    pub update_time: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BinancePositionRisk {
+    pub symbol: String,
+    pub entry_price: String,
+    pub break_even_price: Option<String>,
+    /// `isolated` or `cross`
+    pub margin_type: String,
+    pub is_auto_add_margin: String,
+    pub isolated_margin: String,
+    pub leverage: String,
+    pub liquidation_price: String,
+    pub mark_price: String,
+    pub max_notional_value: String,
+    pub position_amt: String,
+    pub notional: String,
+    pub isolated_wallet: String,
+    pub un_realized_profit: String,
+    pub position_side: BinancePositionSide,
+    pub update_time: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeLeverageResponse {
+    pub leverage: u32,
+    pub max_notional_value: String,
+    pub symbol: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChangeMarginTypeResponse {
+    pub code: i64,
+    pub msg: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommissionRate {
+    pub symbol: String,
+    pub maker_commission_rate: String,
+    pub taker_commission_rate: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExchangeInfo {
@@ -53,7 +488,7 @@ pub struct Symbol {
     pub underlying_sub_type: Vec<String>,
     pub settle_plan: Option<i32>,
     pub trigger_protect: String,
-    // pub filters: Vec<Filter>,
+    pub filters: Vec<Filter>,
     #[serde(rename = "OrderType")]
     pub order_types: Option<Vec<String>>,
     pub time_in_force: Vec<String>,
@@ -62,7 +497,7 @@ pub struct Symbol {
 }
 
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all_fields = "camelCase")]
 #[serde(tag = "filterType")]
 pub enum Filter {
     #[serde(rename = "PRICE_FILTER")]
@@ -93,13 +528,16 @@ pub enum Filter {
     PercentPrice {
         multiplier_up: String,
         multiplier_down: String,
-        multiplier_decimal: i32,
+        multiplier_decimal: String,
     },
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OrderBook {
-    pub last_update_id: Option<i64>,
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: i64,
     #[serde(rename = "E")]
     pub event_time: i64,
     #[serde(rename = "T")]
@@ -107,3 +545,104 @@ pub struct OrderBook {
     pub bids: Vec<[String; 2]>, // [price, quantity]
     pub asks: Vec<[String; 2]>, // [price, quantity]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_new_order_request() {
+        let req = NewOrderRequest::new("BTCUSDT", BinanceSide::Buy, BinanceOrderType::Limit)
+            .time_in_force(BinanceTimeInForce::Gtc)
+            .quantity("0.001")
+            .price("10001")
+            .reduce_only(true);
+        assert_eq!(
+            serde_urlencoded::to_string(&req).unwrap(),
+            "symbol=BTCUSDT&side=BUY&type=LIMIT&timeInForce=GTC&quantity=0.001&reduceOnly=true&price=10001"
+        );
+        assert_eq!(
+            serde_json::to_string(&req).unwrap(),
+            r#"{"symbol":"BTCUSDT","side":"BUY","type":"LIMIT","timeInForce":"GTC","quantity":"0.001","reduceOnly":"true","price":"10001"}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_symbol_filters() {
+        let res = r#"{"symbol":"BLZUSDT","pair":"BLZUSDT","contractType":"PERPETUAL","deliveryDate":4133404800000,"onboardDate":1598252400000,"status":"TRADING","maintMarginPercent":"2.5000","requiredMarginPercent":"5.0000","baseAsset":"BLZ","quoteAsset":"USDT","marginAsset":"USDT","pricePrecision":5,"quantityPrecision":0,"baseAssetPrecision":8,"quotePrecision":8,"underlyingType":"COIN","underlyingSubType":["STORAGE"],"settlePlan":0,"triggerProtect":"0.15","filters":[{"filterType":"PRICE_FILTER","maxPrice":"300","minPrice":"0.0001","tickSize":"0.0001"},{"filterType":"LOT_SIZE","maxQty":"10000000","minQty":"1","stepSize":"1"},{"filterType":"MARKET_LOT_SIZE","maxQty":"590119","minQty":"1","stepSize":"1"},{"filterType":"MAX_NUM_ORDERS","limit":200},{"filterType":"MAX_NUM_ALGO_ORDERS","limit":10},{"filterType":"MIN_NOTIONAL","notional":"5.0"},{"filterType":"PERCENT_PRICE","multiplierUp":"1.1500","multiplierDown":"0.8500","multiplierDecimal":"4"},{"filterType":"POSITION_RISK_CONTROL","positionControlSide":"NONE"}],"OrderType":["LIMIT","MARKET","STOP","STOP_MARKET","TAKE_PROFIT","TAKE_PROFIT_MARKET","TRAILING_STOP_MARKET"],"timeInForce":["GTC","IOC","FOK","GTX"],"liquidationFee":"0.010000","marketTakeBound":"0.30"}"#;
+        let symbol = serde_json::from_str::<Symbol>(res).unwrap();
+        assert_eq!(symbol.filters.len(), 8);
+        assert!(matches!(
+            symbol.filters[0],
+            Filter::PriceFilter { ref tick_size, .. } if tick_size == "0.0001"
+        ));
+        assert!(matches!(symbol.filters[7], Filter::Other));
+    }
+
+    #[test]
+    fn test_deserialize_order() {
+        let res = r#"{"clientOrderId":"testOrder","cumQty":"0","cumQuote":"0","executedQty":"0","orderId":22542179,"avgPrice":"0.00000","origQty":"10","price":"0","reduceOnly":false,"side":"BUY","positionSide":"SHORT","status":"NEW","stopPrice":"9300","closePosition":false,"symbol":"BTCUSDT","timeInForce":"GTD","type":"TRAILING_STOP_MARKET","origType":"TRAILING_STOP_MARKET","activatePrice":"9020","priceRate":"0.3","updateTime":1566818724722,"workingType":"CONTRACT_PRICE","priceProtect":false,"priceMatch":"NONE","selfTradePreventionMode":"NONE","goodTillDate":1693207680000}"#;
+        let order = serde_json::from_str::<BinanceOrder>(res).unwrap();
+        assert_eq!(order.order_id, 22542179);
+        assert_eq!(order.order_type, BinanceOrderType::TrailingStopMarket);
+        assert_eq!(order.position_side, BinancePositionSide::Short);
+    }
+
+    #[test]
+    fn test_deserialize_close_position_order() {
+        let res = r#"{"orderId":4069515435,"symbol":"BTCUSDT","status":"NEW","clientOrderId":"web_Dzm0VRFJJsxNDGBZqpLY","price":"0","avgPrice":"0","origQty":"0","executedQty":"0","cumQty":"0","cumQuote":"0","timeInForce":"GTE_GTC","type":"STOP_MARKET","reduceOnly":true,"closePosition":true,"side":"SELL","positionSide":"BOTH","stopPrice":"58000","workingType":"MARK_PRICE","priceProtect":true,"origType":"STOP_MARKET","priceMatch":"NONE","selfTradePreventionMode":"EXPIRE_MAKER","goodTillDate":0,"updateTime":1727431812343}"#;
+        let order = serde_json::from_str::<BinanceOrder>(res).unwrap();
+        assert_eq!(order.time_in_force, BinanceTimeInForce::GteGtc);
+        assert!(order.close_position);
+        assert_eq!(BinanceTimeInForce::GteGtc.to_string(), "GTE_GTC");
+    }
+
+    #[test]
+    fn test_deserialize_batch_orders_response() {
+        let res = r#"[{"clientOrderId":"testOrder","cumQty":"0","cumQuote":"0","executedQty":"0","orderId":22542179,"avgPrice":"0.00000","origQty":"10","price":"0","reduceOnly":false,"side":"BUY","positionSide":"SHORT","status":"NEW","stopPrice":"9300","closePosition":false,"symbol":"BTCUSDT","timeInForce":"GTC","type":"LIMIT","origType":"LIMIT","updateTime":1566818724722,"workingType":"CONTRACT_PRICE","priceProtect":false},{"code":-2022,"msg":"ReduceOnly Order is rejected."}]"#;
+        let res = serde_json::from_str::<Vec<BatchOrderResult>>(res).unwrap();
+        assert!(matches!(res[0], BatchOrderResult::Order(_)));
+        assert!(matches!(res[1], BatchOrderResult::Error(ref e) if e.code == -2022));
+    }
+
+    #[test]
+    fn test_deserialize_balance() {
+        let res = r#"[{"accountAlias":"SgsR","asset":"USDT","balance":"122607.35137903","crossWalletBalance":"23.72469206","crossUnPnl":"0.00000000","availableBalance":"23.72469206","maxWithdrawAmount":"23.72469206","marginAvailable":true,"updateTime":1617939110373}]"#;
+        let res = serde_json::from_str::<Vec<BinanceBalance>>(res).unwrap();
+        assert_eq!(res[0].asset, "USDT");
+    }
+
+    #[test]
+    fn test_deserialize_account_info() {
+        let res = r#"{"feeTier":0,"canTrade":true,"canDeposit":true,"canWithdraw":true,"updateTime":0,"multiAssetsMargin":false,"tradeGroupId":-1,"totalInitialMargin":"0.00000000","totalMaintMargin":"0.00000000","totalWalletBalance":"23.72469206","totalUnrealizedProfit":"0.00000000","totalMarginBalance":"23.72469206","totalPositionInitialMargin":"0.00000000","totalOpenOrderInitialMargin":"0.00000000","totalCrossWalletBalance":"23.72469206","totalCrossUnPnl":"0.00000000","availableBalance":"23.72469206","maxWithdrawAmount":"23.72469206","assets":[{"asset":"USDT","walletBalance":"23.72469206","unrealizedProfit":"0.00000000","marginBalance":"23.72469206","maintMargin":"0.00000000","initialMargin":"0.00000000","positionInitialMargin":"0.00000000","openOrderInitialMargin":"0.00000000","crossWalletBalance":"23.72469206","crossUnPnl":"0.00000000","availableBalance":"23.72469206","maxWithdrawAmount":"23.72469206","marginAvailable":true,"updateTime":1625474304765}],"positions":[{"symbol":"BTCUSDT","initialMargin":"0","maintMargin":"0","unrealizedProfit":"0.00000000","positionInitialMargin":"0","openOrderInitialMargin":"0","leverage":"100","isolated":true,"entryPrice":"0.00000","maxNotional":"250000","bidNotional":"0","askNotional":"0","positionSide":"BOTH","positionAmt":"0","updateTime":0}]}"#;
+        let res = serde_json::from_str::<BinanceAccountInfo>(res).unwrap();
+        assert_eq!(res.positions[0].position_side, BinancePositionSide::Both);
+    }
+
+    #[test]
+    fn test_deserialize_position_risk() {
+        let res = r#"[{"entryPrice":"0.00000","breakEvenPrice":"0.0","marginType":"isolated","isAutoAddMargin":"false","isolatedMargin":"0.00000000","leverage":"10","liquidationPrice":"0","markPrice":"6679.50671178","maxNotionalValue":"20000000","positionAmt":"0.000","notional":"0","isolatedWallet":"0","symbol":"BTCUSDT","unRealizedProfit":"0.00000000","positionSide":"BOTH","updateTime":0}]"#;
+        let res = serde_json::from_str::<Vec<BinancePositionRisk>>(res).unwrap();
+        assert_eq!(res[0].mark_price, "6679.50671178");
+    }
+
+    #[test]
+    fn test_deserialize_leverage_and_commission() {
+        let res = r#"{"leverage":21,"maxNotionalValue":"1000000","symbol":"BTCUSDT"}"#;
+        let res = serde_json::from_str::<ChangeLeverageResponse>(res).unwrap();
+        assert_eq!(res.leverage, 21);
+
+        let res =
+            r#"{"symbol":"BTCUSDT","makerCommissionRate":"0.0002","takerCommissionRate":"0.0004"}"#;
+        let res = serde_json::from_str::<CommissionRate>(res).unwrap();
+        assert_eq!(res.taker_commission_rate, "0.0004");
+    }
+
+    #[test]
+    fn test_deserialize_orderbook() {
+        let res = r#"{"lastUpdateId":1027024,"E":1589436922972,"T":1589436922959,"bids":[["4.00000000","431.00000000"]],"asks":[["4.00000200","12.00000000"]]}"#;
+        let res = serde_json::from_str::<OrderBook>(res).unwrap();
+        assert_eq!(res.last_update_id, 1027024);
+        assert_eq!(res.transaction_time, 1589436922959);
+    }
+}
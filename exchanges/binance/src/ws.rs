@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use cefi_rs_common::ws::{SupervisorConfig, WsProtocol, WsSupervisor};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::error;
+
+pub(crate) use cefi_rs_common::ws::{WsCommand, WsFrame};
+
+use crate::{
+    config::Environment,
+    ws_types::{BinanceStream, BinanceWsEvent, BinanceWsMessage},
+};
+
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    pub url: String,
+    /// Interval between two ping frames.
+    pub ping_interval: Duration,
+    /// Time allowed for the pong before the connection is considered dead.
+    pub pong_timeout: Duration,
+    /// Delay before the first reconnection attempt, doubled on every failure.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Binance closes connections after 24 hours, they are renewed before.
+    pub max_connection_age: Duration,
+    /// Interval between two listen key keepalives, user data streams only.
+    pub listen_key_keepalive: Duration,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            url: Environment::Mainnet.ws_market_url().to_string(),
+            ping_interval: Duration::from_secs(60),
+            pong_timeout: Duration::from_secs(10),
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_connection_age: Duration::from_secs(23 * 60 * 60),
+            listen_key_keepalive: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl WsConfig {
+    /// User data streams, `url` is the base the listen key is appended to.
+    pub fn user_data() -> Self {
+        Self {
+            url: Environment::Mainnet.ws_user_url().to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn supervisor_config(&self) -> SupervisorConfig {
+        SupervisorConfig {
+            url: self.url.clone(),
+            ping_interval: self.ping_interval,
+            pong_timeout: self.pong_timeout,
+            min_backoff: self.min_backoff,
+            max_backoff: self.max_backoff,
+            max_connection_age: Some(self.max_connection_age),
+        }
+    }
+}
+
+/// Subscription requests of the combined stream, heartbeats are ping frames.
+struct BinanceProtocol {
+    next_id: u64,
+}
+
+impl BinanceProtocol {
+    fn request(&mut self, method: &str, streams: &[String]) -> String {
+        let id = self.next_id;
+        self.next_id += 1;
+        json!({
+            "method": method,
+            "params": streams,
+            "id": id
+        })
+        .to_string()
+    }
+}
+
+impl WsProtocol for BinanceProtocol {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn subscribe(&mut self, streams: &[String]) -> String {
+        self.request("SUBSCRIBE", streams)
+    }
+
+    fn unsubscribe(&mut self, streams: &[String]) -> String {
+        self.request("UNSUBSCRIBE", streams)
+    }
+
+    fn ping(&self) -> Message {
+        Message::Ping(vec![])
+    }
+}
+
+/// Supervises a stream of `config` in a background task, connections are
+/// renewed before Binance closes them.
+pub(crate) fn supervise(
+    config: &WsConfig,
+    streams: Vec<String>,
+) -> (
+    mpsc::UnboundedReceiver<WsFrame>,
+    mpsc::UnboundedSender<WsCommand>,
+) {
+    WsSupervisor::spawn(
+        config.supervisor_config(),
+        BinanceProtocol { next_id: 1 },
+        streams,
+    )
+}
+
+fn stream_names(streams: &[BinanceStream]) -> Vec<String> {
+    streams.iter().map(|stream| stream.to_string()).collect()
+}
+
+/// Market data client on the combined stream endpoint. The connection is
+/// supervised in a background task, a dropped link is reported as
+/// `ConnectionState` events and restored with the same subscriptions.
+pub struct BinanceWs {
+    frames: mpsc::UnboundedReceiver<WsFrame>,
+    commands: mpsc::UnboundedSender<WsCommand>,
+}
+
+impl BinanceWs {
+    pub fn new(streams: Vec<BinanceStream>) -> Self {
+        Self::with_config(streams, WsConfig::default())
+    }
+
+    pub fn with_config(streams: Vec<BinanceStream>, config: WsConfig) -> Self {
+        let (frames, commands) = supervise(&config, stream_names(&streams));
+        Self { frames, commands }
+    }
+
+    /// Subscribes to more streams, they are replayed on reconnection as well.
+    pub fn subscribe(&self, streams: Vec<BinanceStream>) {
+        // the supervisor only stops once this client is dropped
+        let _ = self
+            .commands
+            .send(WsCommand::Subscribe(stream_names(&streams)));
+    }
+
+    pub fn unsubscribe(&self, streams: Vec<BinanceStream>) {
+        let _ = self
+            .commands
+            .send(WsCommand::Unsubscribe(stream_names(&streams)));
+    }
+
+    /// Returns the next message or connection state change, `None` once the
+    /// background task has stopped.
+    pub async fn next(&mut self) -> Option<BinanceWsEvent> {
+        loop {
+            match self.frames.recv().await? {
+                WsFrame::State(state) => return Some(BinanceWsEvent::State(state)),
+                WsFrame::Text(text) => match serde_json::from_str::<BinanceWsMessage>(&text) {
+                    Ok(message) => return Some(BinanceWsEvent::Update(message)),
+                    Err(err) => error!("error deserializing {text}: {err}"),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, WebSocketStream};
+
+    use super::*;
+    use crate::ws_types::{BinanceMarketEvent, ConnectionState};
+
+    const AGG_TRADE: &str = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":123456789,"s":"BTCUSDT","a":5933014,"p":"0.001","q":"100","f":100,"l":105,"T":123456785,"m":true}}"#;
+
+    fn test_config(url: String) -> WsConfig {
+        WsConfig {
+            url,
+            ping_interval: Duration::from_millis(100),
+            pong_timeout: Duration::from_millis(100),
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            max_connection_age: Duration::from_secs(60),
+            listen_key_keepalive: Duration::from_secs(60),
+        }
+    }
+
+    async fn local_server() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        (listener, url)
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        accept_async(stream).await.unwrap()
+    }
+
+    async fn next_text(server: &mut WebSocketStream<TcpStream>) -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    async fn expect_state(ws: &mut BinanceWs) -> ConnectionState {
+        match ws.next().await.unwrap() {
+            BinanceWsEvent::State(state) => state,
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_and_receive() {
+        let (listener, url) = local_server().await;
+        let mut ws = BinanceWs::with_config(
+            vec![BinanceStream::AggTrade("BTCUSDT".to_string())],
+            test_config(url),
+        );
+
+        let mut server = accept(&listener).await;
+        let subscribe = next_text(&mut server).await;
+        assert_eq!(
+            subscribe,
+            json!({"method": "SUBSCRIBE", "params": ["btcusdt@aggTrade"], "id": 1})
+        );
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+
+        server
+            .send(Message::Text(r#"{"result":null,"id":1}"#.to_string()))
+            .await
+            .unwrap();
+        server
+            .send(Message::Text(AGG_TRADE.to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            ws.next().await.unwrap(),
+            BinanceWsEvent::Update(BinanceWsMessage::Response { id: 1, .. })
+        ));
+        match ws.next().await.unwrap() {
+            BinanceWsEvent::Update(BinanceWsMessage::Stream {
+                data: BinanceMarketEvent::AggTrade(trade),
+                ..
+            }) => assert_eq!(trade.agg_trade_id, 5933014),
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        ws.unsubscribe(vec![BinanceStream::AggTrade("BTCUSDT".to_string())]);
+        let unsubscribe = next_text(&mut server).await;
+        assert_eq!(unsubscribe["method"], "UNSUBSCRIBE");
+        assert_eq!(unsubscribe["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_connection_age_limit() {
+        let (listener, url) = local_server().await;
+        // a renewal does not wait for the backoff
+        let config = WsConfig {
+            max_connection_age: Duration::from_millis(200),
+            min_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+            ..test_config(url)
+        };
+        let mut ws = BinanceWs::with_config(
+            vec![BinanceStream::BookTicker("BTCUSDT".to_string())],
+            config,
+        );
+
+        let mut server = accept(&listener).await;
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+        // answer pings while the connection is young
+        tokio::spawn(async move { while let Some(Ok(_)) = server.next().await {} });
+
+        assert_eq!(
+            expect_state(&mut ws).await,
+            ConnectionState::Disconnected("connection age limit reached".to_string())
+        );
+
+        let mut server = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_text(&mut server).await["params"],
+            json!(["btcusdt@bookTicker"])
+        );
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_missing_pong_reconnects() {
+        let (listener, url) = local_server().await;
+        let mut ws = BinanceWs::with_config(vec![], test_config(url));
+
+        // a server that never reads does not answer the pings
+        let _server = accept(&listener).await;
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+        match expect_state(&mut ws).await {
+            ConnectionState::Disconnected(reason) => assert!(reason.contains("no pong")),
+            state => panic!("unexpected state {state:?}"),
+        }
+    }
+}
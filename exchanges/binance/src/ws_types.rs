@@ -0,0 +1,523 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    BinanceErrorResponse, BinanceOrderStatus, BinanceOrderType, BinancePositionSide, BinanceSide,
+    BinanceTimeInForce,
+};
+
+pub use cefi_rs_common::ws::ConnectionState;
+
+/// Market streams, formatted as expected by `SUBSCRIBE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinanceStream {
+    /// Diff depth, `speed_ms` is 100, 250 (default) or 500.
+    Depth {
+        symbol: String,
+        speed_ms: Option<u32>,
+    },
+    AggTrade(String),
+    BookTicker(String),
+    /// Mark price and funding rate, every 3s or every second.
+    MarkPrice {
+        symbol: String,
+        every_second: bool,
+    },
+    /// Interval as in the REST api, e.g. 1m, 5m, 1h or 1d.
+    Kline {
+        symbol: String,
+        interval: String,
+    },
+}
+
+impl std::fmt::Display for BinanceStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinanceStream::Depth { symbol, speed_ms } => {
+                write!(f, "{}@depth", symbol.to_lowercase())?;
+                match speed_ms {
+                    Some(speed_ms) => write!(f, "@{speed_ms}ms"),
+                    None => Ok(()),
+                }
+            }
+            BinanceStream::AggTrade(symbol) => write!(f, "{}@aggTrade", symbol.to_lowercase()),
+            BinanceStream::BookTicker(symbol) => write!(f, "{}@bookTicker", symbol.to_lowercase()),
+            BinanceStream::MarkPrice {
+                symbol,
+                every_second,
+            } => {
+                write!(f, "{}@markPrice", symbol.to_lowercase())?;
+                if *every_second {
+                    write!(f, "@1s")?;
+                }
+                Ok(())
+            }
+            BinanceStream::Kline { symbol, interval } => {
+                write!(f, "{}@kline_{interval}", symbol.to_lowercase())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BinanceWsEvent {
+    Update(BinanceWsMessage),
+    State(ConnectionState),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum BinanceWsMessage {
+    /// Payload of a combined stream.
+    Stream {
+        stream: String,
+        data: BinanceMarketEvent,
+    },
+    Error {
+        error: BinanceErrorResponse,
+        id: Option<u64>,
+    },
+    /// Answer to a `SUBSCRIBE` or `UNSUBSCRIBE` request.
+    Response {
+        result: Option<serde_json::Value>,
+        id: u64,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "e")]
+pub enum BinanceMarketEvent {
+    #[serde(rename = "depthUpdate")]
+    DepthUpdate(DepthUpdate),
+    #[serde(rename = "aggTrade")]
+    AggTrade(AggTrade),
+    #[serde(rename = "bookTicker")]
+    BookTicker(BookTicker),
+    #[serde(rename = "markPriceUpdate")]
+    MarkPriceUpdate(MarkPriceUpdate),
+    #[serde(rename = "kline")]
+    Kline(Box<KlineEvent>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DepthUpdate {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "U")]
+    pub first_update_id: i64,
+    #[serde(rename = "u")]
+    pub final_update_id: i64,
+    /// Final update id of the previous event.
+    #[serde(rename = "pu")]
+    pub prev_final_update_id: i64,
+    #[serde(rename = "b")]
+    pub bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    pub asks: Vec<[String; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AggTrade {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "a")]
+    pub agg_trade_id: i64,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "q")]
+    pub quantity: String,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "l")]
+    pub last_trade_id: i64,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    /// Whether the buyer is the maker, i.e. the taker sold.
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookTicker {
+    #[serde(rename = "u")]
+    pub update_id: i64,
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b")]
+    pub bid_price: String,
+    #[serde(rename = "B")]
+    pub bid_qty: String,
+    #[serde(rename = "a")]
+    pub ask_price: String,
+    #[serde(rename = "A")]
+    pub ask_qty: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkPriceUpdate {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p")]
+    pub mark_price: String,
+    #[serde(rename = "i")]
+    pub index_price: String,
+    #[serde(rename = "P")]
+    pub estimated_settle_price: String,
+    #[serde(rename = "r")]
+    pub funding_rate: String,
+    #[serde(rename = "T")]
+    pub next_funding_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KlineEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "k")]
+    pub kline: Kline,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Kline {
+    #[serde(rename = "t")]
+    pub start_time: i64,
+    #[serde(rename = "T")]
+    pub close_time: i64,
+    #[serde(rename = "i")]
+    pub interval: String,
+    #[serde(rename = "o")]
+    pub open: String,
+    #[serde(rename = "c")]
+    pub close: String,
+    #[serde(rename = "h")]
+    pub high: String,
+    #[serde(rename = "l")]
+    pub low: String,
+    #[serde(rename = "v")]
+    pub volume: String,
+    #[serde(rename = "n")]
+    pub trades: i64,
+    /// Whether the kline is closed.
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+    #[serde(rename = "q")]
+    pub quote_volume: String,
+    #[serde(rename = "V")]
+    pub taker_buy_volume: String,
+    #[serde(rename = "Q")]
+    pub taker_buy_quote_volume: String,
+}
+
+#[derive(Debug)]
+pub enum BinanceUserWsEvent {
+    Update(BinanceUserEvent),
+    State(ConnectionState),
+}
+
+/// Event of the user data stream.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "e")]
+pub enum BinanceUserEvent {
+    #[serde(rename = "ORDER_TRADE_UPDATE")]
+    OrderTradeUpdate(Box<OrderTradeUpdate>),
+    #[serde(rename = "ACCOUNT_UPDATE")]
+    AccountUpdate(AccountUpdate),
+    #[serde(rename = "MARGIN_CALL")]
+    MarginCall(MarginCall),
+    /// The listen key expired, the stream is renewed with a new key.
+    #[serde(rename = "listenKeyExpired")]
+    ListenKeyExpired(ListenKeyExpired),
+    /// Event types without a dedicated variant.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderTradeUpdate {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "o")]
+    pub order: OrderUpdate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: BinanceSide,
+    #[serde(rename = "o")]
+    pub order_type: BinanceOrderType,
+    #[serde(rename = "f")]
+    pub time_in_force: BinanceTimeInForce,
+    #[serde(rename = "q")]
+    pub orig_qty: String,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "ap")]
+    pub avg_price: String,
+    #[serde(rename = "sp")]
+    pub stop_price: String,
+    /// Execution type, e.g. NEW, TRADE, CANCELED, EXPIRED or AMENDMENT.
+    #[serde(rename = "x")]
+    pub execution_type: String,
+    #[serde(rename = "X")]
+    pub status: BinanceOrderStatus,
+    #[serde(rename = "i")]
+    pub order_id: i64,
+    #[serde(rename = "l")]
+    pub last_filled_qty: String,
+    #[serde(rename = "z")]
+    pub cum_filled_qty: String,
+    #[serde(rename = "L")]
+    pub last_filled_price: String,
+    /// Missing when no commission was charged.
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "n")]
+    pub commission: Option<String>,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    #[serde(rename = "t")]
+    pub trade_id: i64,
+    #[serde(rename = "m")]
+    pub is_maker: bool,
+    #[serde(rename = "R")]
+    pub reduce_only: bool,
+    #[serde(rename = "ps")]
+    pub position_side: BinancePositionSide,
+    #[serde(rename = "cp")]
+    pub close_position: bool,
+    #[serde(rename = "rp")]
+    pub realized_profit: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountUpdate {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "a")]
+    pub account: AccountUpdateData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountUpdateData {
+    /// Reason of the update, e.g. ORDER, FUNDING_FEE or DEPOSIT.
+    #[serde(rename = "m")]
+    pub reason: String,
+    #[serde(rename = "B")]
+    pub balances: Vec<BalanceUpdate>,
+    #[serde(rename = "P")]
+    pub positions: Vec<PositionUpdate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalanceUpdate {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "wb")]
+    pub wallet_balance: String,
+    #[serde(rename = "cw")]
+    pub cross_wallet_balance: String,
+    /// Balance change except for PnL and commission.
+    #[serde(rename = "bc")]
+    pub balance_change: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PositionUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "pa")]
+    pub position_amt: String,
+    #[serde(rename = "ep")]
+    pub entry_price: String,
+    #[serde(rename = "bep")]
+    pub break_even_price: String,
+    /// Accumulated realized PnL.
+    #[serde(rename = "cr")]
+    pub realized_pnl: String,
+    #[serde(rename = "up")]
+    pub unrealized_pnl: String,
+    /// `isolated` or `cross`.
+    #[serde(rename = "mt")]
+    pub margin_type: String,
+    #[serde(rename = "iw")]
+    pub isolated_wallet: String,
+    #[serde(rename = "ps")]
+    pub position_side: BinancePositionSide,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarginCall {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "cw")]
+    pub cross_wallet_balance: Option<String>,
+    #[serde(rename = "p")]
+    pub positions: Vec<MarginCallPosition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarginCallPosition {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "ps")]
+    pub position_side: BinancePositionSide,
+    #[serde(rename = "pa")]
+    pub position_amt: String,
+    #[serde(rename = "mt")]
+    pub margin_type: String,
+    #[serde(rename = "iw")]
+    pub isolated_wallet: String,
+    #[serde(rename = "mp")]
+    pub mark_price: String,
+    #[serde(rename = "up")]
+    pub unrealized_pnl: String,
+    #[serde(rename = "mm")]
+    pub maintenance_margin: String,
+}
+
+// the event time is sent as a string or a number depending on the endpoint,
+// so it is not kept
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListenKeyExpired {
+    #[serde(rename = "listenKey")]
+    pub listen_key: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_names() {
+        let depth = BinanceStream::Depth {
+            symbol: "BTCUSDT".to_string(),
+            speed_ms: Some(100),
+        };
+        assert_eq!(depth.to_string(), "btcusdt@depth@100ms");
+        let mark_price = BinanceStream::MarkPrice {
+            symbol: "BTCUSDT".to_string(),
+            every_second: true,
+        };
+        assert_eq!(mark_price.to_string(), "btcusdt@markPrice@1s");
+        let kline = BinanceStream::Kline {
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+        };
+        assert_eq!(kline.to_string(), "btcusdt@kline_1m");
+    }
+
+    #[test]
+    fn test_deserialize_depth_update() {
+        let res = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":123456789,"T":123456788,"s":"BTCUSDT","U":157,"u":160,"pu":149,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}}"#;
+        match serde_json::from_str::<BinanceWsMessage>(res).unwrap() {
+            BinanceWsMessage::Stream {
+                stream,
+                data: BinanceMarketEvent::DepthUpdate(depth),
+            } => {
+                assert_eq!(stream, "btcusdt@depth@100ms");
+                assert_eq!(depth.prev_final_update_id, 149);
+                assert_eq!(depth.bids[0], ["0.0024".to_string(), "10".to_string()]);
+            }
+            res => panic!("unexpected message {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_market_events() {
+        let res = r#"{"e":"aggTrade","E":123456789,"s":"BTCUSDT","a":5933014,"p":"0.001","q":"100","f":100,"l":105,"T":123456785,"m":true}"#;
+        let res = serde_json::from_str::<BinanceMarketEvent>(res).unwrap();
+        assert!(matches!(res, BinanceMarketEvent::AggTrade(ref t) if t.is_buyer_maker));
+
+        let res = r#"{"e":"bookTicker","u":400900217,"E":1568014460893,"T":1568014460891,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+        let res = serde_json::from_str::<BinanceMarketEvent>(res).unwrap();
+        assert!(matches!(res, BinanceMarketEvent::BookTicker(ref t) if t.ask_qty == "40.66000000"));
+
+        let res = r#"{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT","p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}"#;
+        let res = serde_json::from_str::<BinanceMarketEvent>(res).unwrap();
+        assert!(
+            matches!(res, BinanceMarketEvent::MarkPriceUpdate(ref m) if m.funding_rate == "0.00038167")
+        );
+
+        let res = r#"{"e":"kline","E":1638747660000,"s":"BTCUSDT","k":{"t":1638747660000,"T":1638747719999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","n":100,"x":false,"q":"1.0000","V":"500","Q":"0.500","B":"123456"}}"#;
+        let res = serde_json::from_str::<BinanceMarketEvent>(res).unwrap();
+        assert!(matches!(res, BinanceMarketEvent::Kline(ref k) if k.kline.interval == "1m"));
+    }
+
+    #[test]
+    fn test_deserialize_user_events() {
+        let res = r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"TEST","S":"SELL","o":"TRAILING_STOP_MARKET","f":"GTC","q":"0.001","p":"0","ap":"0","sp":"7103.04","x":"NEW","X":"NEW","i":8886774,"l":"0","z":"0","L":"0","N":"USDT","n":"0","T":1568879465650,"t":0,"b":"0","a":"9.91","m":false,"R":false,"wt":"CONTRACT_PRICE","ot":"TRAILING_STOP_MARKET","ps":"LONG","cp":false,"AP":"7476.89","cr":"5.0","pP":false,"si":0,"ss":0,"rp":"0","V":"EXPIRE_TAKER","pm":"OPPONENT","gtd":0}}"#;
+        match serde_json::from_str::<BinanceUserEvent>(res).unwrap() {
+            BinanceUserEvent::OrderTradeUpdate(update) => {
+                assert_eq!(update.order.order_id, 8886774);
+                assert_eq!(update.order.side, BinanceSide::Sell);
+                assert_eq!(update.order.status, BinanceOrderStatus::New);
+                assert_eq!(update.order.position_side, BinancePositionSide::Long);
+            }
+            res => panic!("unexpected event {res:?}"),
+        }
+
+        let res = r#"{"e":"ORDER_TRADE_UPDATE","E":1727434153621,"T":1727434153618,"o":{"s":"BTCUSDT","c":"autoclose-1727434153617298693","S":"SELL","o":"LIMIT","f":"IOC","q":"0.002","p":"62931.30","ap":"0","sp":"0","x":"NEW","X":"NEW_INSURANCE","i":4073291102,"l":"0","z":"0","L":"0","T":1727434153618,"t":0,"b":"0","a":"0","m":false,"R":false,"wt":"CONTRACT_PRICE","ot":"LIMIT","ps":"BOTH","cp":false,"rp":"0","pP":false,"si":0,"ss":0,"V":"NONE","pm":"NONE","gtd":0}}"#;
+        match serde_json::from_str::<BinanceUserEvent>(res).unwrap() {
+            BinanceUserEvent::OrderTradeUpdate(update) => {
+                assert_eq!(update.order.status, BinanceOrderStatus::NewInsurance);
+            }
+            res => panic!("unexpected event {res:?}"),
+        }
+
+        let res = r#"{"e":"ACCOUNT_UPDATE","E":1564745798939,"T":1564745798938,"a":{"m":"ORDER","B":[{"a":"USDT","wb":"122624.12345678","cw":"100.12345678","bc":"50.12345678"}],"P":[{"s":"BTCUSDT","pa":"0","ep":"0.00000","bep":"0","cr":"200","up":"0","mt":"isolated","iw":"0.00000000","ps":"BOTH"}]}}"#;
+        match serde_json::from_str::<BinanceUserEvent>(res).unwrap() {
+            BinanceUserEvent::AccountUpdate(update) => {
+                assert_eq!(update.account.reason, "ORDER");
+                assert_eq!(update.account.balances[0].wallet_balance, "122624.12345678");
+                assert_eq!(update.account.positions[0].margin_type, "isolated");
+            }
+            res => panic!("unexpected event {res:?}"),
+        }
+
+        let res = r#"{"e":"MARGIN_CALL","E":1587727187525,"cw":"3.16812045","p":[{"s":"ETHUSDT","ps":"LONG","pa":"1.327","mt":"CROSSED","iw":"0","mp":"187.17127","up":"-1.166074","mm":"1.614445"}]}"#;
+        let res = serde_json::from_str::<BinanceUserEvent>(res).unwrap();
+        assert!(
+            matches!(res, BinanceUserEvent::MarginCall(ref call) if call.positions[0].maintenance_margin == "1.614445")
+        );
+
+        let res = r#"{"e":"listenKeyExpired","E":"1736996475556","listenKey":"WsCMN0a4KHUPTQuX6IUnqEZfB1inxmv1qR4kbf1LuEjur5VdbzqvyxqG9TSjVVxv"}"#;
+        let res = serde_json::from_str::<BinanceUserEvent>(res).unwrap();
+        assert!(matches!(res, BinanceUserEvent::ListenKeyExpired(_)));
+
+        let res = r#"{"e":"ACCOUNT_CONFIG_UPDATE","E":1611646737479,"T":1611646737476,"ac":{"s":"BTCUSDT","l":25}}"#;
+        let res = serde_json::from_str::<BinanceUserEvent>(res).unwrap();
+        assert!(matches!(res, BinanceUserEvent::Other));
+    }
+
+    #[test]
+    fn test_deserialize_responses() {
+        let res = serde_json::from_str::<BinanceWsMessage>(r#"{"result":null,"id":1}"#).unwrap();
+        assert!(matches!(res, BinanceWsMessage::Response { id: 1, .. }));
+
+        let res = r#"{"error":{"code":2,"msg":"Invalid request: unknown variant"},"id":3}"#;
+        let res = serde_json::from_str::<BinanceWsMessage>(res).unwrap();
+        assert!(matches!(res, BinanceWsMessage::Error { ref error, .. } if error.code == 2));
+    }
+}
@@ -0,0 +1,256 @@
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+use crate::{
+    errors::BinanceResult,
+    http::BinanceHttp,
+    ws::{supervise, WsCommand, WsConfig, WsFrame},
+    ws_types::{BinanceUserEvent, BinanceUserWsEvent},
+};
+
+fn stream_url(config: &WsConfig, listen_key: &str) -> String {
+    format!("{}/{listen_key}", config.url)
+}
+
+/// Extends the listen key every `listen_key_keepalive` and moves the stream to
+/// a new key when the current one expired or could not be extended. Closes
+/// the key once the client is dropped.
+async fn keep_listen_key(
+    client: BinanceHttp,
+    config: WsConfig,
+    mut listen_key: String,
+    commands: mpsc::UnboundedSender<WsCommand>,
+    mut expired: mpsc::UnboundedReceiver<()>,
+) {
+    let mut keepalive = tokio::time::interval(config.listen_key_keepalive);
+    keepalive.tick().await;
+    loop {
+        let renew = tokio::select! {
+            _ = keepalive.tick() => match client.keepalive_listen_key().await {
+                Ok(_) => false,
+                Err(err) => {
+                    warn!("error extending listen key: {err}");
+                    true
+                }
+            },
+            msg = expired.recv() => match msg {
+                Some(()) => true,
+                None => break,
+            },
+        };
+        if !renew {
+            continue;
+        }
+
+        // returns the current key while it is still valid
+        match client.create_listen_key().await {
+            Ok(new_listen_key) if new_listen_key == listen_key => {}
+            Ok(new_listen_key) => {
+                listen_key = new_listen_key;
+                let url = stream_url(&config, &listen_key);
+                if commands.send(WsCommand::Reconnect(url)).is_err() {
+                    break;
+                }
+            }
+            // retried on the next keepalive
+            Err(err) => error!("error renewing listen key: {err}"),
+        }
+    }
+
+    if let Err(err) = client.close_listen_key().await {
+        warn!("error closing listen key: {err}");
+    }
+}
+
+/// Private order, fill, position and balance updates. The listen key is kept
+/// alive and renewed in the background, reconnections are reported as
+/// `ConnectionState` events.
+pub struct BinanceUserWs {
+    frames: mpsc::UnboundedReceiver<WsFrame>,
+    expired: mpsc::UnboundedSender<()>,
+}
+
+impl BinanceUserWs {
+    pub async fn new(client: BinanceHttp) -> BinanceResult<Self> {
+        Self::with_config(client, WsConfig::user_data()).await
+    }
+
+    pub async fn with_config(client: BinanceHttp, config: WsConfig) -> BinanceResult<Self> {
+        let listen_key = client.create_listen_key().await?;
+        let stream_config = WsConfig {
+            url: stream_url(&config, &listen_key),
+            ..config.clone()
+        };
+        let (frames, commands) = supervise(&stream_config, vec![]);
+        let (expired_tx, expired_rx) = mpsc::unbounded_channel();
+        tokio::spawn(keep_listen_key(
+            client, config, listen_key, commands, expired_rx,
+        ));
+        Ok(Self {
+            frames,
+            expired: expired_tx,
+        })
+    }
+
+    /// Returns the next event or connection state change, `None` once the
+    /// background task has stopped.
+    pub async fn next(&mut self) -> Option<BinanceUserWsEvent> {
+        loop {
+            match self.frames.recv().await? {
+                WsFrame::State(state) => return Some(BinanceUserWsEvent::State(state)),
+                WsFrame::Text(text) => match serde_json::from_str::<BinanceUserEvent>(&text) {
+                    Ok(event) => {
+                        if let BinanceUserEvent::ListenKeyExpired(_) = event {
+                            let _ = self.expired.send(());
+                        }
+                        return Some(BinanceUserWsEvent::Update(event));
+                    }
+                    Err(err) => error!("error deserializing {text}: {err}"),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::SinkExt;
+    use serde_json::json;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::{
+            handshake::server::{Request, Response},
+            Message,
+        },
+        WebSocketStream,
+    };
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::ws_types::ConnectionState;
+
+    const ORDER_TRADE_UPDATE: &str = r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"TEST","S":"BUY","o":"LIMIT","f":"GTC","q":"0.001","p":"65000","ap":"65000","sp":"0","x":"TRADE","X":"FILLED","i":8886774,"l":"0.001","z":"0.001","L":"65000","N":"USDT","n":"0.013","T":1568879465650,"t":12,"b":"0","a":"0","m":true,"R":false,"wt":"CONTRACT_PRICE","ot":"LIMIT","ps":"BOTH","cp":false,"rp":"0"}}"#;
+    const LISTEN_KEY_EXPIRED: &str =
+        r#"{"e":"listenKeyExpired","E":"1736996475556","listenKey":"key-1"}"#;
+
+    fn listen_key_response(listen_key: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({ "listenKey": listen_key }))
+    }
+
+    /// Accepts a connection and returns it with the requested path.
+    async fn accept(listener: &TcpListener) -> (WebSocketStream<TcpStream>, String) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut path = String::new();
+        let ws = accept_hdr_async(stream, |request: &Request, response: Response| {
+            path = request.uri().path().to_string();
+            Ok(response)
+        })
+        .await
+        .unwrap();
+        (ws, path)
+    }
+
+    async fn expect_update(ws: &mut BinanceUserWs) -> BinanceUserEvent {
+        loop {
+            match ws.next().await.unwrap() {
+                BinanceUserWsEvent::Update(event) => return event,
+                BinanceUserWsEvent::State(_) => {}
+            }
+        }
+    }
+
+    async fn requests(server: &MockServer, http_method: &str) -> usize {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.method.as_str() == http_method)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_user_stream_renews_expired_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(listen_key_response("key-1"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(listen_key_response("key-2"))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(listen_key_response("key-1"))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/fapi/v1/listenKey"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = WsConfig {
+            url: format!("ws://{}/ws", listener.local_addr().unwrap()),
+            min_backoff: Duration::from_millis(10),
+            listen_key_keepalive: Duration::from_millis(100),
+            ..WsConfig::user_data()
+        };
+        let client = BinanceHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri());
+        let mut ws = BinanceUserWs::with_config(client, config).await.unwrap();
+
+        let (mut stream, path) = accept(&listener).await;
+        assert_eq!(path, "/ws/key-1");
+        stream
+            .send(Message::Text(ORDER_TRADE_UPDATE.to_string()))
+            .await
+            .unwrap();
+        match expect_update(&mut ws).await {
+            BinanceUserEvent::OrderTradeUpdate(update) => {
+                assert_eq!(update.order.cum_filled_qty, "0.001")
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(requests(&server, "PUT").await >= 1);
+
+        stream
+            .send(Message::Text(LISTEN_KEY_EXPIRED.to_string()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            expect_update(&mut ws).await,
+            BinanceUserEvent::ListenKeyExpired(_)
+        ));
+        let (_stream, path) = accept(&listener).await;
+        assert_eq!(path, "/ws/key-2");
+        loop {
+            if let Some(BinanceUserWsEvent::State(ConnectionState::Connected)) = ws.next().await {
+                break;
+            }
+        }
+
+        drop(ws);
+        for _ in 0..50 {
+            if requests(&server, "DELETE").await == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("listen key not closed");
+    }
+}
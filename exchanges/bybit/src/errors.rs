@@ -1,11 +1,22 @@
 use std::fmt;
 
+use crate::transport::TransportError;
+
 pub type BybitResult<T> = Result<T, BybitError>;
 
 #[derive(Debug)]
 pub enum BybitError {
     ApiError(i64, String),
+    HttpError(reqwest::Error),
+    /// 5xx status, with the body of the response.
+    ServerError(u16, String),
     DeserializeError(String),
+    /// The WebSocket connection is down or was lost before the response.
+    WsError(String),
+    /// No response within the request timeout.
+    Timeout(String),
+    /// Refused locally by the rate limiter.
+    RateLimited(String),
     Unknown(String),
 }
 
@@ -15,6 +26,21 @@ impl From<anyhow::Error> for BybitError {
     }
 }
 
+impl From<reqwest::Error> for BybitError {
+    fn from(value: reqwest::Error) -> Self {
+        BybitError::HttpError(value)
+    }
+}
+
+impl From<TransportError> for BybitError {
+    fn from(value: TransportError) -> Self {
+        match value {
+            TransportError::Http(err) => BybitError::HttpError(err),
+            err => BybitError::Unknown(err.to_string()),
+        }
+    }
+}
+
 impl From<serde_json::Error> for BybitError {
     fn from(value: serde_json::Error) -> Self {
         BybitError::DeserializeError(format!("{}", value))
@@ -23,6 +49,70 @@ impl From<serde_json::Error> for BybitError {
 
 impl fmt::Display for BybitError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            BybitError::ApiError(code, msg) => write!(f, "bybit api error {code}: {msg}"),
+            BybitError::HttpError(err) => write!(f, "bybit http error: {err}"),
+            BybitError::ServerError(status, body) => {
+                write!(f, "bybit server error {status}: {body}")
+            }
+            BybitError::DeserializeError(msg) => write!(f, "bybit deserialize error: {msg}"),
+            BybitError::WsError(msg) => write!(f, "bybit ws error: {msg}"),
+            BybitError::Timeout(msg) => write!(f, "bybit timeout: {msg}"),
+            BybitError::RateLimited(msg) => write!(f, "bybit rate limited: {msg}"),
+            BybitError::Unknown(msg) => write!(f, "bybit error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for BybitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BybitError::HttpError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            BybitError::ApiError(10001, "Qty invalid".to_string()).to_string(),
+            "bybit api error 10001: Qty invalid"
+        );
+        assert_eq!(
+            BybitError::DeserializeError("expected value at line 1 column 1".to_string())
+                .to_string(),
+            "bybit deserialize error: expected value at line 1 column 1"
+        );
+        assert_eq!(
+            BybitError::Unknown("invalid side Flat".to_string()).to_string(),
+            "bybit error: invalid side Flat"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_error_source() {
+        // nothing listens on the discard port
+        let err: BybitError = reqwest::get("http://127.0.0.1:9").await.unwrap_err().into();
+        assert!(err.to_string().starts_with("bybit http error: "));
+        assert!(err.source().is_some());
+        assert!(BybitError::Unknown("".to_string()).source().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_from_transport_error() {
+        let err = TransportError::from(reqwest::get("http://127.0.0.1:9").await.unwrap_err());
+        assert!(matches!(BybitError::from(err), BybitError::HttpError(_)));
+        let err = TransportError::NoMockResponse("GET fapi/v1/time".to_string());
+        assert_eq!(
+            BybitError::from(err).to_string(),
+            "bybit error: no mock response for GET fapi/v1/time"
+        );
     }
 }
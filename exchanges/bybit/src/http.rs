@@ -1,33 +1,101 @@
 use crate::{
+    clock::ServerClock,
+    config::ClientConfig,
     errors::{BybitError, BybitResult},
+    rate_limit::RateLimiter,
+    retry::{is_transient, RetryPolicy},
+    transport::{HttpRequest, ReqwestTransport, Transport},
     types::*,
 };
-use chrono::Utc;
 use hmac::{Hmac, Mac};
-use reqwest::{Client, RequestBuilder};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Method, StatusCode,
+};
 use serde_json::{Map, Value};
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
+use tokio::time::sleep;
+use tracing::{debug, warn};
 
-static BYBIT_HOST: &'static str = "https://api.bybit.com";
-static RECV_WINDOW: &'static str = "5000";
+/// Cancelling twice has the effect of cancelling once.
+const IDEMPOTENT_POST_ENDPOINTS: [&str; 2] = ["v5/order/cancel", "v5/order/cancel-all"];
 
 pub struct BybitHttp {
     api_key: String,
     api_secret: String,
-    client: Client,
+    host: String,
+    recv_window: String,
+    transport: Arc<dyn Transport>,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    clock: Arc<ServerClock>,
 }
 
 impl BybitHttp {
-    pub fn new(api_key: String, api_secret: String) -> Self {
-        Self {
+    /// Client of mainnet with the default settings, fails if the HTTP client
+    /// cannot be built.
+    pub fn new(api_key: String, api_secret: String) -> BybitResult<Self> {
+        Self::with_config(api_key, api_secret, &ClientConfig::default())
+    }
+
+    /// Fails if the proxy of `config` is not a valid url.
+    pub fn with_config(
+        api_key: String,
+        api_secret: String,
+        config: &ClientConfig,
+    ) -> BybitResult<Self> {
+        Ok(Self {
             api_key,
             api_secret,
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(10))
-                .build()
-                .expect("reqwest client"),
-        }
+            host: config.rest_url().to_string(),
+            recv_window: config.recv_window().as_millis().to_string(),
+            transport: Arc::new(ReqwestTransport::new(config.http_client()?)),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            retry_policy: RetryPolicy::default(),
+            clock: config.clock().clone(),
+        })
+    }
+
+    /// Overrides the REST base url, e.g. to point the client at a mock server.
+    pub fn with_host(mut self, host: String) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Replaces the transport requests are sent with, e.g. by a
+    /// `MockTransport` serving recorded responses.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Replaces the default limiter, which waits for the limits. Clients of
+    /// the same account and IP should share one.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Replaces the clock signed requests are timestamped with, e.g. to share
+    /// it with `WsConfig::clock`.
+    pub fn with_clock(mut self, clock: Arc<ServerClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Clock corrected by `sync_clock`, its offset can be monitored.
+    pub fn clock(&self) -> &Arc<ServerClock> {
+        &self.clock
     }
 
     fn generate_get_signature(
@@ -39,7 +107,7 @@ impl BybitHttp {
             .expect("HMAC can take key of any size");
         mac.update(timestamp.as_bytes());
         mac.update(self.api_key.as_bytes());
-        mac.update(RECV_WINDOW.as_bytes());
+        mac.update(self.recv_window.as_bytes());
         mac.update(Self::generate_query_str(params).as_bytes());
 
         let result = mac.finalize();
@@ -56,7 +124,7 @@ impl BybitHttp {
             .expect("HMAC can take key of any size");
         mac.update(timestamp.as_bytes());
         mac.update(self.api_key.as_bytes());
-        mac.update(RECV_WINDOW.as_bytes());
+        mac.update(self.recv_window.as_bytes());
         mac.update(serde_json::to_string(&params)?.as_bytes());
 
         let result = mac.finalize();
@@ -72,50 +140,79 @@ impl BybitHttp {
             .join("&")
     }
 
-    fn add_headers(
-        &self,
-        builder: RequestBuilder,
-        timestamp: &String,
-        signature: &String,
-    ) -> RequestBuilder {
-        builder
-            .header("X-BAPI-API-KEY", self.api_key.clone())
-            .header("X-BAPI-SIGN", signature)
-            .header("X-BAPI-SIGN-TYPE", "2")
-            .header("X-BAPI-TIMESTAMP", timestamp.clone())
-            .header("X-BAPI-RECV-WINDOW", RECV_WINDOW)
-            .header("Content-Type", "application/json")
+    fn auth_headers(&self, timestamp: &str, signature: &str) -> BybitResult<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("X-BAPI-API-KEY", self.api_key.as_str()),
+            ("X-BAPI-SIGN", signature),
+            ("X-BAPI-SIGN-TYPE", "2"),
+            ("X-BAPI-TIMESTAMP", timestamp),
+            ("X-BAPI-RECV-WINDOW", self.recv_window.as_str()),
+            ("Content-Type", "application/json"),
+        ] {
+            let value = HeaderValue::from_str(value)
+                .map_err(|err| BybitError::Unknown(format!("invalid {name} header: {err}")))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
     }
 
-    pub(crate) async fn send_get_request<T>(
+    /// Sends a request built by `build`, once per attempt so that every
+    /// attempt is signed with a fresh timestamp. Transient failures are
+    /// retried following the retry policy if the request is `idempotent`.
+    async fn send_request<T>(
         &self,
         endpoint: &str,
-        params: HashMap<&str, &str>,
-        is_auth: bool,
+        idempotent: bool,
+        build: impl Fn() -> BybitResult<HttpRequest>,
     ) -> BybitResult<T>
     where
         T: for<'a> serde::Deserialize<'a>,
     {
-        let query_str = Self::generate_query_str(&params);
-
-        let mut builder = self
-            .client
-            .get(&format!("{}/{}?{}", BYBIT_HOST, endpoint, query_str));
-
-        if is_auth {
-            let timestamp = Utc::now().timestamp_millis().to_string();
-            let signature = self.generate_get_signature(&timestamp, &params)?;
-            builder = self.add_headers(builder, &timestamp, &signature)
+        let mut attempt = 1;
+        loop {
+            debug!("bybit {endpoint} attempt {attempt}");
+            self.rate_limiter.acquire(endpoint).await?;
+            let res = self.send_attempt(endpoint, build()?).await;
+            match res {
+                Err(err)
+                    if idempotent
+                        && attempt <= self.retry_policy.max_retries
+                        && is_transient(&err) =>
+                {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!(
+                        "bybit {endpoint} attempt {attempt} failed: {err}, retrying in {delay:?}"
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
         }
+    }
 
-        let response = builder
-            .send()
-            .await
-            .map_err(|err| BybitError::Unknown(format!("{}", err)))?;
-        let res = &response
-            .text()
-            .await
-            .map_err(|err| BybitError::Unknown(format!("{}", err)))?;
+    async fn send_attempt<T>(&self, endpoint: &str, request: HttpRequest) -> BybitResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        let response = self.transport.send(request).await?;
+        self.rate_limiter
+            .on_response(endpoint, response.status, &response.headers);
+        if response.status.is_server_error() {
+            return Err(BybitError::ServerError(
+                response.status.as_u16(),
+                response.body,
+            ));
+        }
+        // the IP ban is answered with a plain text or html page
+        if response.status == StatusCode::FORBIDDEN {
+            return Err(BybitError::RateLimited(format!(
+                "{endpoint} forbidden, the IP is banned: {}",
+                response.body
+            )));
+        }
+        let res = &response.body;
         let res = serde_json::from_str::<BybitHttpResponse>(res)
             .map_err(|err| BybitError::DeserializeError(format!("{res}: {err}")))?;
         match res.ret_code {
@@ -129,6 +226,35 @@ impl BybitHttp {
         }
     }
 
+    pub(crate) async fn send_get_request<T>(
+        &self,
+        endpoint: &str,
+        params: HashMap<&str, &str>,
+        is_auth: bool,
+    ) -> BybitResult<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        let query_str = Self::generate_query_str(&params);
+
+        self.send_request(endpoint, true, || {
+            let headers = if is_auth {
+                let timestamp = self.clock.now_ms().to_string();
+                let signature = self.generate_get_signature(&timestamp, &params)?;
+                self.auth_headers(&timestamp, &signature)?
+            } else {
+                HeaderMap::new()
+            };
+            Ok(HttpRequest {
+                method: Method::GET,
+                url: format!("{}/{}?{}", self.host, endpoint, query_str),
+                headers,
+                body: None,
+            })
+        })
+        .await
+    }
+
     pub(crate) async fn send_post_request<T>(
         &self,
         endpoint: &str,
@@ -137,32 +263,174 @@ impl BybitHttp {
     where
         T: for<'a> serde::Deserialize<'a>,
     {
-        let timestamp = Utc::now().timestamp_millis().to_string();
-        let signature = self.generate_post_signature(&timestamp, &params)?;
-
-        let builder = self
-            .client
-            .post(format!("{BYBIT_HOST}/{}", endpoint))
-            .json(&params);
-        let builder = self.add_headers(builder, &timestamp, &signature);
-        let response = builder
-            .send()
+        // The venue rejects a second order with the same `orderLinkId` instead
+        // of placing it twice, so requests carrying one are safe to repeat.
+        let has_link_id = params
+            .get("orderLinkId")
+            .and_then(Value::as_str)
+            .is_some_and(|link_id| !link_id.is_empty());
+        let idempotent = has_link_id || IDEMPOTENT_POST_ENDPOINTS.contains(&endpoint);
+
+        self.send_request(endpoint, idempotent, || {
+            let timestamp = self.clock.now_ms().to_string();
+            let signature = self.generate_post_signature(&timestamp, &params)?;
+
+            Ok(HttpRequest {
+                method: Method::POST,
+                url: format!("{}/{}", self.host, endpoint),
+                headers: self.auth_headers(&timestamp, &signature)?,
+                body: Some(serde_json::to_string(&params)?),
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        config::Environment,
+        rate_limit::{RateLimitMode, RateLimiter},
+    };
+
+    fn test_client(server: &MockServer) -> BybitHttp {
+        BybitHttp::new("key".to_string(), "secret".to_string())
+            .unwrap()
+            .with_host(server.uri())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                min_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(20),
+            })
+    }
+
+    fn order_response() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "retCode": 0, "retMsg": "OK",
+            "result": {"orderId": "1321003749386327552", "orderLinkId": "cloid-1"},
+            "retExtInfo": {}, "time": 1672211918471u64
+        }))
+    }
+
+    fn limit_order() -> PlaceOrderRequest {
+        PlaceOrderRequest::new("BTCUSDT", BybitSide::Buy, BybitOrderType::Limit, "0.001")
+            .price("60000")
+            .time_in_force(BybitTimeInForce::Gtc)
+    }
+
+    #[tokio::test]
+    async fn test_retry_get() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/time"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "retCode": 10016, "retMsg": "Internal server error", "result": {}, "retExtInfo": {}, "time": 1688111859337u64
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/time"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "retCode": 0, "retMsg": "OK",
+                "result": {"timeSecond": "1688639403", "timeNano": "1688639403423213947"},
+                "retExtInfo": {}, "time": 1688639403423u64
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let time = test_client(&server).get_server_time().await.unwrap();
+        assert_eq!(time.time_second, "1688639403");
+    }
+
+    #[tokio::test]
+    async fn test_client_config() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .and(header("user-agent", "cefi-rs-test"))
+            .and(header("X-BAPI-RECV-WINDOW", "10000"))
+            .respond_with(order_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = ClientConfig::new(Environment::Testnet)
+            .with_rest_url(server.uri())
+            .with_recv_window(Duration::from_secs(10))
+            .with_user_agent("cefi-rs-test".to_string());
+        let client =
+            BybitHttp::with_config("key".to_string(), "secret".to_string(), &config).unwrap();
+        let res = client
+            .create_order(&limit_order().order_link_id("cloid-1"))
             .await
-            .map_err(|err| BybitError::Unknown(format!("{}", err)))?;
-        let res = &response
-            .text()
+            .unwrap();
+        assert_eq!(res.order_link_id, "cloid-1");
+    }
+
+    #[tokio::test]
+    async fn test_retry_order_only_with_link_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v5/order/create"))
+            .respond_with(order_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = test_client(&server);
+
+        // the order may have been placed, a second attempt could duplicate it
+        match client.create_order(&limit_order()).await {
+            Err(BybitError::ServerError(status, _)) => assert_eq!(status, 502),
+            res => panic!("unexpected result {res:?}"),
+        }
+        let res = client
+            .create_order(&limit_order().order_link_id("cloid-1"))
             .await
-            .map_err(|err| BybitError::Unknown(format!("{}", err)))?;
-        let res = serde_json::from_str::<BybitHttpResponse>(res)
-            .map_err(|err| BybitError::DeserializeError(format!("{res}: {err}")))?;
-        match res.ret_code {
-            0 => {
-                let value_str = &res.result.to_string();
-                let res = serde_json::from_str::<T>(value_str)
-                    .map_err(|err| BybitError::DeserializeError(format!("{value_str}: {err}")))?;
-                Ok(res)
-            }
-            _ => Err(BybitError::ApiError(res.ret_code, res.ret_msg)),
+            .unwrap();
+        assert_eq!(res.order_link_id, "cloid-1");
+    }
+
+    #[tokio::test]
+    async fn test_ip_ban() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/time"))
+            .respond_with(
+                ResponseTemplate::new(403).set_body_string("<html>access too frequent</html>"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = test_client(&server)
+            .with_rate_limiter(Arc::new(RateLimiter::new(RateLimitMode::FailFast)));
+
+        match client.get_server_time().await {
+            Err(BybitError::RateLimited(msg)) => assert!(msg.contains("access too frequent")),
+            res => panic!("unexpected result {res:?}"),
         }
+        // refused locally while the ban lasts
+        assert!(matches!(
+            client.get_server_time().await,
+            Err(BybitError::RateLimited(_))
+        ));
     }
 }
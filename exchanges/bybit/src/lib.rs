@@ -1,6 +1,14 @@
+pub mod config;
 pub mod errors;
 pub mod http;
+pub mod rate_limit;
 pub mod rest;
+pub mod retry;
+pub mod transport;
 pub mod types;
 pub mod ws;
+pub mod ws_public;
+pub mod ws_trade;
 pub mod ws_types;
+
+pub use cefi_rs_common::clock;
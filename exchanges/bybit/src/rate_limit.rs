@@ -0,0 +1,309 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::Utc;
+use reqwest::{header::HeaderMap, StatusCode};
+use tokio::time::{sleep, Instant};
+
+use crate::errors::{BybitError, BybitResult};
+
+/// Bucket shared by all requests, 600 requests per 5 seconds per IP.
+const IP_BUCKET: &str = "ip";
+const IP_LIMIT: u32 = 600;
+const IP_INTERVAL: Duration = Duration::from_secs(5);
+/// Exceeding the IP limit bans the IP for at least 10 minutes, answered with
+/// 403. The ban covers every endpoint.
+const IP_BAN: Duration = Duration::from_secs(600);
+
+/// Per second limits per UID of the private endpoints, for linear contracts.
+/// Responses carry the actual limit of the account in `X-Bapi-Limit`.
+const ENDPOINT_LIMITS: [(&str, u32); 8] = [
+    ("v5/order/create", 10),
+    ("v5/order/amend", 10),
+    ("v5/order/cancel", 10),
+    ("v5/order/cancel-all", 10),
+    ("v5/order/realtime", 50),
+    ("v5/position/list", 50),
+    ("v5/account/wallet-balance", 50),
+    ("v5/account/info", 50),
+];
+
+/// What a request does when its limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    /// Waits until the request fits in the limit.
+    #[default]
+    Wait,
+    /// Returns `BybitError::RateLimited` right away.
+    FailFast,
+}
+
+/// Token bucket refilled continuously, `capacity` tokens per `interval`.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    interval: Duration,
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, interval: Duration, now: Instant) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity: capacity as f64,
+            interval,
+            tokens: capacity as f64,
+            updated: now,
+            blocked_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refilled = elapsed * self.capacity / self.interval.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Time until `cost` tokens are available, zero if they are.
+    fn wait_time(&mut self, cost: f64, now: Instant) -> Duration {
+        self.refill(now);
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return until - now;
+            }
+            self.blocked_until = None;
+        }
+        let missing = cost.min(self.capacity) - self.tokens;
+        if missing <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(missing * self.interval.as_secs_f64() / self.capacity)
+    }
+
+    fn take(&mut self, cost: f64) {
+        self.tokens -= cost.min(self.capacity);
+    }
+
+    fn set_limit(&mut self, capacity: u32, interval: Duration, now: Instant) {
+        self.refill(now);
+        self.capacity = capacity.max(1) as f64;
+        self.interval = interval;
+        self.tokens = self.tokens.min(self.capacity);
+    }
+
+    fn set_remaining(&mut self, remaining: f64, now: Instant) {
+        self.refill(now);
+        self.tokens = remaining.min(self.capacity);
+    }
+}
+
+fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Client side throttling of REST requests. Every request takes a token from
+/// the IP bucket and from the bucket of its endpoint, if it has one. The
+/// buckets are corrected from the `X-Bapi-Limit*` headers of the responses.
+///
+/// A 403 means the IP is banned: all requests through the limiter are then
+/// held back for 10 minutes, waiting or failing depending on the mode, as
+/// any request sent meanwhile would only extend the ban.
+///
+/// Share one limiter between the clients of an account with
+/// `BybitHttp::with_rate_limiter`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    mode: RateLimitMode,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitMode::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(mode: RateLimitMode) -> Self {
+        let now = Instant::now();
+        let mut buckets = HashMap::from([(
+            IP_BUCKET.to_string(),
+            TokenBucket::new(IP_LIMIT, IP_INTERVAL, now),
+        )]);
+        for (endpoint, limit) in ENDPOINT_LIMITS {
+            let bucket = TokenBucket::new(limit, Duration::from_secs(1), now);
+            buckets.insert(endpoint.to_string(), bucket);
+        }
+        Self {
+            mode,
+            buckets: Mutex::new(buckets),
+        }
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Sets the number of requests per second allowed on an endpoint.
+    pub fn set_endpoint_limit(&self, endpoint: &str, limit: u32) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(endpoint) {
+            Some(bucket) => bucket.set_limit(limit, Duration::from_secs(1), now),
+            None => {
+                let bucket = TokenBucket::new(limit, Duration::from_secs(1), now);
+                buckets.insert(endpoint.to_string(), bucket);
+            }
+        }
+    }
+
+    /// Takes a token for a request to `endpoint`, waiting for it or failing
+    /// depending on the mode.
+    pub(crate) async fn acquire(&self, endpoint: &str) -> BybitResult<()> {
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().unwrap();
+                let mut wait = Duration::ZERO;
+                for key in [IP_BUCKET, endpoint] {
+                    if let Some(bucket) = buckets.get_mut(key) {
+                        wait = wait.max(bucket.wait_time(1.0, now));
+                    }
+                }
+                if wait.is_zero() {
+                    for key in [IP_BUCKET, endpoint] {
+                        if let Some(bucket) = buckets.get_mut(key) {
+                            bucket.take(1.0);
+                        }
+                    }
+                    return Ok(());
+                }
+                wait
+            };
+            match self.mode {
+                RateLimitMode::Wait => sleep(wait).await,
+                RateLimitMode::FailFast => {
+                    return Err(BybitError::RateLimited(format!(
+                        "{endpoint} limit reached, retry in {wait:?}"
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Aligns the endpoint bucket with the limit status reported by the venue.
+    pub(crate) fn on_response(&self, endpoint: &str, status: StatusCode, headers: &HeaderMap) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if status == StatusCode::FORBIDDEN {
+            if let Some(bucket) = buckets.get_mut(IP_BUCKET) {
+                bucket.blocked_until = Some(now + IP_BAN);
+            }
+        }
+
+        let Some(remaining) = header::<f64>(headers, "X-Bapi-Limit-Status") else {
+            return;
+        };
+        let limit = header::<u32>(headers, "X-Bapi-Limit");
+        let bucket = match (buckets.entry(endpoint.to_string()), limit) {
+            (Entry::Occupied(entry), _) => entry.into_mut(),
+            (Entry::Vacant(entry), Some(limit)) => {
+                entry.insert(TokenBucket::new(limit, Duration::from_secs(1), now))
+            }
+            (Entry::Vacant(_), None) => return,
+        };
+        if let Some(limit) = limit {
+            bucket.set_limit(limit, Duration::from_secs(1), now);
+        }
+        bucket.set_remaining(remaining, now);
+        if remaining <= 0.0 {
+            if let Some(reset_ms) = header::<i64>(headers, "X-Bapi-Limit-Reset-Timestamp") {
+                let delay_ms = (reset_ms - Utc::now().timestamp_millis()).max(0);
+                bucket.blocked_until = Some(now + Duration::from_millis(delay_ms as u64));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(1), now);
+        for _ in 0..10 {
+            assert_eq!(bucket.wait_time(1.0, now), Duration::ZERO);
+            bucket.take(1.0);
+        }
+        assert_eq!(bucket.wait_time(1.0, now), Duration::from_millis(100));
+        assert_eq!(
+            bucket.wait_time(1.0, now + Duration::from_millis(100)),
+            Duration::ZERO
+        );
+
+        bucket.set_remaining(0.0, now + Duration::from_millis(100));
+        bucket.blocked_until = Some(now + Duration::from_secs(2));
+        assert_eq!(
+            bucket.wait_time(1.0, now + Duration::from_secs(1)),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        limiter.set_endpoint_limit("v5/order/create", 2);
+        for _ in 0..2 {
+            limiter.acquire("v5/order/create").await.unwrap();
+        }
+        assert!(matches!(
+            limiter.acquire("v5/order/create").await,
+            Err(BybitError::RateLimited(_))
+        ));
+        // other endpoints only share the IP bucket
+        limiter.acquire("v5/order/realtime").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait() {
+        let limiter = RateLimiter::new(RateLimitMode::Wait);
+        limiter.set_endpoint_limit("v5/order/create", 10);
+        let start = Instant::now();
+        // the last two requests wait 100ms each for a token
+        for _ in 0..12 {
+            limiter.acquire("v5/order/create").await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(190));
+    }
+
+    #[tokio::test]
+    async fn test_limit_headers() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        let reset_ms = Utc::now().timestamp_millis() + 1000;
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Bapi-Limit", HeaderValue::from_static("20"));
+        headers.insert("X-Bapi-Limit-Status", HeaderValue::from_static("0"));
+        headers.insert(
+            "X-Bapi-Limit-Reset-Timestamp",
+            HeaderValue::from_str(&reset_ms.to_string()).unwrap(),
+        );
+        limiter.on_response("v5/order/create", StatusCode::OK, &headers);
+
+        match limiter.acquire("v5/order/create").await {
+            Err(BybitError::RateLimited(msg)) => assert!(msg.starts_with("v5/order/create")),
+            res => panic!("unexpected result {res:?}"),
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets["v5/order/create"].capacity, 20.0);
+    }
+}
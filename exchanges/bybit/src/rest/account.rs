@@ -23,26 +23,35 @@ impl BybitHttp {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use dotenv::dotenv;
+    use reqwest::Method;
+
+    use crate::transport::mock_client;
 
     #[tokio::test]
     async fn test_get_wallet_balance() {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key, api_secret);
+        let (client, transport) = mock_client(
+            Method::GET,
+            "v5/account/wallet-balance",
+            include_str!("../../fixtures/wallet_balance.json"),
+        );
         let balance = client.get_wallet_balance().await.unwrap();
-        println!("{:?}", balance);
+        assert_eq!(balance.list[0].total_equity, "3.31216591");
+        assert_eq!(balance.list[0].coin[0].coin, "BTC");
+        assert_eq!(
+            transport.requests()[0].query_param("accountType").unwrap(),
+            "UNIFIED"
+        );
     }
 
     #[tokio::test]
     async fn test_get_account_info() {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key, api_secret);
+        let (client, _) = mock_client(
+            Method::GET,
+            "v5/account/info",
+            include_str!("../../fixtures/account_info.json"),
+        );
         let account_info = client.get_account_info().await.unwrap();
-        println!("{:?}", account_info);
+        assert_eq!(account_info.unified_margin_status, 4);
+        assert_eq!(account_info.margin_mode, "REGULAR_MARGIN");
     }
 }
@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use crate::{errors::BybitResult, http::BybitHttp, types::GetTickersResponse};
+use crate::{
+    errors::{BybitError, BybitResult},
+    http::BybitHttp,
+    types::GetTickersResponse,
+};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -108,6 +113,20 @@ impl BybitHttp {
             .await
     }
 
+    /// Samples the server time into the clock of the client and returns the
+    /// resulting offset in milliseconds. Call it periodically to follow the
+    /// drift of the host clock.
+    pub async fn sync_clock(&self) -> BybitResult<i64> {
+        let sent_ms = Utc::now().timestamp_millis();
+        let time = self.get_server_time().await?;
+        let received_ms = Utc::now().timestamp_millis();
+        let server_ms = time.time_nano.parse::<i64>().map_err(|err| {
+            BybitError::DeserializeError(format!("time nano {}: {err}", time.time_nano))
+        })? / 1_000_000;
+        self.clock().add_sample(sent_ms, server_ms, received_ms);
+        Ok(self.clock().offset_ms())
+    }
+
     pub async fn get_kline(
         &self,
         symbol: &str,
@@ -147,6 +166,15 @@ impl BybitHttp {
         .await
     }
 
+    pub async fn get_instrument_info(&self, symbol: &str) -> BybitResult<InstrumentsInfoResponse> {
+        self.send_get_request::<InstrumentsInfoResponse>(
+            "v5/market/instruments-info",
+            HashMap::from([("category", "linear"), ("symbol", symbol)]),
+            false,
+        )
+        .await
+    }
+
     pub async fn get_orderbook(
         &self,
         category: String,
@@ -177,42 +205,94 @@ impl BybitHttp {
 
 #[cfg(test)]
 mod tests {
+    use reqwest::Method;
+
     use super::*;
+    use crate::transport::mock_client;
 
     #[tokio::test]
     async fn test_get_server_time() {
-        let client = BybitHttp::new("".to_string(), "".to_string());
-        let res = client.get_server_time().await;
-        println!("{:?}", res);
+        let (client, _) = mock_client(
+            Method::GET,
+            "v5/market/time",
+            include_str!("../../fixtures/server_time.json"),
+        );
+        let res = client.get_server_time().await.unwrap();
+        assert_eq!(res.time_second, "1688639403");
     }
 
     #[tokio::test]
     async fn test_get_kline() {
-        let client = BybitHttp::new("".to_string(), "".to_string());
-        let res = client.get_kline("btcusdt", "5", None, None, None).await;
-        println!("{:?}", res);
+        let (client, transport) = mock_client(
+            Method::GET,
+            "v5/market/kline",
+            include_str!("../../fixtures/kline.json"),
+        );
+        let res = client
+            .get_kline("btcusdt", "5", Some("linear"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(res.list.len(), 2);
+        assert_eq!(res.list[0][4], "17055.5");
+        let request = &transport.requests()[0];
+        assert_eq!(request.query_param("symbol").unwrap(), "BTCUSDT");
+        assert_eq!(request.query_param("interval").unwrap(), "5");
     }
 
     #[tokio::test]
     async fn test_get_instruments_info() {
-        let client = BybitHttp::new("".to_string(), "".to_string());
-        let res = client.get_instruments_info("linear".to_string()).await;
-        println!("{:?}", res);
+        let (client, transport) = mock_client(
+            Method::GET,
+            "v5/market/instruments-info",
+            include_str!("../../fixtures/instruments_info.json"),
+        );
+        let res = client
+            .get_instruments_info("linear".to_string())
+            .await
+            .unwrap();
+        assert_eq!(res.list[0].symbol, "BTCUSDT");
+        assert_eq!(
+            transport.requests()[0].query_param("category").unwrap(),
+            "linear"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_instruments_info() {
+        let res = r#"{"category":"linear","list":[{"symbol":"BTCUSDT","contractType":"LinearPerpetual","status":"Trading","baseCoin":"BTC","quoteCoin":"USDT","launchTime":"1585526400000","deliveryTime":"0","deliveryFeeRate":"","priceScale":"2","leverageFilter":{"minLeverage":"1","maxLeverage":"100.00","leverageStep":"0.01"},"priceFilter":{"minPrice":"0.10","maxPrice":"1999999.80","tickSize":"0.10"},"lotSizeFilter":{"maxOrderQty":"1190.000","minOrderQty":"0.001","qtyStep":"0.001","postOnlyMaxOrderQty":"1190.000","maxMktOrderQty":"500.000","minNotionalValue":"5"},"unifiedMarginTrade":true,"fundingInterval":480,"settleCoin":"USDT","copyTrading":"both","upperFundingRate":"0.00375","lowerFundingRate":"-0.00375","isPreListing":false,"preListingInfo":null,"riskParameters":{"priceLimitRatioX":"0.01","priceLimitRatioY":"0.02"}}],"nextPageCursor":""}"#;
+        let res = serde_json::from_str::<InstrumentsInfoResponse>(res).unwrap();
+        assert_eq!(res.list[0].price_filter.tick_size, "0.10");
+        assert_eq!(res.list[0].lot_size_filter.min_notional_value, "5");
     }
 
     #[tokio::test]
     async fn test_get_orderbook() {
-        let client = BybitHttp::new("".to_string(), "".to_string());
+        let (client, transport) = mock_client(
+            Method::GET,
+            "v5/market/orderbook",
+            include_str!("../../fixtures/orderbook.json"),
+        );
         let res = client
             .get_orderbook("linear".to_string(), "BTCUSDT".to_string(), 5)
-            .await;
-        println!("{:?}", res);
+            .await
+            .unwrap();
+        assert_eq!(
+            res.asks[0],
+            ["65557.7".to_string(), "16.606555".to_string()]
+        );
+        assert_eq!(res.bids.len(), 2);
+        assert_eq!(res.update_id, 230704);
+        assert_eq!(transport.requests()[0].query_param("limit").unwrap(), "5");
     }
 
     #[tokio::test]
     async fn test_get_tickers() {
-        let client = BybitHttp::new("".to_string(), "".to_string());
-        let res = client.get_tickers().await;
-        println!("{:?}", res);
+        let (client, _) = mock_client(
+            Method::GET,
+            "v5/market/tickers",
+            include_str!("../../fixtures/tickers.json"),
+        );
+        let res = client.get_tickers().await.unwrap();
+        assert_eq!(res.list[0].mark_price, "16596.00");
     }
 }
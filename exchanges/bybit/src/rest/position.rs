@@ -16,17 +16,21 @@ impl BybitHttp {
 
 #[cfg(test)]
 mod tests {
+    use reqwest::Method;
+
     use super::*;
-    use dotenv::dotenv;
+    use crate::transport::mock_client;
 
     #[tokio::test]
     async fn test_get_positions() -> BybitResult<()> {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key.to_string(), api_secret.to_string());
-        let res = client.get_positions().await;
-        println!("{:?}", res);
+        let (client, _) = mock_client(
+            Method::GET,
+            "v5/position/list",
+            include_str!("../../fixtures/position_list.json"),
+        );
+        let res = client.get_positions().await?;
+        assert_eq!(res.list[0].symbol, "SOLUSDT");
+        assert_eq!(res.list[0].size, "0.5");
         Ok(())
     }
 }
@@ -1,17 +1,17 @@
 use std::collections::HashMap;
 
-use serde_json::{Map, json};
+use serde_json::{json, Map, Value};
 
 use crate::{
     errors::BybitResult,
     http::BybitHttp,
-    types::{CancelAllOrdersResponse, GetOrderResponse, OrderResponse},
+    types::{CancelAllOrdersResponse, GetOrderResponse, OrderResponse, PlaceOrderRequest},
 };
 
 impl BybitHttp {
     pub async fn get_open_order(
         &self,
-        symbol: &String,
+        symbol: &str,
         cloid: Option<String>,
     ) -> BybitResult<GetOrderResponse> {
         let mut params = HashMap::new();
@@ -28,34 +28,31 @@ impl BybitHttp {
             .await
     }
 
-    pub async fn place_order(
+    pub async fn get_order_by_id(
         &self,
-        _cloid: &String,
-        symbol: &String,
-        side: &String,
-        price: &String,
-        qty: &String,
-    ) -> BybitResult<OrderResponse> {
-        let mut params = Map::new();
-        // params.insert("orderLinkId".to_string(), json!(cloid));
-        params.insert("category".to_string(), json!("linear"));
-        params.insert("symbol".to_string(), json!(symbol));
-        params.insert("side".to_string(), json!(side));
-        params.insert("positionIdx".to_string(), json!(0));
-        params.insert("orderType".to_string(), json!("Limit"));
-        params.insert("qty".to_string(), json!(qty));
-        params.insert("price".to_string(), json!(price));
-        params.insert("timeInForce".to_string(), json!("GTC"));
+        symbol: &str,
+        order_id: &str,
+    ) -> BybitResult<GetOrderResponse> {
+        let mut params = HashMap::new();
+        params.insert("category", "linear");
+        params.insert("symbol", symbol);
+        params.insert("orderId", order_id);
+
+        self.send_get_request::<GetOrderResponse>("v5/order/realtime", params, true)
+            .await
+    }
+
+    pub async fn create_order(&self, request: &PlaceOrderRequest) -> BybitResult<OrderResponse> {
+        let params = match serde_json::to_value(request)? {
+            Value::Object(params) => params,
+            _ => unreachable!("PlaceOrderRequest serializes to an object"),
+        };
 
         self.send_post_request::<OrderResponse>("v5/order/create", params)
             .await
     }
 
-    pub async fn cancel_order(
-        &self,
-        symbol: &String,
-        cloid: &String,
-    ) -> BybitResult<OrderResponse> {
+    pub async fn cancel_order(&self, symbol: &str, cloid: &str) -> BybitResult<OrderResponse> {
         let mut params = Map::new();
         params.insert("category".to_string(), json!("linear"));
         params.insert("symbol".to_string(), json!(symbol));
@@ -65,7 +62,7 @@ impl BybitHttp {
             .await
     }
 
-    pub async fn cancel_all_orders(&self, symbol: &String) -> BybitResult<CancelAllOrdersResponse> {
+    pub async fn cancel_all_orders(&self, symbol: &str) -> BybitResult<CancelAllOrdersResponse> {
         let mut params = Map::new();
         params.insert("category".to_string(), json!("linear"));
         params.insert("symbol".to_string(), json!(symbol));
@@ -76,17 +73,51 @@ impl BybitHttp {
 
     pub async fn amend_order(
         &self,
-        symbol: &String,
-        cloid: &String,
-        price: &String,
-        // qty: &String,
+        symbol: &str,
+        cloid: &str,
+        price: &str,
     ) -> BybitResult<OrderResponse> {
         let mut params = Map::new();
         params.insert("category".to_string(), json!("linear"));
         params.insert("orderLinkId".to_string(), json!(cloid));
         params.insert("symbol".to_string(), json!(symbol));
         params.insert("price".to_string(), json!(price));
-        // params.insert("qty".to_string(), json!(qty));
+
+        self.send_post_request::<OrderResponse>("v5/order/amend", params)
+            .await
+    }
+
+    pub async fn cancel_order_by_id(
+        &self,
+        symbol: &str,
+        order_id: &str,
+    ) -> BybitResult<OrderResponse> {
+        let mut params = Map::new();
+        params.insert("category".to_string(), json!("linear"));
+        params.insert("symbol".to_string(), json!(symbol));
+        params.insert("orderId".to_string(), json!(order_id));
+
+        self.send_post_request::<OrderResponse>("v5/order/cancel", params)
+            .await
+    }
+
+    pub async fn amend_order_by_id(
+        &self,
+        symbol: &str,
+        order_id: &str,
+        price: Option<String>,
+        qty: Option<String>,
+    ) -> BybitResult<OrderResponse> {
+        let mut params = Map::new();
+        params.insert("category".to_string(), json!("linear"));
+        params.insert("orderId".to_string(), json!(order_id));
+        params.insert("symbol".to_string(), json!(symbol));
+        if let Some(price) = price {
+            params.insert("price".to_string(), json!(price));
+        }
+        if let Some(qty) = qty {
+            params.insert("qty".to_string(), json!(qty));
+        }
 
         self.send_post_request::<OrderResponse>("v5/order/amend", params)
             .await
@@ -95,47 +126,67 @@ impl BybitHttp {
 
 #[cfg(test)]
 mod tests {
+    use reqwest::Method;
+
     use super::*;
-    use dotenv::dotenv;
+    use crate::{
+        transport::mock_client,
+        types::{BybitOrderStatus, BybitOrderType, BybitSide, BybitTimeInForce},
+    };
 
     #[tokio::test]
     async fn test_place_order() -> BybitResult<()> {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key.to_string(), api_secret.to_string());
-        let res = client
-            .place_order(
-                &"".to_string(),
-                &"SOLUSDT".to_string(),
-                &"Buy".to_string(),
-                &"110".to_string(),
-                &"0.1".to_string(),
-            )
-            .await;
-        println!("{:?}", res);
+        let (client, transport) = mock_client(
+            Method::POST,
+            "v5/order/create",
+            include_str!("../../fixtures/order_create.json"),
+        );
+        let request =
+            PlaceOrderRequest::new("SOLUSDT", BybitSide::Buy, BybitOrderType::Limit, "0.1")
+                .price("110")
+                .time_in_force(BybitTimeInForce::Gtc)
+                .order_link_id("spot-test-postonly");
+        let res = client.create_order(&request).await?;
+        assert_eq!(res.order_id, "1321003749386327552");
+
+        let request = &transport.requests()[0];
+        assert!(request.headers.contains_key("X-BAPI-SIGN"));
+        let body: Value = serde_json::from_str(request.body.as_deref().unwrap())?;
+        assert_eq!(body["side"], "Buy");
+        assert_eq!(body["price"], "110");
+        assert_eq!(body["orderLinkId"], "spot-test-postonly");
         Ok(())
     }
 
     #[tokio::test]
     async fn test_get_open_order() -> BybitResult<()> {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key.to_string(), api_secret.to_string());
-        let res = client.get_open_order(&"SOLUSDT".to_string(), None).await;
-        println!("{:?}", res);
+        let (client, transport) = mock_client(
+            Method::GET,
+            "v5/order/realtime",
+            include_str!("../../fixtures/order_realtime.json"),
+        );
+        let res = client
+            .get_open_order("ETHUSDT", Some("test-000005".to_string()))
+            .await?;
+        assert!(matches!(res.list[0].order_status, BybitOrderStatus::New));
+        assert_eq!(res.list[0].leaves_qty, "0.10");
+        assert_eq!(
+            transport.requests()[0].query_param("orderLinkId").unwrap(),
+            "test-000005"
+        );
         Ok(())
     }
 
     #[tokio::test]
     async fn test_cancel_all_orders() -> BybitResult<()> {
-        dotenv().ok();
-        let api_key = std::env::var("BYBIT_API_KEY").expect("BYBIT_API_KEY");
-        let api_secret = std::env::var("BYBIT_API_SECRET").expect("BYBIT_API_SECRET");
-        let client = BybitHttp::new(api_key.to_string(), api_secret.to_string());
-        let res = client.cancel_all_orders(&"SOLUSDT".to_string()).await;
-        println!("{:?}", res);
+        let (client, _) = mock_client(
+            Method::POST,
+            "v5/order/cancel-all",
+            include_str!("../../fixtures/order_cancel_all.json"),
+        );
+        let res = client.cancel_all_orders("SOLUSDT").await?;
+        assert_eq!(res.list.len(), 2);
+        assert_eq!(res.list[1].order_link_id, "1616024287544869633");
         Ok(())
     }
 }
@@ -0,0 +1,47 @@
+use crate::errors::BybitError;
+
+pub use cefi_rs_common::retry::RetryPolicy;
+
+/// Codes of failures that may go away on their own: server timeout, request
+/// time outside of the receive window, which a new attempt signs again,
+/// internal error and backend timeout. Too many visits, 10006, is left to the
+/// rate limiter, retrying it would only spend more of the limit.
+const TRANSIENT_CODES: [i64; 4] = [10000, 10002, 10016, 170007];
+
+/// Whether the request may succeed when sent again.
+pub(crate) fn is_transient(err: &BybitError) -> bool {
+    match err {
+        BybitError::HttpError(err) => err.is_timeout() || err.is_connect(),
+        BybitError::ServerError(..) => true,
+        BybitError::ApiError(code, _) => TRANSIENT_CODES.contains(code),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&BybitError::ApiError(
+            10016,
+            "Internal server error".to_string()
+        )));
+        assert!(!is_transient(&BybitError::ApiError(
+            110007,
+            "Insufficient available balance".to_string()
+        )));
+        assert!(!is_transient(&BybitError::ApiError(
+            10006,
+            "Too many visits!".to_string()
+        )));
+        assert!(is_transient(&BybitError::ServerError(
+            503,
+            "Service Unavailable".to_string()
+        )));
+        assert!(!is_transient(&BybitError::DeserializeError(
+            "expected value".to_string()
+        )));
+    }
+}
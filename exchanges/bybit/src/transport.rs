@@ -0,0 +1,18 @@
+pub use cefi_rs_common::transport::{
+    HttpRequest, HttpResponse, MockTransport, ReqwestTransport, Transport, TransportError,
+};
+
+/// Client answering `method` requests to `endpoint` with `fixture`.
+#[cfg(test)]
+pub(crate) fn mock_client(
+    method: reqwest::Method,
+    endpoint: &str,
+    fixture: &str,
+) -> (crate::http::BybitHttp, std::sync::Arc<MockTransport>) {
+    let transport = std::sync::Arc::new(MockTransport::new());
+    transport.push_ok(method, endpoint, fixture);
+    let client = crate::http::BybitHttp::new("key".to_string(), "secret".to_string())
+        .unwrap()
+        .with_transport(transport.clone());
+    (client, transport)
+}
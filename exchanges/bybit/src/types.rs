@@ -1,6 +1,16 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum BybitCategory {
+    Linear,
+    Inverse,
+    Spot,
+    Option,
+}
+
 #[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum BybitSide {
     Buy,
@@ -22,6 +32,228 @@ pub enum BybitOrderStatus {
     Deactivated,
 }
 
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BybitOrderType {
+    Market,
+    Limit,
+}
+
+#[derive(strum_macros::Display, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BybitTimeInForce {
+    #[serde(rename = "GTC")]
+    #[strum(serialize = "GTC")]
+    Gtc,
+    #[serde(rename = "IOC")]
+    #[strum(serialize = "IOC")]
+    Ioc,
+    #[serde(rename = "FOK")]
+    #[strum(serialize = "FOK")]
+    Fok,
+    PostOnly,
+}
+
+/// Direction the last price has to move to trigger a conditional order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum BybitTriggerDirection {
+    Rise,
+    Fall,
+}
+
+impl From<BybitTriggerDirection> for u8 {
+    fn from(value: BybitTriggerDirection) -> Self {
+        match value {
+            BybitTriggerDirection::Rise => 1,
+            BybitTriggerDirection::Fall => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for BybitTriggerDirection {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(BybitTriggerDirection::Rise),
+            2 => Ok(BybitTriggerDirection::Fall),
+            _ => Err(format!("invalid trigger direction {value}")),
+        }
+    }
+}
+
+/// Body of `POST v5/order/create`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceOrderRequest {
+    pub category: String,
+    pub symbol: String,
+    pub side: BybitSide,
+    pub order_type: BybitOrderType,
+    pub qty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<BybitTimeInForce>,
+    /// 0 one-way mode, 1 hedge-mode buy side, 2 hedge-mode sell side
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_idx: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_link_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_direction: Option<BybitTriggerDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub close_on_trigger: Option<bool>,
+}
+
+impl PlaceOrderRequest {
+    pub fn new(symbol: &str, side: BybitSide, order_type: BybitOrderType, qty: &str) -> Self {
+        Self {
+            category: "linear".to_string(),
+            symbol: symbol.to_string(),
+            side,
+            order_type,
+            qty: qty.to_string(),
+            price: None,
+            time_in_force: None,
+            position_idx: None,
+            order_link_id: None,
+            trigger_price: None,
+            trigger_direction: None,
+            reduce_only: None,
+            close_on_trigger: None,
+        }
+    }
+
+    pub fn price(mut self, price: &str) -> Self {
+        self.price = Some(price.to_string());
+        self
+    }
+
+    pub fn time_in_force(mut self, time_in_force: BybitTimeInForce) -> Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn position_idx(mut self, position_idx: u8) -> Self {
+        self.position_idx = Some(position_idx);
+        self
+    }
+
+    pub fn order_link_id(mut self, order_link_id: &str) -> Self {
+        self.order_link_id = Some(order_link_id.to_string());
+        self
+    }
+
+    pub fn trigger(mut self, trigger_price: &str, direction: BybitTriggerDirection) -> Self {
+        self.trigger_price = Some(trigger_price.to_string());
+        self.trigger_direction = Some(direction);
+        self
+    }
+
+    pub fn reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = Some(reduce_only);
+        self
+    }
+
+    pub fn close_on_trigger(mut self, close_on_trigger: bool) -> Self {
+        self.close_on_trigger = Some(close_on_trigger);
+        self
+    }
+}
+
+/// Body of `POST v5/order/amend`, the order is identified by `order_id` or
+/// `order_link_id`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendOrderRequest {
+    pub category: String,
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_link_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<String>,
+}
+
+impl AmendOrderRequest {
+    pub fn new(symbol: &str, order: BybitOrderRef) -> Self {
+        let (order_id, order_link_id) = order.into_fields();
+        Self {
+            category: "linear".to_string(),
+            symbol: symbol.to_string(),
+            order_id,
+            order_link_id,
+            qty: None,
+            price: None,
+            trigger_price: None,
+        }
+    }
+
+    pub fn qty(mut self, qty: &str) -> Self {
+        self.qty = Some(qty.to_string());
+        self
+    }
+
+    pub fn price(mut self, price: &str) -> Self {
+        self.price = Some(price.to_string());
+        self
+    }
+
+    pub fn trigger_price(mut self, trigger_price: &str) -> Self {
+        self.trigger_price = Some(trigger_price.to_string());
+        self
+    }
+}
+
+/// Body of `POST v5/order/cancel`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelOrderRequest {
+    pub category: String,
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_link_id: Option<String>,
+}
+
+impl CancelOrderRequest {
+    pub fn new(symbol: &str, order: BybitOrderRef) -> Self {
+        let (order_id, order_link_id) = order.into_fields();
+        Self {
+            category: "linear".to_string(),
+            symbol: symbol.to_string(),
+            order_id,
+            order_link_id,
+        }
+    }
+}
+
+/// Identifies an existing order either by the exchange id or by the client id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BybitOrderRef {
+    OrderId(String),
+    OrderLinkId(String),
+}
+
+impl BybitOrderRef {
+    fn into_fields(self) -> (Option<String>, Option<String>) {
+        match self {
+            BybitOrderRef::OrderId(order_id) => (Some(order_id), None),
+            BybitOrderRef::OrderLinkId(order_link_id) => (None, Some(order_link_id)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BybitHttpResponse {
@@ -91,7 +323,8 @@ pub struct GetPositionResponse {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BybitLinearPosition {
-    // "avgPrice": "143.87891359",
+    #[serde(alias = "entryPrice")]
+    pub avg_price: String,
     // "bustPrice": "0.010",
     pub created_time: String,
     // "cumRealisedPnl": "-3.18147417",
@@ -203,17 +436,40 @@ mod tests {
     #[test]
     fn deserialize_http_response_error() {
         let json_data = r#"{"retCode":10001,"retMsg":"Qty invalid","result":{},"retExtInfo":{},"time":1727663049561}"#;
-        let r = serde_json::from_str::<BybitHttpResponse>(&json_data).unwrap();
+        let r = serde_json::from_str::<BybitHttpResponse>(json_data).unwrap();
         println!("{:?}", r.result.to_string());
     }
 
     #[test]
     fn deserialize_http_order_response() {
         let json_data = r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"29c242ff-7da2-47d4-a243-ba6ff034005e","orderLinkId":""},"retExtInfo":{},"time":1727663866609}"#;
-        let r = serde_json::from_str::<BybitHttpResponse>(&json_data).unwrap();
+        let r = serde_json::from_str::<BybitHttpResponse>(json_data).unwrap();
         println!("{:?}", r.result.to_string());
     }
 
+    #[test]
+    fn serialize_place_order_request() {
+        let req =
+            PlaceOrderRequest::new("BTCUSDT", BybitSide::Sell, BybitOrderType::Market, "0.01")
+                .trigger("60000", BybitTriggerDirection::Fall)
+                .order_link_id("sl-1")
+                .close_on_trigger(true);
+        assert_eq!(
+            serde_json::to_value(&req).unwrap(),
+            serde_json::json!({
+                "category": "linear",
+                "symbol": "BTCUSDT",
+                "side": "Sell",
+                "orderType": "Market",
+                "qty": "0.01",
+                "orderLinkId": "sl-1",
+                "triggerPrice": "60000",
+                "triggerDirection": 2,
+                "closeOnTrigger": true
+            })
+        );
+    }
+
     #[test]
     fn test_deserialize_get_positions_response() {
         let response = r#"{"category":"linear","list":[{"adlRankIndicator":2,"autoAddMargin":0,"avgPrice":"149.127428","bustPrice":"0.010","createdTime":"1715069854842","cumRealisedPnl":"-0.86731017","curRealisedPnl":"-1.36077531","isReduceOnly":false,"leverage":"10","leverageSysUpdatedTime":"","liqPrice":"","markPrice":"173.732","mmrSysUpdatedTime":"","positionBalance":"7.49328044","positionIM":"0.9916974","positionIdx":0,"positionMM":"0.0000325","positionStatus":"Normal","positionValue":"74.563714","riskId":281,"riskLimitValue":"210000","seq":155916379358,"sessionAvgPrice":"","side":"Buy","size":"0.5","stopLoss":"0.000","symbol":"SOLUSDT","takeProfit":"0.000","tpslMode":"Full","tradeMode":0,"trailingStop":"0.000","unrealisedPnl":"12.302286","updatedTime":"1730087396775"},{"adlRankIndicator":2,"autoAddMargin":0,"avgPrice":"1.3841","bustPrice":"15.22880","createdTime":"1724910435941","cumRealisedPnl":"-5.7101154","curRealisedPnl":"0.38234475","isReduceOnly":false,"leverage":"10","leverageSysUpdatedTime":"","liqPrice":"15.21490","markPrice":"1.69352","mmrSysUpdatedTime":"","positionBalance":"4.48667381","positionIM":"0.27682","positionIdx":0,"positionMM":"1.52288","positionStatus":"Normal","positionValue":"13.841","riskId":1,"riskLimitValue":"200000","seq":184120255016,"sessionAvgPrice":"","side":"Sell","size":"10","stopLoss":"0.00000","symbol":"SUIUSDT","takeProfit":"0.00000","tpslMode":"Full","tradeMode":0,"trailingStop":"0.00000","unrealisedPnl":"-3.0942","updatedTime":"1730087396775"},{"adlRankIndicator":2,"autoAddMargin":0,"avgPrice":"1.6729","bustPrice":"36.0273","createdTime":"1723618141587","cumRealisedPnl":"0.11741437","curRealisedPnl":"0.11741437","isReduceOnly":false,"leverage":"10","leverageSysUpdatedTime":"","liqPrice":"36.0161","markPrice":"2.3678","mmrSysUpdatedTime":"","positionBalance":"3.45280842","positionIM":"0.08899828","positionIdx":0,"positionMM":"0.96553164","positionStatus":"Normal","positionValue":"6.6916","riskId":1,"riskLimitValue":"100000","seq":135593215329,"sessionAvgPrice":"","side":"Sell","size":"4","stopLoss":"0.0000","symbol":"WIFUSDT","takeProfit":"0.0000","tpslMode":"Full","tradeMode":0,"trailingStop":"0.0000","unrealisedPnl":"-2.7796","updatedTime":"1730087396775"},{"adlRankIndicator":4,"autoAddMargin":0,"avgPrice":"0.116144","bustPrice":"0.439317","createdTime":"1723618019735","cumRealisedPnl":"0.30966853","curRealisedPnl":"0.30966853","isReduceOnly":false,"leverage":"10","leverageSysUpdatedTime":"","liqPrice":"0.434671","markPrice":"0.063599","mmrSysUpdatedTime":"","positionBalance":"5.02440687","positionIM":"3.9953536","positionIdx":0,"positionMM":"7.5562524","positionStatus":"Normal","positionValue":"49.94192","riskId":1,"riskLimitValue":"25000","seq":30747967432,"sessionAvgPrice":"","side":"Sell","size":"430","stopLoss":"0.000000","symbol":"MOTHERUSDT","takeProfit":"0.000000","tpslMode":"Full","tradeMode":0,"trailingStop":"0.000000","unrealisedPnl":"22.59435","updatedTime":"1730082173992"}],"nextPageCursor":""}"#;
@@ -1,19 +1,19 @@
-use futures::stream::SplitStream;
-use futures::{SinkExt, StreamExt};
+use cefi_rs_common::ws::{SupervisorConfig, WsProtocol, WsSupervisor};
 use hmac::{Hmac, Mac};
 use serde_json::json;
 use sha2::Sha256;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::net::TcpStream;
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{error, info, warn};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
 
-use crate::ws_types::BybitWsUpdate;
+pub(crate) use cefi_rs_common::ws::{WsCommand, WsFrame};
 
-static BYBIT_HOST: &'static str = "wss://stream.bybit.com/v5/private";
-
-type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+use crate::{
+    clock::ServerClock,
+    config::{Environment, RECV_WINDOW},
+    errors::{BybitError, BybitResult},
+    ws_types::{BybitWsEvent, BybitWsUpdate},
+};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 struct AuthMessage {
@@ -22,12 +22,8 @@ struct AuthMessage {
     args: Vec<String>,
 }
 
-fn get_auth_message(api_key: String, api_secret: String) -> String {
-    let expires = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_millis()
-        + 5000;
+fn get_auth_message(api_key: &str, api_secret: &str, now_ms: i64, recv_window: Duration) -> String {
+    let expires = now_ms + recv_window.as_millis() as i64;
     let mut mac =
         Hmac::<Sha256>::new_from_slice(api_secret.as_bytes()).expect("Invalid key length");
     mac.update(format!("GET/realtime{expires}").as_bytes());
@@ -40,89 +36,410 @@ fn get_auth_message(api_key: String, api_secret: String) -> String {
     serde_json::to_string(&auth_msg).unwrap()
 }
 
+/// `op` is `subscribe` or `unsubscribe`.
+fn get_subscribe_message(op: &str, topics: &[String]) -> String {
+    json!({
+        "req_id": "1",
+        "op": op,
+        "args": topics
+    })
+    .to_string()
+}
+
+/// Private streams answer a ping with `op: pong`, public ones echo `op: ping`
+/// with `ret_msg: pong`.
+fn is_pong(text: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else {
+        return false;
+    };
+    match value["op"].as_str() {
+        Some("pong") => true,
+        Some("ping") => value["ret_msg"] == "pong",
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    pub url: String,
+    /// Interval between two heartbeats.
+    pub ping_interval: Duration,
+    /// Time allowed for the pong before the connection is considered dead.
+    pub pong_timeout: Duration,
+    /// Delay before the first reconnection attempt, doubled on every failure.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Time the authentication, or a trade request, stays valid after its
+    /// timestamp.
+    pub recv_window: Duration,
+    /// Clock the authentication expiry is computed with, share the one of
+    /// `BybitHttp` to correct for a drifting host clock.
+    pub clock: Arc<ServerClock>,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            url: Environment::Mainnet.ws_private_url().to_string(),
+            ping_interval: Duration::from_secs(20),
+            pong_timeout: Duration::from_secs(10),
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            recv_window: RECV_WINDOW,
+            clock: Arc::new(ServerClock::new()),
+        }
+    }
+}
+
+impl WsConfig {
+    fn supervisor_config(&self) -> SupervisorConfig {
+        SupervisorConfig {
+            url: self.url.clone(),
+            ping_interval: self.ping_interval,
+            pong_timeout: self.pong_timeout,
+            min_backoff: self.min_backoff,
+            max_backoff: self.max_backoff,
+            max_connection_age: None,
+        }
+    }
+}
+
+/// Authentication of the private and trade streams, subscriptions and the
+/// `op: ping` heartbeat.
+struct BybitProtocol {
+    credentials: Option<(String, String)>,
+    clock: Arc<ServerClock>,
+    recv_window: Duration,
+}
+
+impl WsProtocol for BybitProtocol {
+    fn name(&self) -> &'static str {
+        "bybit"
+    }
+
+    fn on_connect(&mut self) -> Vec<String> {
+        self.credentials
+            .iter()
+            .map(|(api_key, api_secret)| {
+                get_auth_message(api_key, api_secret, self.clock.now_ms(), self.recv_window)
+            })
+            .collect()
+    }
+
+    fn subscribe(&mut self, topics: &[String]) -> String {
+        get_subscribe_message("subscribe", topics)
+    }
+
+    fn unsubscribe(&mut self, topics: &[String]) -> String {
+        get_subscribe_message("unsubscribe", topics)
+    }
+
+    fn ping(&self) -> Message {
+        let msg = json!({
+            "req_id": "100001",
+            "op": "ping",
+        });
+        Message::Text(msg.to_string())
+    }
+
+    fn is_pong(&self, text: &str) -> bool {
+        is_pong(text)
+    }
+}
+
+/// Supervises a stream of `config` in a background task, authenticated with
+/// `credentials` if any.
+pub(crate) fn supervise(
+    config: &WsConfig,
+    credentials: Option<(String, String)>,
+    topics: Vec<String>,
+) -> (
+    mpsc::UnboundedReceiver<WsFrame>,
+    mpsc::UnboundedSender<WsCommand>,
+) {
+    let protocol = BybitProtocol {
+        credentials,
+        clock: config.clock.clone(),
+        recv_window: config.recv_window,
+    };
+    WsSupervisor::spawn(config.supervisor_config(), protocol, topics)
+}
+
+/// Private stream client. The connection is supervised in a background task,
+/// so a dropped link is reported as `ConnectionState` events and restored with
+/// the same subscriptions instead of ending the stream.
 pub struct BybitWs {
-    stream: WsStream,
+    frames: mpsc::UnboundedReceiver<WsFrame>,
+    commands: mpsc::UnboundedSender<WsCommand>,
 }
 
 impl BybitWs {
-    pub async fn new(api_key: String, api_secret: String, topics: Vec<String>) -> Self {
-        let (ws_stream, _) = connect_async(BYBIT_HOST).await.expect("Failed to connect");
+    pub fn new(api_key: String, api_secret: String, topics: Vec<String>) -> Self {
+        Self::with_config(api_key, api_secret, topics, WsConfig::default())
+    }
 
-        let (mut write, read) = ws_stream.split();
+    pub fn with_config(
+        api_key: String,
+        api_secret: String,
+        topics: Vec<String>,
+        config: WsConfig,
+    ) -> Self {
+        let (frames, commands) = supervise(&config, Some((api_key, api_secret)), topics);
+        Self { frames, commands }
+    }
 
-        // send auth message
-        let auth_message = get_auth_message(api_key, api_secret);
-        write
-            .send(Message::Text(auth_message))
-            .await
-            .expect("error sending auth message");
+    /// Subscribes to more topics, they are replayed on reconnection as well.
+    pub fn subscribe(&self, topics: Vec<String>) {
+        // the supervisor only stops once this client is dropped
+        let _ = self.commands.send(WsCommand::Subscribe(topics));
+    }
 
-        // subscribe to private updates
-        let sub_msg = json!({
-            "req_id": "1",
-            "op": "subscribe",
-            "args": topics
-        });
-        write
-            .send(Message::Text(sub_msg.to_string()))
+    /// Returns the next event, `None` only once the background task has
+    /// stopped. A message that cannot be parsed is returned as an error and
+    /// the stream goes on.
+    pub async fn next(&mut self) -> Option<BybitResult<BybitWsEvent>> {
+        loop {
+            let text = match self.frames.recv().await? {
+                WsFrame::State(state) => return Some(Ok(BybitWsEvent::State(state))),
+                WsFrame::Text(text) => text,
+            };
+            let update = match serde_json::from_str::<BybitWsUpdate>(&text) {
+                Ok(update) => update,
+                Err(err) => {
+                    let err = BybitError::DeserializeError(format!("{err}: {text}"));
+                    return Some(Err(err));
+                }
+            };
+            let event = match update {
+                BybitWsUpdate::BybitUpdateData(data) => BybitWsEvent::Update(data),
+                BybitWsUpdate::OpResponse(response) => match response.op.as_str() {
+                    "auth" if response.success => BybitWsEvent::Authenticated,
+                    "auth" => BybitWsEvent::AuthFailed(response.ret_msg),
+                    "subscribe" => BybitWsEvent::Subscribed(response),
+                    _ => continue,
+                },
+                // pongs are consumed by the supervisor already
+                BybitWsUpdate::Pong(_) => continue,
+            };
+            return Some(Ok(event));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws_types::{BybitUpdateData, ConnectionState};
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, WebSocketStream};
+
+    type WsStream = WebSocketStream<TcpStream>;
+
+    const ORDER_UPDATE: &str = r#"{"topic":"order.linear","id":"62552242_SOLUSDT_149260954383","creationTime":1727433749096,"data":[{"category":"linear","symbol":"SOLUSDT","orderId":"f38c5e38-46ef-4a8a-b115-fd3392d4f144","orderLinkId":"","side":"Buy","orderStatus":"New","cancelType":"UNKNOWN","rejectReason":"EC_NoError","price":"99","qty":"0.1","leavesQty":"0.1","cumExecQty":"0","createType":"CreateByUser","createdTime":"1727433748480","updatedTime":"1727433748481"}]}"#;
+
+    fn test_config(url: String) -> WsConfig {
+        WsConfig {
+            url,
+            ping_interval: Duration::from_millis(100),
+            pong_timeout: Duration::from_millis(100),
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            ..WsConfig::default()
+        }
+    }
+
+    async fn local_server() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        (listener, url)
+    }
+
+    async fn accept(listener: &TcpListener) -> WsStream {
+        let (stream, _) = listener.accept().await.unwrap();
+        accept_async(stream).await.unwrap()
+    }
+
+    async fn next_text(server: &mut WsStream) -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    async fn expect_state(ws: &mut BybitWs) -> ConnectionState {
+        match ws.next().await.unwrap().unwrap() {
+            BybitWsEvent::State(state) => state,
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[test]
+    fn test_is_pong() {
+        assert!(is_pong(
+            r#"{"req_id":"100001","op":"pong","args":["1727434943071"],"conn_id":"cmjonqvavkfduu60h6d0-231ex0"}"#
+        ));
+        assert!(is_pong(
+            r#"{"success":true,"ret_msg":"pong","conn_id":"0970e817-426e-429a-a679-ff7f55e0b16a","op":"ping"}"#
+        ));
+        assert!(!is_pong(ORDER_UPDATE));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_and_resubscribe() {
+        let (listener, url) = local_server().await;
+        let mut ws = BybitWs::with_config(
+            "key".to_string(),
+            "secret".to_string(),
+            vec!["order.linear".to_string()],
+            test_config(url),
+        );
+
+        let mut server = accept(&listener).await;
+        assert_eq!(next_text(&mut server).await["op"], "auth");
+        let subscribe = next_text(&mut server).await;
+        assert_eq!(subscribe["op"], "subscribe");
+        assert_eq!(subscribe["args"], json!(["order.linear"]));
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+
+        server
+            .send(Message::Text(ORDER_UPDATE.to_string()))
             .await
-            .unwrap_or_else(|err| error!("Error sending ping: {err}"));
+            .unwrap();
+        match ws.next().await.unwrap().unwrap() {
+            BybitWsEvent::Update(BybitUpdateData::OrderLinear(orders)) => {
+                assert_eq!(orders[0].order.symbol, "SOLUSDT")
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+
+        // topics already subscribed are not sent again
+        ws.subscribe(vec!["order.linear".to_string()]);
+        ws.subscribe(vec![
+            "order.linear".to_string(),
+            "position.linear".to_string(),
+            "position.linear".to_string(),
+        ]);
+        assert_eq!(
+            next_text(&mut server).await["args"],
+            json!(["position.linear"])
+        );
+
+        server.close(None).await.unwrap();
+        drop(server);
+        assert!(matches!(
+            expect_state(&mut ws).await,
+            ConnectionState::Disconnected(_)
+        ));
+        assert!(matches!(
+            expect_state(&mut ws).await,
+            ConnectionState::Reconnecting { attempt: 1, .. }
+        ));
 
+        let mut server = accept(&listener).await;
+        assert_eq!(next_text(&mut server).await["op"], "auth");
+        assert_eq!(
+            next_text(&mut server).await["args"],
+            json!(["order.linear", "position.linear"])
+        );
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_missing_pong_reconnects() {
+        let (listener, url) = local_server().await;
+        let mut ws = BybitWs::with_config(
+            "key".to_string(),
+            "secret".to_string(),
+            vec![],
+            test_config(url),
+        );
+
+        // keep the socket open but never answer the pings
+        let mut server = accept(&listener).await;
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+        match expect_state(&mut ws).await {
+            ConnectionState::Disconnected(reason) => assert!(reason.contains("no pong")),
+            state => panic!("unexpected state {state:?}"),
+        }
+        assert_eq!(next_text(&mut server).await["op"], "auth");
+        assert_eq!(next_text(&mut server).await["op"], "ping");
+
+        let _server = accept(&listener).await;
+        assert!(matches!(
+            expect_state(&mut ws).await,
+            ConnectionState::Reconnecting { .. }
+        ));
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_answered_pings_keep_connection() {
+        let (listener, url) = local_server().await;
+        let mut ws = BybitWs::with_config(
+            "key".to_string(),
+            "secret".to_string(),
+            vec![],
+            test_config(url),
+        );
+
+        let mut server = accept(&listener).await;
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
         tokio::spawn(async move {
             loop {
-                info!("Sending ping...");
-                let msg = json!({
-                    "req_id": "100001",
-                    "op": "ping",
-                });
-                write
-                    .send(Message::Text(msg.to_string()))
-                    .await
-                    .unwrap_or_else(|err| error!("Error sending ping: {err}"));
-                tokio::time::sleep(Duration::from_secs(20)).await;
+                let msg = next_text(&mut server).await;
+                if msg["op"] == "ping" {
+                    let pong = json!({"req_id": "100001", "op": "pong", "args": ["1727434943071"], "conn_id": "test"});
+                    server.send(Message::Text(pong.to_string())).await.unwrap();
+                }
             }
         });
 
-        Self { stream: read }
+        let res = tokio::time::timeout(Duration::from_millis(500), ws.next()).await;
+        assert!(res.is_err(), "unexpected event {res:?}");
     }
 
-    pub async fn next(&mut self) -> Option<BybitWsUpdate> {
-        if let Some(msg) = self.stream.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    match serde_json::from_str::<BybitWsUpdate>(text.as_str()) {
-                        Ok(update) => return Some(update),
-                        Err(err) => {
-                            error!("error deserializing {text}: {err}");
-                            return None;
-                        }
-                    }
-                }
-                Ok(Message::Binary(bin)) => {
-                    info!("Received binary data: {:?}", bin);
-                }
-                Ok(Message::Ping(ping)) => {
-                    info!("Received ping: {:?}", ping);
-                }
-                Ok(Message::Pong(_pong)) => {
-                    info!("Received pong");
-                }
-                Ok(Message::Close(close)) => {
-                    warn!("Connection closed: {:?}", close);
-                }
-                Err(e) => {
-                    error!("Error receiving message: {:?}", e);
-                }
-                _ => {}
-            }
+    #[tokio::test]
+    async fn test_typed_events() {
+        let (listener, url) = local_server().await;
+        let mut ws = BybitWs::with_config(
+            "key".to_string(),
+            "secret".to_string(),
+            vec!["order.linear".to_string()],
+            test_config(url),
+        );
+
+        let mut server = accept(&listener).await;
+        assert_eq!(expect_state(&mut ws).await, ConnectionState::Connected);
+        for msg in [
+            r#"{"success":false,"ret_msg":"Request not authorized","op":"auth","conn_id":"cnj7ulkfpa38vo3ucsjg-5"}"#,
+            r#"{"success":true,"ret_msg":"","op":"subscribe","conn_id":"cnj7ulkfpa38vo3ucsjg-5","req_id":"1"}"#,
+            r#"{"req_id":"100001","op":"pong","args":["1727434943071"],"conn_id":"cnj7ulkfpa38vo3ucsjg-5"}"#,
+            r#"{"topic":"order.linear","data":[{"symbol":"SOLUSDT"}]}"#,
+            ORDER_UPDATE,
+        ] {
+            server.send(Message::Text(msg.to_string())).await.unwrap();
         }
-        None
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::ws_types::BybitWsUpdate;
+        match ws.next().await.unwrap().unwrap() {
+            BybitWsEvent::AuthFailed(msg) => assert_eq!(msg, "Request not authorized"),
+            event => panic!("unexpected event {event:?}"),
+        }
+        match ws.next().await.unwrap().unwrap() {
+            BybitWsEvent::Subscribed(response) => assert!(response.success),
+            event => panic!("unexpected event {event:?}"),
+        }
+        // the pong is skipped, the malformed update does not end the stream
+        assert!(matches!(
+            ws.next().await.unwrap(),
+            Err(BybitError::DeserializeError(_))
+        ));
+        assert!(matches!(
+            ws.next().await.unwrap().unwrap(),
+            BybitWsEvent::Update(BybitUpdateData::OrderLinear(_))
+        ));
+    }
 
     #[test]
     fn test_deserialize_position() {
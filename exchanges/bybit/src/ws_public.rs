@@ -0,0 +1,182 @@
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::{
+    config::Environment,
+    types::BybitCategory,
+    ws::{supervise, WsCommand, WsConfig, WsFrame},
+    ws_types::{BybitPublicTopic, BybitPublicWsEvent, BybitPublicWsUpdate},
+};
+
+impl WsConfig {
+    pub fn public(category: BybitCategory) -> Self {
+        Self {
+            url: format!("{}/{category}", Environment::Mainnet.ws_public_url()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Market data stream of one category, supervised like `BybitWs` but without
+/// authentication.
+pub struct BybitPublicWs {
+    frames: mpsc::UnboundedReceiver<WsFrame>,
+    commands: mpsc::UnboundedSender<WsCommand>,
+}
+
+impl BybitPublicWs {
+    pub fn new(category: BybitCategory, topics: Vec<BybitPublicTopic>) -> Self {
+        Self::with_config(topics, WsConfig::public(category))
+    }
+
+    pub fn with_config(topics: Vec<BybitPublicTopic>, config: WsConfig) -> Self {
+        let topics = topics.iter().map(|topic| topic.to_string()).collect();
+        let (frames, commands) = supervise(&config, None, topics);
+        Self { frames, commands }
+    }
+
+    /// Subscribes to more topics, they are replayed on reconnection as well.
+    pub fn subscribe(&self, topics: Vec<BybitPublicTopic>) {
+        let topics = topics.iter().map(|topic| topic.to_string()).collect();
+        // the supervisor only stops once this client is dropped
+        let _ = self.commands.send(WsCommand::Subscribe(topics));
+    }
+
+    /// Stops the updates of `topics`, they are not replayed on reconnection
+    /// anymore.
+    pub fn unsubscribe(&self, topics: Vec<BybitPublicTopic>) {
+        let topics = topics.iter().map(|topic| topic.to_string()).collect();
+        let _ = self.commands.send(WsCommand::Unsubscribe(topics));
+    }
+
+    /// Unsubscribes and subscribes again, e.g. to get a new orderbook
+    /// snapshot, which Bybit only sends on subscription.
+    pub fn resubscribe(&self, topics: Vec<BybitPublicTopic>) {
+        self.unsubscribe(topics.clone());
+        self.subscribe(topics);
+    }
+
+    /// Returns the next update or connection state change, `None` once the
+    /// background task has stopped.
+    pub async fn next(&mut self) -> Option<BybitPublicWsEvent> {
+        loop {
+            match self.frames.recv().await? {
+                WsFrame::State(state) => return Some(BybitPublicWsEvent::State(state)),
+                WsFrame::Text(text) => match serde_json::from_str::<BybitPublicWsUpdate>(&text) {
+                    Ok(update) => return Some(BybitPublicWsEvent::Update(update)),
+                    Err(err) => error!("error deserializing {text}: {err}"),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+    use super::*;
+    use crate::ws_types::{BybitPublicData, ConnectionState};
+
+    #[test]
+    fn test_public_url() {
+        assert_eq!(
+            WsConfig::public(BybitCategory::Linear).url,
+            "wss://stream.bybit.com/v5/public/linear"
+        );
+        assert_eq!(
+            WsConfig::public(BybitCategory::Option).url,
+            "wss://stream.bybit.com/v5/public/option"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_public_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = WsConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            ..WsConfig::public(BybitCategory::Linear)
+        };
+        let mut ws = BybitPublicWs::with_config(
+            vec![BybitPublicTopic::PublicTrade("BTCUSDT".to_string())],
+            config,
+        );
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = accept_async(stream).await.unwrap();
+        // no auth on public streams, the subscription comes first
+        let Some(Ok(Message::Text(subscribe))) = server.next().await else {
+            panic!("expected a subscribe message");
+        };
+        let subscribe: serde_json::Value = serde_json::from_str(&subscribe).unwrap();
+        assert_eq!(subscribe["op"], "subscribe");
+        assert_eq!(subscribe["args"][0], "publicTrade.BTCUSDT");
+
+        server
+            .send(Message::Text(r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Sell","v":"0.001","p":"16578.50","L":"MinusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false}]}"#.to_string()))
+            .await
+            .unwrap();
+
+        let events = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut events = vec![];
+            while events.len() < 2 {
+                events.push(ws.next().await.unwrap());
+            }
+            events
+        })
+        .await
+        .unwrap();
+        assert!(matches!(
+            events[0],
+            BybitPublicWsEvent::State(ConnectionState::Connected)
+        ));
+        match &events[1] {
+            BybitPublicWsEvent::Update(BybitPublicWsUpdate::Data(update)) => {
+                assert!(matches!(update.data, BybitPublicData::PublicTrade(_)));
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    async fn next_message(server: &mut WebSocketStream<TcpStream>) -> serde_json::Value {
+        let Some(Ok(Message::Text(text))) = server.next().await else {
+            panic!("expected a text message");
+        };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = WsConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            ..WsConfig::public(BybitCategory::Linear)
+        };
+        let topic = BybitPublicTopic::PublicTrade("BTCUSDT".to_string());
+        let ws = BybitPublicWs::with_config(vec![topic.clone()], config);
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = accept_async(stream).await.unwrap();
+        assert_eq!(next_message(&mut server).await["op"], "subscribe");
+
+        ws.resubscribe(vec![topic.clone()]);
+        let unsubscribe = next_message(&mut server).await;
+        assert_eq!(unsubscribe["op"], "unsubscribe");
+        assert_eq!(unsubscribe["args"][0], "publicTrade.BTCUSDT");
+        let subscribe = next_message(&mut server).await;
+        assert_eq!(subscribe["op"], "subscribe");
+        assert_eq!(subscribe["args"][0], "publicTrade.BTCUSDT");
+
+        // topics not subscribed are not sent
+        ws.unsubscribe(vec![BybitPublicTopic::Tickers("ETHUSDT".to_string())]);
+        ws.unsubscribe(vec![topic]);
+        assert_eq!(
+            next_message(&mut server).await["args"][0],
+            "publicTrade.BTCUSDT"
+        );
+    }
+}
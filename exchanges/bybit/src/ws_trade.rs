@@ -0,0 +1,459 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, warn};
+
+use crate::{
+    clock::ServerClock,
+    config::Environment,
+    errors::{BybitError, BybitResult},
+    types::{AmendOrderRequest, CancelOrderRequest, OrderResponse, PlaceOrderRequest},
+    ws::{supervise, WsCommand, WsConfig, WsFrame},
+    ws_types::ConnectionState,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl WsConfig {
+    pub fn trade() -> Self {
+        Self {
+            url: Environment::Mainnet.ws_trade_url().to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TradeResponse {
+    req_id: Option<String>,
+    ret_code: i64,
+    ret_msg: String,
+    op: String,
+    #[serde(default)]
+    data: Value,
+    #[serde(default)]
+    ret_ext_info: Value,
+}
+
+#[derive(Deserialize)]
+struct BatchList<T> {
+    list: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct BatchStatus {
+    code: i64,
+    msg: String,
+}
+
+struct TradeRequest {
+    req_id: String,
+    message: String,
+    response: oneshot::Sender<BybitResult<TradeResponse>>,
+}
+
+/// Hands requests to the supervisor and routes the responses back by `reqId`.
+/// Requests still waiting when the connection drops fail right away, since
+/// their responses are lost with it.
+async fn route_responses(
+    mut frames: mpsc::UnboundedReceiver<WsFrame>,
+    commands: mpsc::UnboundedSender<WsCommand>,
+    mut requests: mpsc::UnboundedReceiver<TradeRequest>,
+) {
+    let mut pending: HashMap<String, oneshot::Sender<BybitResult<TradeResponse>>> = HashMap::new();
+    let mut connected = false;
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let Some(request) = request else {
+                    return;
+                };
+                if !connected {
+                    let _ = request
+                        .response
+                        .send(Err(BybitError::WsError("not connected".to_string())));
+                    continue;
+                }
+                // drops the requests whose caller timed out
+                pending.retain(|_, response| !response.is_closed());
+                pending.insert(request.req_id, request.response);
+                if commands.send(WsCommand::Send(request.message)).is_err() {
+                    return;
+                }
+            }
+            frame = frames.recv() => match frame {
+                Some(WsFrame::State(ConnectionState::Connected)) => connected = true,
+                Some(WsFrame::State(ConnectionState::Disconnected(reason))) => {
+                    connected = false;
+                    for (_, response) in pending.drain() {
+                        let err = BybitError::WsError(format!("connection lost: {reason}"));
+                        let _ = response.send(Err(err));
+                    }
+                }
+                Some(WsFrame::State(ConnectionState::Reconnecting { .. })) => {}
+                Some(WsFrame::Text(text)) => match serde_json::from_str::<TradeResponse>(&text) {
+                    Ok(response) => {
+                        let request = response.req_id.as_ref().and_then(|id| pending.remove(id));
+                        match request {
+                            Some(request) => {
+                                let _ = request.send(Ok(response));
+                            }
+                            None if response.ret_code != 0 => {
+                                error!("bybit trade {} failed: {}", response.op, response.ret_msg)
+                            }
+                            None => {}
+                        }
+                    }
+                    Err(err) => warn!("error deserializing {text}: {err}"),
+                },
+                None => return,
+            },
+        }
+    }
+}
+
+fn to_params<T: Serialize>(request: &T) -> BybitResult<Map<String, Value>> {
+    match serde_json::to_value(request)? {
+        Value::Object(params) => Ok(params),
+        _ => unreachable!("requests serialize to an object"),
+    }
+}
+
+/// Batch args take the category once, next to the list of requests.
+fn to_batch_params<T: Serialize>(requests: &[T]) -> BybitResult<Map<String, Value>> {
+    let mut category = Value::Null;
+    let mut list = Vec::with_capacity(requests.len());
+    for request in requests {
+        let mut params = to_params(request)?;
+        if let Some(request_category) = params.remove("category") {
+            category = request_category;
+        }
+        list.push(Value::Object(params));
+    }
+    let mut params = Map::new();
+    params.insert("category".to_string(), category);
+    params.insert("request".to_string(), Value::Array(list));
+    Ok(params)
+}
+
+/// Order entry over the trade WebSocket, with lower latency than REST. The
+/// connection is authenticated and supervised like `BybitWs`, responses are
+/// matched to requests by `reqId`.
+pub struct BybitWsTrade {
+    requests: mpsc::UnboundedSender<TradeRequest>,
+    clock: Arc<ServerClock>,
+    recv_window: String,
+    next_req_id: AtomicU64,
+    request_timeout: Duration,
+}
+
+impl BybitWsTrade {
+    pub fn new(api_key: String, api_secret: String) -> Self {
+        Self::with_config(api_key, api_secret, WsConfig::trade())
+    }
+
+    pub fn with_config(api_key: String, api_secret: String, config: WsConfig) -> Self {
+        let clock = config.clock.clone();
+        let recv_window = config.recv_window.as_millis().to_string();
+        let (frames, commands) = supervise(&config, Some((api_key, api_secret)), vec![]);
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+        tokio::spawn(route_responses(frames, commands, requests_rx));
+        Self {
+            requests: requests_tx,
+            clock,
+            recv_window,
+            next_req_id: AtomicU64::new(1),
+            request_timeout: REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Time to wait for a response, 5 seconds by default.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    async fn send(&self, op: &str, params: Map<String, Value>) -> BybitResult<TradeResponse> {
+        let req_id = self.next_req_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let message = json!({
+            "reqId": req_id,
+            "header": {
+                "X-BAPI-TIMESTAMP": self.clock.now_ms().to_string(),
+                "X-BAPI-RECV-WINDOW": self.recv_window,
+            },
+            "op": op,
+            "args": [params],
+        })
+        .to_string();
+
+        let (response_tx, response_rx) = oneshot::channel();
+        let request = TradeRequest {
+            req_id,
+            message,
+            response: response_tx,
+        };
+        if self.requests.send(request).is_err() {
+            return Err(BybitError::WsError("connection task stopped".to_string()));
+        }
+        let response = match tokio::time::timeout(self.request_timeout, response_rx).await {
+            Ok(Ok(response)) => response?,
+            Ok(Err(_)) => return Err(BybitError::WsError("connection task stopped".to_string())),
+            Err(_) => {
+                return Err(BybitError::Timeout(format!(
+                    "no response to {op} within {:?}",
+                    self.request_timeout
+                )))
+            }
+        };
+        if response.ret_code != 0 {
+            return Err(BybitError::ApiError(response.ret_code, response.ret_msg));
+        }
+        Ok(response)
+    }
+
+    async fn send_order(&self, op: &str, params: Map<String, Value>) -> BybitResult<OrderResponse> {
+        let response = self.send(op, params).await?;
+        Ok(serde_json::from_value(response.data)?)
+    }
+
+    /// Returns one result per request, in the order of the requests.
+    async fn send_batch(
+        &self,
+        op: &str,
+        params: Map<String, Value>,
+    ) -> BybitResult<Vec<BybitResult<OrderResponse>>> {
+        let response = self.send(op, params).await?;
+        let orders = serde_json::from_value::<BatchList<OrderResponse>>(response.data)?.list;
+        let statuses =
+            serde_json::from_value::<BatchList<BatchStatus>>(response.ret_ext_info)?.list;
+        Ok(orders
+            .into_iter()
+            .zip(statuses)
+            .map(|(order, status)| match status.code {
+                0 => Ok(order),
+                code => Err(BybitError::ApiError(code, status.msg)),
+            })
+            .collect())
+    }
+
+    pub async fn create_order(&self, request: &PlaceOrderRequest) -> BybitResult<OrderResponse> {
+        self.send_order("order.create", to_params(request)?).await
+    }
+
+    pub async fn amend_order(&self, request: &AmendOrderRequest) -> BybitResult<OrderResponse> {
+        self.send_order("order.amend", to_params(request)?).await
+    }
+
+    pub async fn cancel_order(&self, request: &CancelOrderRequest) -> BybitResult<OrderResponse> {
+        self.send_order("order.cancel", to_params(request)?).await
+    }
+
+    /// All requests must share the category. Up to 20 orders per batch on
+    /// linear contracts.
+    pub async fn create_batch_orders(
+        &self,
+        requests: &[PlaceOrderRequest],
+    ) -> BybitResult<Vec<BybitResult<OrderResponse>>> {
+        self.send_batch("order.create-batch", to_batch_params(requests)?)
+            .await
+    }
+
+    pub async fn amend_batch_orders(
+        &self,
+        requests: &[AmendOrderRequest],
+    ) -> BybitResult<Vec<BybitResult<OrderResponse>>> {
+        self.send_batch("order.amend-batch", to_batch_params(requests)?)
+            .await
+    }
+
+    pub async fn cancel_batch_orders(
+        &self,
+        requests: &[CancelOrderRequest],
+    ) -> BybitResult<Vec<BybitResult<OrderResponse>>> {
+        self.send_batch("order.cancel-batch", to_batch_params(requests)?)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+    use super::*;
+    use crate::types::{BybitOrderRef, BybitOrderType, BybitSide};
+
+    async fn local_trade_ws() -> (TcpListener, BybitWsTrade) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = WsConfig {
+            url: format!("ws://{}", listener.local_addr().unwrap()),
+            recv_window: Duration::from_secs(10),
+            ..WsConfig::trade()
+        };
+        let ws = BybitWsTrade::with_config("key".to_string(), "secret".to_string(), config)
+            .request_timeout(Duration::from_millis(300));
+        (listener, ws)
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = accept_async(stream).await.unwrap();
+        assert_eq!(next_request(&mut server).await["op"], "auth");
+        server
+            .send(Message::Text(
+                r#"{"retCode":0,"retMsg":"OK","op":"auth","connId":"cnj7ulkfpa38vo3ucsjg-5"}"#
+                    .to_string(),
+            ))
+            .await
+            .unwrap();
+        server
+    }
+
+    async fn next_request(server: &mut WebSocketStream<TcpStream>) -> Value {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Waits until the client saw the connection, requests fail fast before.
+    async fn wait_connected(ws: &BybitWsTrade) {
+        let request = CancelOrderRequest::new("BTCUSDT", BybitOrderRef::OrderId("1".to_string()));
+        for _ in 0..50 {
+            match ws.cancel_order(&request).await {
+                Err(BybitError::WsError(msg)) if msg == "not connected" => {
+                    tokio::time::sleep(Duration::from_millis(10)).await
+                }
+                _ => return,
+            }
+        }
+        panic!("not connected");
+    }
+
+    #[tokio::test]
+    async fn test_create_order() {
+        let (listener, ws) = local_trade_ws().await;
+        let server = tokio::spawn(async move {
+            let mut server = accept(&listener).await;
+            // answers the probe of `wait_connected` and the order
+            for _ in 0..2 {
+                let request = next_request(&mut server).await;
+                let response = match request["op"].as_str().unwrap() {
+                    "order.cancel" => json!({
+                        "reqId": request["reqId"],
+                        "retCode": 110001,
+                        "retMsg": "order not exists or too late to cancel",
+                        "op": "order.cancel",
+                        "data": {},
+                        "retExtInfo": {},
+                        "header": {},
+                        "connId": "cnj7ulkfpa38vo3ucsjg-5"
+                    }),
+                    _ => {
+                        assert_eq!(request["args"][0]["symbol"], "BTCUSDT");
+                        assert_eq!(request["args"][0]["orderType"], "Limit");
+                        assert!(request["header"]["X-BAPI-TIMESTAMP"].is_string());
+                        assert_eq!(request["header"]["X-BAPI-RECV-WINDOW"], "10000");
+                        json!({
+                            "reqId": request["reqId"],
+                            "retCode": 0,
+                            "retMsg": "OK",
+                            "op": "order.create",
+                            "data": {
+                                "orderId": "1321003749386327552",
+                                "orderLinkId": "spot-test-postonly"
+                            },
+                            "retExtInfo": {},
+                            "header": {},
+                            "connId": "cnj7ulkfpa38vo3ucsjg-5"
+                        })
+                    }
+                };
+                server
+                    .send(Message::Text(response.to_string()))
+                    .await
+                    .unwrap();
+            }
+            server
+        });
+
+        wait_connected(&ws).await;
+        let request =
+            PlaceOrderRequest::new("BTCUSDT", BybitSide::Buy, BybitOrderType::Limit, "0.001")
+                .price("60000")
+                .order_link_id("spot-test-postonly");
+        let response = ws.create_order(&request).await.unwrap();
+        assert_eq!(response.order_id, "1321003749386327552");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_orders() {
+        let (listener, ws) = local_trade_ws().await;
+        tokio::spawn(async move {
+            let mut server = accept(&listener).await;
+            loop {
+                let request = next_request(&mut server).await;
+                if request["op"] != "order.cancel-batch" {
+                    continue;
+                }
+                assert_eq!(request["args"][0]["category"], "linear");
+                assert!(request["args"][0]["request"][0].get("category").is_none());
+                let response = json!({
+                    "reqId": request["reqId"],
+                    "retCode": 0,
+                    "retMsg": "OK",
+                    "op": "order.cancel-batch",
+                    "data": {"list": [
+                        {"category": "linear", "symbol": "BTCUSDT", "orderId": "1", "orderLinkId": ""},
+                        {"category": "linear", "symbol": "BTCUSDT", "orderId": "", "orderLinkId": ""}
+                    ]},
+                    "retExtInfo": {"list": [
+                        {"code": 0, "msg": "OK"},
+                        {"code": 110001, "msg": "order not exists or too late to cancel"}
+                    ]},
+                    "header": {},
+                    "connId": "cnj7ulkfpa38vo3ucsjg-5"
+                });
+                server
+                    .send(Message::Text(response.to_string()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        wait_connected(&ws).await;
+        let requests = ["1", "2"]
+            .map(|id| CancelOrderRequest::new("BTCUSDT", BybitOrderRef::OrderId(id.to_string())));
+        let results = ws.cancel_batch_orders(&requests).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().order_id, "1");
+        assert!(matches!(results[1], Err(BybitError::ApiError(110001, _))));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let (listener, ws) = local_trade_ws().await;
+        let _server = accept(&listener).await;
+
+        wait_connected(&ws).await;
+        let request =
+            CancelOrderRequest::new("BTCUSDT", BybitOrderRef::OrderLinkId("a".to_string()));
+        match ws.cancel_order(&request).await {
+            Err(BybitError::Timeout(msg)) => assert!(msg.contains("order.cancel")),
+            res => panic!("unexpected result {res:?}"),
+        }
+    }
+}
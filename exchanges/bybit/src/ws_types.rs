@@ -1,64 +1,335 @@
 use serde::{Deserialize, Serialize};
 
-use crate::types::{BybitLinearPosition, BybitOrderStatus, BybitSide};
+use crate::types::{BybitLinearPosition, BybitSide, GetOrderResponseData, WalletBalance};
 
+pub use cefi_rs_common::ws::ConnectionState;
+
+/// Public stream topics, formatted as expected by `subscribe`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BybitPublicTopic {
+    /// Depth 1, 50, 200 or 500 for derivatives, 1, 50 or 200 for spot.
+    Orderbook {
+        depth: u32,
+        symbol: String,
+    },
+    PublicTrade(String),
+    Tickers(String),
+    /// Interval as in the REST api: 1, 3, 5, 15, 30, 60, 120, 240, 360, 720, D, W or M.
+    Kline {
+        interval: String,
+        symbol: String,
+    },
+    Liquidation(String),
+}
+
+impl std::fmt::Display for BybitPublicTopic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BybitPublicTopic::Orderbook { depth, symbol } => {
+                write!(f, "orderbook.{depth}.{symbol}")
+            }
+            BybitPublicTopic::PublicTrade(symbol) => write!(f, "publicTrade.{symbol}"),
+            BybitPublicTopic::Tickers(symbol) => write!(f, "tickers.{symbol}"),
+            BybitPublicTopic::Kline { interval, symbol } => write!(f, "kline.{interval}.{symbol}"),
+            BybitPublicTopic::Liquidation(symbol) => write!(f, "liquidation.{symbol}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BybitWsEvent {
+    Update(BybitUpdateData),
+    Authenticated,
+    /// The credentials were rejected, the connection stays open without
+    /// private data.
+    AuthFailed(String),
+    /// Answer to a subscribe request, `success` is false when a topic was
+    /// rejected.
+    Subscribed(OpResponse),
+    State(ConnectionState),
+}
+
+/// Any message of the private stream.
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
 pub enum BybitWsUpdate {
-    AuthResponse(OpResponse),
-    BybitUpdateData(BybitUpdateData),
+    OpResponse(OpResponse),
     Pong(Pong),
+    BybitUpdateData(BybitUpdateData),
 }
 
+/// Answer to a ping.
 #[derive(Deserialize, Debug)]
 pub struct Pong {
-    pub success: bool,
-    pub ret_msg: String,
+    pub req_id: Option<String>,
     pub op: String,
+    pub args: Vec<String>,
     pub conn_id: String,
 }
 
+/// Answer to `auth` and `subscribe`.
 #[derive(Deserialize, Debug)]
 pub struct OpResponse {
+    pub success: bool,
+    pub ret_msg: String,
     pub op: String,
-    pub args: Vec<String>,
     pub conn_id: String,
+    pub req_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct RawPrivateUpdate {
+    topic: String,
+    data: serde_json::Value,
+}
+
+/// Private stream message, by topic. The all-in-one topics (`order`,
+/// `execution`, `position`) carry every category, the category of an item is
+/// in its data. Topics without a dedicated variant are kept in `Other`.
 #[derive(Deserialize, Debug)]
-#[serde(tag = "topic", content = "data")]
+#[serde(try_from = "RawPrivateUpdate")]
 pub enum BybitUpdateData {
-    #[serde(rename = "order.linear")]
-    OrderLinear(Vec<BybitOrderLinearData>),
-    #[serde(rename = "execution.linear")]
-    ExecutionLinear(Vec<BybitExecutionLinearData>),
-    #[serde(rename = "position.linear")]
+    Order(Vec<BybitOrderData>),
+    OrderSpot(Vec<BybitOrderData>),
+    OrderLinear(Vec<BybitOrderData>),
+    OrderInverse(Vec<BybitOrderData>),
+    OrderOption(Vec<BybitOrderData>),
+    Execution(Vec<BybitExecutionData>),
+    ExecutionSpot(Vec<BybitExecutionData>),
+    ExecutionLinear(Vec<BybitExecutionData>),
+    ExecutionInverse(Vec<BybitExecutionData>),
+    ExecutionOption(Vec<BybitExecutionData>),
+    /// Any of the `execution.fast` topics.
+    ExecutionFast(Vec<BybitFastExecutionData>),
+    Position(Vec<BybitLinearPosition>),
     PositionLinear(Vec<BybitLinearPosition>),
+    PositionInverse(Vec<BybitLinearPosition>),
+    PositionOption(Vec<BybitLinearPosition>),
+    Wallet(Vec<WalletBalance>),
+    Greeks(Vec<BybitGreeksData>),
+    Other {
+        topic: String,
+        data: serde_json::Value,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl TryFrom<RawPrivateUpdate> for BybitUpdateData {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawPrivateUpdate) -> Result<Self, Self::Error> {
+        let data = raw.data;
+        Ok(match raw.topic.as_str() {
+            "order" => BybitUpdateData::Order(serde_json::from_value(data)?),
+            "order.spot" => BybitUpdateData::OrderSpot(serde_json::from_value(data)?),
+            "order.linear" => BybitUpdateData::OrderLinear(serde_json::from_value(data)?),
+            "order.inverse" => BybitUpdateData::OrderInverse(serde_json::from_value(data)?),
+            "order.option" => BybitUpdateData::OrderOption(serde_json::from_value(data)?),
+            "execution" => BybitUpdateData::Execution(serde_json::from_value(data)?),
+            "execution.spot" => BybitUpdateData::ExecutionSpot(serde_json::from_value(data)?),
+            "execution.linear" => BybitUpdateData::ExecutionLinear(serde_json::from_value(data)?),
+            "execution.inverse" => BybitUpdateData::ExecutionInverse(serde_json::from_value(data)?),
+            "execution.option" => BybitUpdateData::ExecutionOption(serde_json::from_value(data)?),
+            topic if topic == "execution.fast" || topic.starts_with("execution.fast.") => {
+                BybitUpdateData::ExecutionFast(serde_json::from_value(data)?)
+            }
+            "position" => BybitUpdateData::Position(serde_json::from_value(data)?),
+            "position.linear" => BybitUpdateData::PositionLinear(serde_json::from_value(data)?),
+            "position.inverse" => BybitUpdateData::PositionInverse(serde_json::from_value(data)?),
+            "position.option" => BybitUpdateData::PositionOption(serde_json::from_value(data)?),
+            "wallet" => BybitUpdateData::Wallet(serde_json::from_value(data)?),
+            "greeks" => BybitUpdateData::Greeks(serde_json::from_value(data)?),
+            _ => BybitUpdateData::Other {
+                topic: raw.topic,
+                data,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum BybitPublicWsEvent {
+    Update(BybitPublicWsUpdate),
+    State(ConnectionState),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum BybitPublicWsUpdate {
+    OpResponse(PublicOpResponse),
+    Data(BybitPublicUpdate),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PublicOpResponse {
+    pub success: bool,
+    pub ret_msg: String,
+    pub op: String,
+    pub conn_id: String,
+    pub req_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BybitUpdateType {
+    Snapshot,
+    Delta,
+}
+
+#[derive(Deserialize)]
+struct RawPublicUpdate {
+    topic: String,
+    #[serde(rename = "type")]
+    update_type: Option<BybitUpdateType>,
+    ts: i64,
+    data: serde_json::Value,
+}
+
+/// Public stream message. The payload depends on the topic prefix, since the
+/// topic also carries the symbol it cannot be used as a serde tag.
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "RawPublicUpdate")]
+pub struct BybitPublicUpdate {
+    pub topic: String,
+    pub update_type: BybitUpdateType,
+    pub ts: i64,
+    pub data: BybitPublicData,
+}
+
+impl TryFrom<RawPublicUpdate> for BybitPublicUpdate {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawPublicUpdate) -> Result<Self, Self::Error> {
+        let prefix = raw.topic.split('.').next().unwrap_or_default();
+        let data = match prefix {
+            "orderbook" => BybitPublicData::Orderbook(serde_json::from_value(raw.data)?),
+            "publicTrade" => BybitPublicData::PublicTrade(serde_json::from_value(raw.data)?),
+            "tickers" => BybitPublicData::Ticker(Box::new(serde_json::from_value(raw.data)?)),
+            "kline" => BybitPublicData::Kline(serde_json::from_value(raw.data)?),
+            "liquidation" => BybitPublicData::Liquidation(serde_json::from_value(raw.data)?),
+            _ => {
+                return Err(serde::de::Error::custom(format!(
+                    "unknown topic {}",
+                    raw.topic
+                )))
+            }
+        };
+        Ok(Self {
+            topic: raw.topic,
+            update_type: raw.update_type.unwrap_or(BybitUpdateType::Snapshot),
+            ts: raw.ts,
+            data,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum BybitPublicData {
+    Orderbook(BybitOrderbookData),
+    PublicTrade(Vec<BybitPublicTradeData>),
+    Ticker(Box<BybitTickerData>),
+    Kline(Vec<BybitKlineData>),
+    Liquidation(BybitLiquidationData),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BybitOrderbookData {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// Bids as `[price, size]`, a size of 0 removes the level in a delta.
+    #[serde(rename = "b")]
+    pub bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    pub asks: Vec<[String; 2]>,
+    #[serde(rename = "u")]
+    pub update_id: i64,
+    pub seq: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BybitPublicTradeData {
+    #[serde(rename = "T")]
+    pub timestamp: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// Taker side.
+    #[serde(rename = "S")]
+    pub side: BybitSide,
+    #[serde(rename = "v")]
+    pub size: String,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "L")]
+    pub tick_direction: Option<String>,
+    #[serde(rename = "i")]
+    pub trade_id: String,
+    #[serde(rename = "BT")]
+    pub block_trade: bool,
+}
+
+/// Ticker of any category. Deltas only carry the fields that changed, so all
+/// fields but the symbol are optional.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct BybitOrderLinearData {
-    pub category: String,
+pub struct BybitTickerData {
+    pub symbol: String,
+    pub last_price: Option<String>,
+    pub mark_price: Option<String>,
+    pub index_price: Option<String>,
+    pub prev_price24h: Option<String>,
+    pub price24h_pcnt: Option<String>,
+    pub high_price24h: Option<String>,
+    pub low_price24h: Option<String>,
+    pub volume24h: Option<String>,
+    pub turnover24h: Option<String>,
+    pub open_interest: Option<String>,
+    pub open_interest_value: Option<String>,
+    pub funding_rate: Option<String>,
+    pub next_funding_time: Option<String>,
+    pub bid1_price: Option<String>,
+    pub bid1_size: Option<String>,
+    pub ask1_price: Option<String>,
+    pub ask1_size: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitKlineData {
+    pub start: i64,
+    pub end: i64,
+    pub interval: String,
+    pub open: String,
+    pub close: String,
+    pub high: String,
+    pub low: String,
+    pub volume: String,
+    pub turnover: String,
+    /// Whether the candle is closed.
+    pub confirm: bool,
+    pub timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitLiquidationData {
+    pub updated_time: i64,
     pub symbol: String,
-    pub order_id: String,
-    pub order_link_id: String,
     pub side: BybitSide,
+    pub size: String,
     pub price: String,
-    pub qty: String,
-    pub order_status: BybitOrderStatus,
-    pub create_type: String,
-    pub cancel_type: String,
-    pub reject_reason: String,
-    pub leaves_qty: String,
-    pub cum_exec_qty: String,
-    pub created_time: String,
-    pub updated_time: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitOrderData {
+    pub category: String,
+    /// Same fields as the orders of the REST api.
+    #[serde(flatten)]
+    pub order: GetOrderResponseData,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct BybitExecutionLinearData {
+pub struct BybitExecutionData {
     pub category: String,
     pub symbol: String,
     pub order_id: String,
@@ -80,6 +351,34 @@ pub struct BybitExecutionLinearData {
     pub mark_price: String,
 }
 
+/// Lighter execution stream, only trades of the account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitFastExecutionData {
+    pub category: String,
+    pub symbol: String,
+    pub order_id: String,
+    pub order_link_id: String,
+    pub side: BybitSide,
+    pub exec_id: String,
+    pub exec_price: String,
+    pub exec_qty: String,
+    pub exec_time: String,
+    pub is_maker: bool,
+    pub seq: i64,
+}
+
+/// Option greeks of the account, per base coin.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitGreeksData {
+    pub base_coin: String,
+    pub total_delta: String,
+    pub total_gamma: String,
+    pub total_vega: String,
+    pub total_theta: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,8 +388,8 @@ mod tests {
         let json_data = r#"
         {"req_id":"100001","op":"pong","args":["1727434943071"],"conn_id":"cmjonqvavkfduu60h6d0-231ex0"}
         "#;
-        let r = serde_json::from_str::<BybitWsUpdate>(&json_data).unwrap();
-        println!("{:?}", r);
+        let r = serde_json::from_str::<BybitWsUpdate>(json_data).unwrap();
+        assert!(matches!(r, BybitWsUpdate::Pong(_)));
     }
 
     #[test]
@@ -98,8 +397,81 @@ mod tests {
         let json_data = r#"
         {"success":true,"ret_msg":"","op":"auth","conn_id":"cmjoqsm8dkqdvjssdqvg-232sm4"}
         "#;
-        let r = serde_json::from_str::<BybitWsUpdate>(&json_data).unwrap();
-        println!("{:?}", r);
+        let r = serde_json::from_str::<BybitWsUpdate>(json_data).unwrap();
+        assert!(matches!(r, BybitWsUpdate::OpResponse(ref op) if op.success && op.op == "auth"));
+    }
+
+    #[test]
+    fn test_public_topic() {
+        let topic = BybitPublicTopic::Orderbook {
+            depth: 50,
+            symbol: "BTCUSDT".to_string(),
+        };
+        assert_eq!(topic.to_string(), "orderbook.50.BTCUSDT");
+        let topic = BybitPublicTopic::Kline {
+            interval: "5".to_string(),
+            symbol: "BTCUSDT".to_string(),
+        };
+        assert_eq!(topic.to_string(), "kline.5.BTCUSDT");
+    }
+
+    #[test]
+    fn test_deserialize_public_orderbook() {
+        let json_data = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"],["30245.40","0.224"]],"a":[["30248.70","0"]],"u":177400507,"seq":66544703342},"cts":1687940967464}"#;
+        let r = serde_json::from_str::<BybitPublicWsUpdate>(json_data).unwrap();
+        match r {
+            BybitPublicWsUpdate::Data(update) => {
+                assert_eq!(update.update_type, BybitUpdateType::Delta);
+                match update.data {
+                    BybitPublicData::Orderbook(book) => {
+                        assert_eq!(book.update_id, 177400507);
+                        assert_eq!(book.asks[0], ["30248.70".to_string(), "0".to_string()]);
+                    }
+                    data => panic!("unexpected data {data:?}"),
+                }
+            }
+            r => panic!("unexpected update {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_public_trade() {
+        let json_data = r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false}]}"#;
+        let r = serde_json::from_str::<BybitPublicUpdate>(json_data).unwrap();
+        assert!(
+            matches!(r.data, BybitPublicData::PublicTrade(ref trades) if trades[0].side == BybitSide::Buy)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_public_ticker() {
+        let json_data = r#"{"topic":"tickers.BTCUSDT","type":"delta","data":{"symbol":"BTCUSDT","price24hPcnt":"-0.017654","markPrice":"28542.57","indexPrice":"28549.74","openInterestValue":"1513722587.29","bid1Price":"28542.50","bid1Size":"0.001","ask1Price":"28542.60","ask1Size":"2.497"},"cs":24987956059,"ts":1673272861686}"#;
+        let r = serde_json::from_str::<BybitPublicUpdate>(json_data).unwrap();
+        match r.data {
+            BybitPublicData::Ticker(ticker) => {
+                assert_eq!(ticker.mark_price.as_deref(), Some("28542.57"));
+                assert_eq!(ticker.last_price, None);
+            }
+            data => panic!("unexpected data {data:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_kline_and_liquidation() {
+        let json_data = r#"{"topic":"kline.5.BTCUSDT","data":[{"start":1672324800000,"end":1672325099999,"interval":"5","open":"16649.5","close":"16677","high":"16677","low":"16608","volume":"2.081","turnover":"34666.4005","confirm":false,"timestamp":1672324988882}],"ts":1672324988882,"type":"snapshot"}"#;
+        let r = serde_json::from_str::<BybitPublicUpdate>(json_data).unwrap();
+        assert!(matches!(r.data, BybitPublicData::Kline(ref klines) if !klines[0].confirm));
+
+        let json_data = r#"{"data":{"price":"0.03803","side":"Buy","size":"1637","symbol":"GALAUSDT","updatedTime":1673251091822},"topic":"liquidation.GALAUSDT","ts":1673251091822,"type":"snapshot"}"#;
+        let r = serde_json::from_str::<BybitPublicUpdate>(json_data).unwrap();
+        assert!(matches!(r.data, BybitPublicData::Liquidation(ref l) if l.size == "1637"));
+    }
+
+    #[test]
+    fn test_deserialize_public_subscribe_response() {
+        let json_data = r#"{"success":true,"ret_msg":"subscribe","conn_id":"2324d924-aa4d-45b0-a858-7b8be29ab52b","req_id":"10001","op":"subscribe"}"#;
+        let r = serde_json::from_str::<BybitPublicWsUpdate>(json_data).unwrap();
+        assert!(matches!(r, BybitPublicWsUpdate::OpResponse(ref op) if op.success));
     }
 
     #[test]
@@ -107,7 +479,52 @@ mod tests {
         let json_data = r#"
         {"topic":"order.linear","id":"62552242_SOLUSDT_149260954383","creationTime":1727433749096,"data":[{"category":"linear","symbol":"SOLUSDT","orderId":"f38c5e38-46ef-4a8a-b115-fd3392d4f144","orderLinkId":"","blockTradeId":"","side":"Buy","positionIdx":0,"orderStatus":"Cancelled","cancelType":"CancelByUser","rejectReason":"EC_PerCancelRequest","timeInForce":"GTC","isLeverage":"","price":"99","qty":"0.1","avgPrice":"","leavesQty":"0","leavesValue":"0","cumExecQty":"0","cumExecValue":"0","cumExecFee":"0","orderType":"Limit","stopOrderType":"","orderIv":"","triggerPrice":"","takeProfit":"","stopLoss":"","triggerBy":"","tpTriggerBy":"","slTriggerBy":"","triggerDirection":0,"placeType":"","lastPriceOnCreated":"156.17","closeOnTrigger":false,"reduceOnly":false,"smpGroup":0,"smpType":"None","smpOrderId":"","slLimitPrice":"0","tpLimitPrice":"0","tpslMode":"UNKNOWN","createType":"CreateByUser","marketUnit":"","createdTime":"1727433748480","updatedTime":"1727433749095","feeCurrency":""}]}
         "#;
-        let r = serde_json::from_str::<BybitWsUpdate>(&json_data).unwrap();
+        let r = serde_json::from_str::<BybitWsUpdate>(json_data).unwrap();
         println!("{:?}", r);
     }
+
+    #[test]
+    fn test_deserialize_wallet_and_greeks() {
+        let json_data = r#"{"id":"592324d2bce751-ad38-48eb-8f42-4671d1fb4d4e","topic":"wallet","creationTime":1700034722104,"data":[{"accountIMRate":"0","accountMMRate":"0","totalEquity":"10262.91335023","totalWalletBalance":"9684.46297164","totalMarginBalance":"9684.46297164","totalAvailableBalance":"9556.6056555","totalPerpUPL":"0","totalInitialMargin":"0","totalMaintenanceMargin":"0","coin":[{"coin":"BTC","equity":"0.00102964","usdValue":"36.70759517","walletBalance":"0.00102964","availableToWithdraw":"0.00102964","availableToBorrow":"","borrowAmount":"0","accruedInterest":"0","totalOrderIM":"","totalPositionIM":"","totalPositionMM":"","unrealisedPnl":"0","cumRealisedPnl":"-0.00000973","bonus":"0","collateralSwitch":true,"marginCollateral":true,"locked":"0","spotHedgingQty":"0"}],"accountLTV":"0","accountType":"UNIFIED"}]}"#;
+        let r = serde_json::from_str::<BybitWsUpdate>(json_data).unwrap();
+        match r {
+            BybitWsUpdate::BybitUpdateData(BybitUpdateData::Wallet(wallets)) => {
+                assert_eq!(wallets[0].coin[0].wallet_balance, "0.00102964")
+            }
+            r => panic!("unexpected update {r:?}"),
+        }
+
+        let json_data = r#"{"id":"592324fa945a30-2603-49a5-b865-21668c29f2a6","topic":"greeks","creationTime":1672364262482,"data":[{"baseCoin":"ETH","totalDelta":"0.06999986","totalGamma":"-0.00000001","totalVega":"-0.00000024","totalTheta":"0.00001314"}]}"#;
+        let r = serde_json::from_str::<BybitUpdateData>(json_data).unwrap();
+        assert!(matches!(r, BybitUpdateData::Greeks(ref greeks) if greeks[0].base_coin == "ETH"));
+    }
+
+    #[test]
+    fn test_deserialize_category_topics() {
+        let json_data = r#"{"topic":"execution.fast.linear","creationTime":1716800399338,"data":[{"category":"linear","symbol":"ICPUSDT","execId":"3510f361-0add-5c7b-a2e7-9679810944fc","execPrice":"12.015","execQty":"3000","orderId":"443d63fa-b4c3-4297-b7b1-23bca88b04dc","isMaker":false,"orderLinkId":"test-00001","side":"Sell","execTime":"1716800399334","seq":34771365464}]}"#;
+        let r = serde_json::from_str::<BybitUpdateData>(json_data).unwrap();
+        assert!(matches!(r, BybitUpdateData::ExecutionFast(ref e) if e[0].exec_qty == "3000"));
+
+        let json_data = r#"{"topic":"order.spot","id":"62552242_BTCUSDT_149260954383","creationTime":1727433749096,"data":[{"category":"spot","symbol":"BTCUSDT","orderId":"1321052653536515584","orderLinkId":"","side":"Buy","orderStatus":"New","cancelType":"UNKNOWN","rejectReason":"EC_NoError","price":"60000","qty":"0.001","leavesQty":"0.001","cumExecQty":"0","createType":"CreateByUser","createdTime":"1727433748480","updatedTime":"1727433748481"}]}"#;
+        let r = serde_json::from_str::<BybitUpdateData>(json_data).unwrap();
+        assert!(matches!(r, BybitUpdateData::OrderSpot(ref o) if o[0].category == "spot"));
+    }
+
+    #[test]
+    fn test_deserialize_unknown_topic() {
+        let json_data =
+            r#"{"topic":"dcp","data":[{"product":"OPTIONS","dcpStatus":"ON","timeWindow":10}]}"#;
+        let r = serde_json::from_str::<BybitWsUpdate>(json_data).unwrap();
+        match r {
+            BybitWsUpdate::BybitUpdateData(BybitUpdateData::Other { topic, data }) => {
+                assert_eq!(topic, "dcp");
+                assert_eq!(data[0]["dcpStatus"], "ON");
+            }
+            r => panic!("unexpected update {r:?}"),
+        }
+
+        // a known topic with an unexpected payload is still an error
+        let json_data = r#"{"topic":"wallet","data":[{"coin":"BTC"}]}"#;
+        assert!(serde_json::from_str::<BybitUpdateData>(json_data).is_err());
+    }
 }
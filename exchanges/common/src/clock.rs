@@ -0,0 +1,97 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::Utc;
+
+/// Number of recent samples the offset is picked from.
+const MAX_SAMPLES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ClockSample {
+    offset_ms: i64,
+    rtt_ms: i64,
+}
+
+/// Offset between the local clock and the venue clock, used to timestamp
+/// signed requests and WebSocket authentication.
+///
+/// Fed with server time samples by the `sync_clock` of the REST clients. The
+/// offset comes from the sample with the shortest round trip among the last
+/// few, the one least skewed by network delays.
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    samples: Mutex<VecDeque<ClockSample>>,
+}
+
+impl ServerClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sample of the server time `server_ms`, from a request sent at
+    /// `sent_ms` and answered at `received_ms` local time.
+    pub fn add_sample(&self, sent_ms: i64, server_ms: i64, received_ms: i64) {
+        let rtt_ms = (received_ms - sent_ms).max(0);
+        let sample = ClockSample {
+            // the server answered halfway through the round trip
+            offset_ms: server_ms - (sent_ms + rtt_ms / 2),
+            rtt_ms,
+        };
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    fn best_sample(&self) -> Option<ClockSample> {
+        let samples = self.samples.lock().unwrap();
+        samples.iter().min_by_key(|sample| sample.rtt_ms).copied()
+    }
+
+    /// Server time minus local time, 0 until the first sample.
+    pub fn offset_ms(&self) -> i64 {
+        self.best_sample().map_or(0, |sample| sample.offset_ms)
+    }
+
+    /// Round trip time of the sample the offset comes from.
+    pub fn rtt_ms(&self) -> Option<i64> {
+        self.best_sample().map(|sample| sample.rtt_ms)
+    }
+
+    /// Estimated server time in milliseconds.
+    pub fn now_ms(&self) -> i64 {
+        Utc::now().timestamp_millis() + self.offset_ms()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset() {
+        let clock = ServerClock::new();
+        assert_eq!(clock.offset_ms(), 0);
+        assert_eq!(clock.rtt_ms(), None);
+
+        // server 1s ahead, 100ms round trip
+        clock.add_sample(10_000, 11_050, 10_100);
+        assert_eq!(clock.offset_ms(), 1000);
+        assert_eq!(clock.rtt_ms(), Some(100));
+
+        // a slow round trip does not replace a fast one
+        clock.add_sample(20_000, 21_900, 20_600);
+        assert_eq!(clock.offset_ms(), 1000);
+
+        clock.add_sample(30_000, 31_015, 30_030);
+        assert_eq!(clock.offset_ms(), 1000);
+        assert_eq!(clock.rtt_ms(), Some(30));
+
+        for i in 0..MAX_SAMPLES as i64 {
+            clock.add_sample(i * 1000, i * 1000 - 480, i * 1000 + 40);
+        }
+        assert_eq!(clock.offset_ms(), -500);
+        let now = Utc::now().timestamp_millis() - 500;
+        assert!((clock.now_ms() - now).abs() < 100);
+    }
+}
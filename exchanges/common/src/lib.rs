@@ -0,0 +1,4 @@
+pub mod clock;
+pub mod retry;
+pub mod transport;
+pub mod ws;
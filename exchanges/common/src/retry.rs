@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Retries of failed requests, with exponential backoff and jitter.
+///
+/// Only requests that are safe to repeat are retried, which ones is up to the
+/// client of each venue.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts after the first one, 0 disables retries.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every retry.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            min_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt per request.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry `attempt`, between half and all of the exponential
+    /// delay so that clients failing together do not retry together.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .min_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            min_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+        };
+        for _ in 0..20 {
+            let delay = policy.backoff(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+            let delay = policy.backoff(5);
+            assert!(delay >= Duration::from_millis(150) && delay <= Duration::from_millis(300));
+        }
+    }
+}
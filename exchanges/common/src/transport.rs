@@ -0,0 +1,222 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use reqwest::{header::HeaderMap, Client, Method, StatusCode, Url};
+
+/// Failure to get a response, each venue maps it into its own error.
+#[derive(Debug)]
+pub enum TransportError {
+    Http(reqwest::Error),
+    InvalidUrl(String),
+    /// No response queued in the `MockTransport`.
+    NoMockResponse(String),
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(value: reqwest::Error) -> Self {
+        TransportError::Http(value)
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Http(err) => write!(f, "http error: {err}"),
+            TransportError::InvalidUrl(msg) => write!(f, "invalid url {msg}"),
+            TransportError::NoMockResponse(msg) => write!(f, "no mock response for {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TransportError::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A signed request, ready to be sent.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Option<String>,
+}
+
+impl HttpRequest {
+    /// Path of the url without the leading slash, e.g. `v5/market/time`.
+    pub fn endpoint(&self) -> Result<String, TransportError> {
+        let url = Url::parse(&self.url)
+            .map_err(|err| TransportError::InvalidUrl(format!("{}: {err}", self.url)))?;
+        Ok(url.path().trim_start_matches('/').to_string())
+    }
+
+    /// Value of a query parameter of the url.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        let url = Url::parse(&self.url).ok()?;
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: StatusCode, body: &str) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Sends the requests of the REST clients. Errors are failures to get a
+/// response, any status is returned as a response.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+/// Sends requests over the network, the default transport.
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send().await?;
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.text().await?,
+        })
+    }
+}
+
+/// Serves canned responses instead of sending requests, to test the clients
+/// offline. Responses are queued per method and endpoint and served in order,
+/// the last one is repeated. The requests are recorded.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    responses: Mutex<HashMap<(Method, String), VecDeque<HttpResponse>>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response to `method` requests to `endpoint`, e.g.
+    /// `v5/market/time`.
+    pub fn push(&self, method: Method, endpoint: &str, response: HttpResponse) {
+        let mut responses = self.responses.lock().unwrap();
+        responses
+            .entry((method, endpoint.to_string()))
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Queues a 200 response with `body`, typically a recorded fixture.
+    pub fn push_ok(&self, method: Method, endpoint: &str, body: &str) {
+        self.push(method, endpoint, HttpResponse::new(StatusCode::OK, body));
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let key = (request.method.clone(), request.endpoint()?);
+        self.requests.lock().unwrap().push(request);
+
+        let mut responses = self.responses.lock().unwrap();
+        let queue = responses
+            .get_mut(&key)
+            .ok_or_else(|| TransportError::NoMockResponse(format!("{} {}", key.0, key.1)))?;
+        let response = match queue.len() {
+            1 => queue[0].clone(),
+            _ => queue.pop_front().expect("queues are never empty"),
+        };
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, url: &str) -> HttpRequest {
+        HttpRequest {
+            method,
+            url: url.to_string(),
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport() {
+        let transport = MockTransport::new();
+        transport.push(
+            Method::GET,
+            "v5/market/time",
+            HttpResponse::new(StatusCode::BAD_GATEWAY, ""),
+        );
+        transport.push_ok(Method::GET, "v5/market/time", "{}");
+
+        let url = "https://api.bybit.com/v5/market/time?category=linear";
+        let response = transport.send(request(Method::GET, url)).await.unwrap();
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+        for _ in 0..2 {
+            let response = transport.send(request(Method::GET, url)).await.unwrap();
+            assert_eq!(response.status, StatusCode::OK);
+            assert_eq!(response.body, "{}");
+        }
+        assert_eq!(
+            transport
+                .send(request(Method::POST, url))
+                .await
+                .unwrap_err()
+                .to_string(),
+            "no mock response for POST v5/market/time"
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].query_param("category").unwrap(), "linear");
+    }
+}
@@ -0,0 +1,402 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, sleep_until, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, warn};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// The socket is open, authentication and subscriptions are sent but not
+    /// acknowledged yet.
+    Connected,
+    Disconnected(String),
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+}
+
+/// Connection settings of a supervised stream, built from the config of the
+/// venue client.
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    pub url: String,
+    /// Interval between two heartbeats.
+    pub ping_interval: Duration,
+    /// Time allowed for the pong before the connection is considered dead.
+    pub pong_timeout: Duration,
+    /// Delay before the first reconnection attempt, doubled on every failure.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Age at which a connection is renewed, `None` keeps it open.
+    pub max_connection_age: Option<Duration>,
+}
+
+impl SupervisorConfig {
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.min_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Messages the supervisor sends on behalf of a venue.
+pub trait WsProtocol: Send + 'static {
+    /// Venue name in the logs.
+    fn name(&self) -> &'static str;
+
+    /// Messages sent first on every connection, e.g. the authentication.
+    fn on_connect(&mut self) -> Vec<String> {
+        vec![]
+    }
+
+    fn subscribe(&mut self, topics: &[String]) -> String;
+
+    fn unsubscribe(&mut self, topics: &[String]) -> String;
+
+    /// Heartbeat sent every `ping_interval`.
+    fn ping(&self) -> Message;
+
+    /// Whether a text message answers the heartbeat, it is not forwarded then.
+    fn is_pong(&self, _text: &str) -> bool {
+        false
+    }
+}
+
+pub enum WsCommand {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+    /// Sends a message as is, only while connected.
+    Send(String),
+    /// Closes the connection and reconnects to another url.
+    Reconnect(String),
+}
+
+pub enum WsFrame {
+    Text(String),
+    State(ConnectionState),
+}
+
+/// Why a connection ended.
+enum Closed {
+    /// Dropped or stopped answering, reconnected after a backoff.
+    Failed(String),
+    /// Closed on purpose to renew it, reconnected right away.
+    Renewed(String),
+}
+
+/// Owns the socket of a stream: authenticates and subscribes on every
+/// connection, keeps it alive with heartbeats and reconnects when it drops or
+/// stops answering. Connections reaching the age limit, or moved to another
+/// url, are renewed without a backoff. Runs until the receiving side of
+/// `frames` is dropped.
+pub struct WsSupervisor<P> {
+    config: SupervisorConfig,
+    protocol: P,
+    topics: Vec<String>,
+    frames: mpsc::UnboundedSender<WsFrame>,
+    commands: mpsc::UnboundedReceiver<WsCommand>,
+}
+
+impl<P: WsProtocol> WsSupervisor<P> {
+    pub fn spawn(
+        config: SupervisorConfig,
+        protocol: P,
+        topics: Vec<String>,
+    ) -> (
+        mpsc::UnboundedReceiver<WsFrame>,
+        mpsc::UnboundedSender<WsCommand>,
+    ) {
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let mut supervisor = Self {
+            config,
+            protocol,
+            topics: vec![],
+            frames: frames_tx,
+            commands: commands_rx,
+        };
+        supervisor.add_topics(topics);
+        tokio::spawn(supervisor.run());
+        (frames_rx, commands_tx)
+    }
+
+    /// Adds the topics not subscribed yet and returns them, venues may reject
+    /// a subscription to a topic twice.
+    fn add_topics(&mut self, topics: Vec<String>) -> Vec<String> {
+        let mut added = vec![];
+        for topic in topics {
+            if !self.topics.contains(&topic) {
+                self.topics.push(topic.clone());
+                added.push(topic);
+            }
+        }
+        added
+    }
+
+    /// Removes the subscribed topics among `topics` and returns them.
+    fn remove_topics(&mut self, topics: Vec<String>) -> Vec<String> {
+        let removed: Vec<String> = topics
+            .into_iter()
+            .filter(|topic| self.topics.contains(topic))
+            .collect();
+        self.topics.retain(|topic| !removed.contains(topic));
+        removed
+    }
+
+    fn emit_state(&self, state: ConnectionState) -> bool {
+        self.frames.send(WsFrame::State(state)).is_ok()
+    }
+
+    async fn run(mut self) {
+        let name = self.protocol.name();
+        let mut attempt = 0;
+        loop {
+            let reason = match connect_async(self.config.url.as_str()).await {
+                Ok((stream, _)) => {
+                    attempt = 0;
+                    match self.serve(stream).await {
+                        Some(Closed::Failed(reason)) => reason,
+                        Some(Closed::Renewed(reason)) => {
+                            // updates may still be missed between the two connections
+                            debug!("{name} ws renewed: {reason}");
+                            if !self.emit_state(ConnectionState::Disconnected(reason)) {
+                                return;
+                            }
+                            continue;
+                        }
+                        None => return,
+                    }
+                }
+                Err(err) => err.to_string(),
+            };
+            warn!("{name} ws disconnected: {reason}");
+            if !self.emit_state(ConnectionState::Disconnected(reason)) {
+                return;
+            }
+
+            attempt += 1;
+            let delay = self.config.backoff(attempt);
+            if !self.emit_state(ConnectionState::Reconnecting { attempt, delay }) {
+                return;
+            }
+            // subscriptions changed while disconnected apply to the next connection
+            let backoff = sleep(delay);
+            tokio::pin!(backoff);
+            loop {
+                tokio::select! {
+                    _ = &mut backoff => break,
+                    command = self.commands.recv() => match command {
+                        Some(WsCommand::Subscribe(topics)) => {
+                            self.add_topics(topics);
+                        }
+                        Some(WsCommand::Unsubscribe(topics)) => {
+                            self.remove_topics(topics);
+                        }
+                        Some(WsCommand::Send(_)) => debug!("{name} ws not connected, message dropped"),
+                        Some(WsCommand::Reconnect(url)) => {
+                            self.config.url = url;
+                            break;
+                        }
+                        None => return,
+                    },
+                    _ = self.frames.closed() => return,
+                }
+            }
+        }
+    }
+
+    /// Drives one connection, returns why it ended or `None` once nobody
+    /// listens anymore.
+    async fn serve(&mut self, stream: WsStream) -> Option<Closed> {
+        let expires_at = self
+            .config
+            .max_connection_age
+            .map(|age| Instant::now() + age);
+        let (mut write, mut read) = stream.split();
+
+        for message in self.protocol.on_connect() {
+            if let Err(err) = write.send(Message::Text(message)).await {
+                return Some(Closed::Failed(format!("error sending message: {err}")));
+            }
+        }
+        if !self.topics.is_empty() {
+            let request = self.protocol.subscribe(&self.topics);
+            if let Err(err) = write.send(Message::Text(request)).await {
+                return Some(Closed::Failed(format!(
+                    "error sending subscribe message: {err}"
+                )));
+            }
+        }
+        if !self.emit_state(ConnectionState::Connected) {
+            return None;
+        }
+
+        let mut ping = tokio::time::interval(self.config.ping_interval);
+        ping.tick().await;
+        let mut pong_deadline: Option<Instant> = None;
+
+        loop {
+            tokio::select! {
+                msg = read.next() => match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if self.protocol.is_pong(&text) {
+                            pong_deadline = None;
+                        } else if self.frames.send(WsFrame::Text(text)).is_err() {
+                            return None;
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        if let Err(err) = write.send(Message::Pong(data)).await {
+                            return Some(Closed::Failed(format!("error sending pong: {err}")));
+                        }
+                    }
+                    Some(Ok(Message::Pong(_))) => pong_deadline = None,
+                    Some(Ok(Message::Close(close))) => {
+                        return Some(Closed::Failed(format!("connection closed: {close:?}")));
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Some(Closed::Failed(format!("error receiving message: {err}"))),
+                    None => return Some(Closed::Failed("connection closed".to_string())),
+                },
+                _ = ping.tick() => {
+                    debug!("sending {} ws ping", self.protocol.name());
+                    if let Err(err) = write.send(self.protocol.ping()).await {
+                        return Some(Closed::Failed(format!("error sending ping: {err}")));
+                    }
+                    pong_deadline.get_or_insert(Instant::now() + self.config.pong_timeout);
+                }
+                _ = sleep_until(pong_deadline.unwrap_or_else(Instant::now)), if pong_deadline.is_some() => {
+                    return Some(Closed::Failed(format!(
+                        "no pong received within {:?}",
+                        self.config.pong_timeout
+                    )));
+                }
+                _ = sleep_until(expires_at.unwrap_or_else(Instant::now)), if expires_at.is_some() => {
+                    let _ = write.send(Message::Close(None)).await;
+                    return Some(Closed::Renewed("connection age limit reached".to_string()));
+                }
+                command = self.commands.recv() => {
+                    let message = match command {
+                        Some(WsCommand::Subscribe(topics)) => {
+                            let topics = self.add_topics(topics);
+                            if topics.is_empty() {
+                                continue;
+                            }
+                            self.protocol.subscribe(&topics)
+                        }
+                        Some(WsCommand::Unsubscribe(topics)) => {
+                            let topics = self.remove_topics(topics);
+                            if topics.is_empty() {
+                                continue;
+                            }
+                            self.protocol.unsubscribe(&topics)
+                        }
+                        Some(WsCommand::Send(text)) => text,
+                        Some(WsCommand::Reconnect(url)) => {
+                            self.config.url = url;
+                            let _ = write.send(Message::Close(None)).await;
+                            return Some(Closed::Renewed("reconnecting to a new url".to_string()));
+                        }
+                        None => return None,
+                    };
+                    if let Err(err) = write.send(Message::Text(message)).await {
+                        return Some(Closed::Failed(format!("error sending message: {err}")));
+                    }
+                }
+                _ = self.frames.closed() => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    use super::*;
+
+    struct TestProtocol;
+
+    impl WsProtocol for TestProtocol {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        fn subscribe(&mut self, topics: &[String]) -> String {
+            format!("subscribe {}", topics.join(","))
+        }
+
+        fn unsubscribe(&mut self, topics: &[String]) -> String {
+            format!("unsubscribe {}", topics.join(","))
+        }
+
+        fn ping(&self) -> Message {
+            Message::Ping(vec![])
+        }
+    }
+
+    fn test_config(url: String) -> SupervisorConfig {
+        SupervisorConfig {
+            url,
+            ping_interval: Duration::from_secs(60),
+            pong_timeout: Duration::from_secs(10),
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            max_connection_age: None,
+        }
+    }
+
+    async fn next_text(server: &mut WebSocketStream<TcpStream>) -> String {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return text;
+            }
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = test_config("".to_string());
+        assert_eq!(config.backoff(1), Duration::from_millis(10));
+        assert_eq!(config.backoff(3), Duration::from_millis(40));
+        assert_eq!(config.backoff(10), Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_topics_are_subscribed_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = test_config(format!("ws://{}", listener.local_addr().unwrap()));
+        let topics = vec!["a".to_string(), "a".to_string()];
+        let (mut frames, commands) = WsSupervisor::spawn(config, TestProtocol, topics);
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = accept_async(stream).await.unwrap();
+        assert_eq!(next_text(&mut server).await, "subscribe a");
+        assert!(matches!(
+            frames.recv().await,
+            Some(WsFrame::State(ConnectionState::Connected))
+        ));
+
+        let topics = vec!["a".to_string(), "b".to_string()];
+        commands.send(WsCommand::Subscribe(topics)).unwrap();
+        assert_eq!(next_text(&mut server).await, "subscribe b");
+        let topics = vec!["b".to_string(), "c".to_string()];
+        commands.send(WsCommand::Unsubscribe(topics)).unwrap();
+        assert_eq!(next_text(&mut server).await, "unsubscribe b");
+
+        // the remaining topics are subscribed again on reconnection
+        drop(server);
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = accept_async(stream).await.unwrap();
+        assert_eq!(next_text(&mut server).await, "subscribe a");
+    }
+}